@@ -59,6 +59,10 @@ fn build_ffi(flags: &[String]) {
 
 fn build_hdfs_lib(flags: &[String]) {
     println!("cargo:rerun-if-changed={}", get_hdfs_file_path("hdfs.c"));
+    println!(
+        "cargo:rerun-if-changed={}",
+        get_hdfs_file_path("exception.c")
+    );
 
     let mut builder = cc::Build::new();
 
@@ -110,15 +110,77 @@ void getExceptionInfo(const char *excName, int noPrintFlags,
     }
 }
 
+/**
+ * The root cause and the stack trace of the last exception of each thread,
+ * which are cleared when the next call of the thread gets its JNIEnv.
+ */
+static __thread char *gLastExceptionRootCause = NULL;
+static __thread char *gLastExceptionStackTrace = NULL;
+
+static void setLastException(char *rootCause, char *stackTrace)
+{
+    free(gLastExceptionRootCause);
+    free(gLastExceptionStackTrace);
+    gLastExceptionRootCause = rootCause;
+    gLastExceptionStackTrace = stackTrace;
+}
+
+void freeLastException(void)
+{
+    setLastException(NULL, NULL);
+}
+
+const char *hdfsGetLastExceptionRootCause(void)
+{
+    return gLastExceptionRootCause;
+}
+
+const char *hdfsGetLastExceptionStackTrace(void)
+{
+    return gLastExceptionStackTrace;
+}
+
+/**
+ * Call a static method of ExceptionUtils which describes the exception.
+ *
+ * @return          a copy of the description to be freed by the caller, or
+ *                  NULL on error.
+ */
+static char *getExceptionUtilString(JNIEnv *env, jthrowable exc,
+        const char *methodName)
+{
+    jthrowable jthr;
+    jvalue jVal;
+    jstring jStr;
+    const char *str;
+    char *copy = NULL;
+
+    // We don't want to  use ExceptionDescribe here, because that requires a
+    // pending exception.  Instead, use ExceptionUtils.
+    jthr = invokeMethod(env, &jVal, STATIC, NULL,
+        "org/apache/commons/lang/exception/ExceptionUtils",
+        methodName, "(Ljava/lang/Throwable;)Ljava/lang/String;", exc);
+    if (jthr) {
+        destroyLocalReference(env, jthr);
+        return NULL;
+    }
+    jStr = jVal.l;
+    str = (*env)->GetStringUTFChars(env, jStr, NULL);
+    if (str) {
+        copy = strdup(str);
+        (*env)->ReleaseStringUTFChars(env, jStr, str);
+    }
+    destroyLocalReference(env, jStr);
+    return copy;
+}
+
 int printExceptionAndFreeV(JNIEnv *env, jthrowable exc, int noPrintFlags,
         const char *fmt, va_list ap)
 {
     int i, noPrint, excErrno;
     char *className = NULL;
-    jstring jStr = NULL;
-    jvalue jVal;
     jthrowable jthr;
-    const char *stackTrace;
+    char *rootCause = NULL, *stackTrace = NULL;
 
     jthr = classNameOfObject(exc, env, &className);
     if (jthr) {
@@ -139,32 +201,26 @@ int printExceptionAndFreeV(JNIEnv *env, jthrowable exc, int noPrintFlags,
         noPrint = 0;
         excErrno = EINTERNAL;
     }
+
+    // Only described for an exception which is printed, or not translated into
+    // a specific errno, to save the JNI calls for the expected ones, e.g. the
+    // FileNotFoundException of a probe
+    if (!noPrint || excErrno == EINTERNAL) {
+        rootCause = getExceptionUtilString(env, exc, "getRootCauseMessage");
+        stackTrace = getExceptionUtilString(env, exc, "getStackTrace");
+    }
     if (!noPrint) {
         vfprintf(stderr, fmt, ap);
         fprintf(stderr, " error:\n");
-
-        // We don't want to  use ExceptionDescribe here, because that requires a
-        // pending exception.  Instead, use ExceptionUtils.
-        jthr = invokeMethod(env, &jVal, STATIC, NULL, 
-            "org/apache/commons/lang/exception/ExceptionUtils",
-            "getStackTrace", "(Ljava/lang/Throwable;)Ljava/lang/String;", exc);
-        if (jthr) {
+        if (!stackTrace) {
             fprintf(stderr, "(unable to get stack trace for %s exception: "
                     "ExceptionUtils::getStackTrace error.)\n", className);
-            destroyLocalReference(env, jthr);
         } else {
-            jStr = jVal.l;
-            stackTrace = (*env)->GetStringUTFChars(env, jStr, NULL);
-            if (!stackTrace) {
-                fprintf(stderr, "(unable to get stack trace for %s exception: "
-                        "GetStringUTFChars error.)\n", className);
-            } else {
-                fprintf(stderr, "%s", stackTrace);
-                (*env)->ReleaseStringUTFChars(env, jStr, stackTrace);
-            }
+            fprintf(stderr, "%s", stackTrace);
         }
     }
-    destroyLocalReference(env, jStr);
+    setLastException(rootCause, stackTrace);
+
     destroyLocalReference(env, exc);
     free(className);
     return excErrno;
@@ -162,6 +162,13 @@ int printPendingExceptionAndFree(JNIEnv *env, int noPrintFlags,
 LIBHDFS_EXTERNAL
 jthrowable getPendingExceptionAndClear(JNIEnv *env);
 
+/**
+ * Free the root cause and the stack trace of the last exception of the
+ * calling thread, which is called when the thread is detached from the JVM.
+ */
+LIBHDFS_EXTERNAL
+void freeLastException(void);
+
 /**
  * Create a new runtime error.
  *
@@ -38,6 +38,7 @@
 #define HADOOP_OSTRM    "org/apache/hadoop/fs/FSDataOutputStream"
 #define HADOOP_STAT     "org/apache/hadoop/fs/FileStatus"
 #define HADOOP_FSPERM   "org/apache/hadoop/fs/permission/FsPermission"
+#define HADOOP_RENAME   "org/apache/hadoop/fs/Options$Rename"
 #define JAVA_NET_ISA    "java/net/InetSocketAddress"
 #define JAVA_NET_URI    "java/net/URI"
 #define JAVA_STRING     "java/lang/String"
@@ -880,10 +881,6 @@ hdfsFile hdfsOpenFile(hdfsFS fs, const char *path, int flags,
       return NULL;
     }
 
-    if ((flags & O_CREAT) && (flags & O_EXCL)) {
-      fprintf(stderr, "WARN: hdfs does not truly support O_CREATE && O_EXCL\n");
-    }
-
     if (accmode == O_RDONLY) {
 	method = "open";
         signature = JMETHOD2(JPARAM(HADOOP_PATH), "I", JPARAM(HADOOP_ISTRM));
@@ -965,8 +962,8 @@ hdfsFile hdfsOpenFile(hdfsFS fs, const char *path, int flags,
        jthr = invokeMethod(env, &jVal, INSTANCE, jFS, HADOOP_FS,
                        method, signature, jPath);
     } else {
-        // WRITE/CREATE
-        jboolean jOverWrite = 1;
+        // WRITE/CREATE, which fails if the file exists with O_EXCL
+        jboolean jOverWrite = (flags & O_EXCL) ? JNI_FALSE : JNI_TRUE;
         jlong jBlockSize = blockSize;
 
         if (jBlockSize == 0) {
@@ -1848,6 +1845,164 @@ done:
     return ret;
 }
 
+int hdfsRenameOverwrite(hdfsFS fs, const char *oldPath, const char *newPath)
+{
+    // JAVA EQUIVALENT:
+    //  Path old = new Path(oldPath);
+    //  Path new = new Path(newPath);
+    //  fs.rename(old, new, Options.Rename.OVERWRITE);
+
+    jobject jFS = (jobject)fs;
+    jthrowable jthr;
+    jobject jOldPath = NULL, jNewPath = NULL, jOverwrite = NULL;
+    jobjectArray jOptions = NULL;
+    jclass jRenameClass;
+    int ret = -1;
+    jvalue jVal;
+
+    //Get the JNIEnv* corresponding to current thread
+    JNIEnv* env = getJNIEnv();
+    if (env == NULL) {
+      errno = EINTERNAL;
+      return -1;
+    }
+
+    jthr = constructNewObjectOfPath(env, oldPath, &jOldPath);
+    if (jthr) {
+        errno = printExceptionAndFree(env, jthr, PRINT_EXC_ALL,
+            "hdfsRenameOverwrite: constructNewObjectOfPath(%s)", oldPath);
+        goto done;
+    }
+    jthr = constructNewObjectOfPath(env, newPath, &jNewPath);
+    if (jthr) {
+        errno = printExceptionAndFree(env, jthr, PRINT_EXC_ALL,
+            "hdfsRenameOverwrite: constructNewObjectOfPath(%s)", newPath);
+        goto done;
+    }
+    jthr = fetchEnumInstance(env, HADOOP_RENAME, "OVERWRITE", &jOverwrite);
+    if (jthr) {
+        errno = printExceptionAndFree(env, jthr, PRINT_EXC_ALL,
+            "hdfsRenameOverwrite(oldPath=%s): fetchEnumInstance", oldPath);
+        goto done;
+    }
+    jthr = globalClassReference(HADOOP_RENAME, env, &jRenameClass);
+    if (jthr) {
+        errno = printExceptionAndFree(env, jthr, PRINT_EXC_ALL,
+            "hdfsRenameOverwrite(oldPath=%s): globalClassReference", oldPath);
+        goto done;
+    }
+    jOptions = (*env)->NewObjectArray(env, 1, jRenameClass, jOverwrite);
+    if (!jOptions) {
+        errno = printPendingExceptionAndFree(env, PRINT_EXC_ALL,
+            "hdfsRenameOverwrite(oldPath=%s): NewObjectArray", oldPath);
+        goto done;
+    }
+
+    // The rename with options is protected in FileSystem, which doesn't matter to JNI,
+    // and it's atomic in DistributedFileSystem
+    jthr = invokeMethod(env, &jVal, INSTANCE, jFS, HADOOP_FS, "rename",
+                     JMETHOD3(JPARAM(HADOOP_PATH), JPARAM(HADOOP_PATH),
+                              JARRPARAM(HADOOP_RENAME), JAVA_VOID),
+                     jOldPath, jNewPath, jOptions);
+    if (jthr) {
+        errno = printExceptionAndFree(env, jthr, PRINT_EXC_ALL,
+            "hdfsRenameOverwrite(oldPath=%s, newPath=%s): FileSystem#rename",
+            oldPath, newPath);
+        goto done;
+    }
+    ret = 0;
+
+done:
+    destroyLocalReference(env, jOldPath);
+    destroyLocalReference(env, jNewPath);
+    destroyLocalReference(env, jOverwrite);
+    destroyLocalReference(env, jOptions);
+    return ret;
+}
+
+int hdfsConcat(hdfsFS fs, const char *trg, const char **srcs)
+{
+    // JAVA EQUIVALENT:
+    //  Path trg = new Path(trg);
+    //  Path[] srcs = { new Path(srcs[0]), ... };
+    //  fs.concat(trg, srcs);
+
+    jobject jFS = (jobject)fs;
+    jthrowable jthr;
+    jobject jTrgPath = NULL, jSrcPath = NULL;
+    jobjectArray jSrcPaths = NULL;
+    jclass jPathClass;
+    jsize numSrcs = 0, i;
+    int ret = -1;
+    jvalue jVal;
+
+    //Get the JNIEnv* corresponding to current thread
+    JNIEnv* env = getJNIEnv();
+    if (env == NULL) {
+      errno = EINTERNAL;
+      return -1;
+    }
+
+    if (!trg || !srcs) {
+        errno = EINVAL;
+        return -1;
+    }
+    while (srcs[numSrcs]) {
+        numSrcs++;
+    }
+
+    jthr = constructNewObjectOfPath(env, trg, &jTrgPath);
+    if (jthr) {
+        errno = printExceptionAndFree(env, jthr, PRINT_EXC_ALL,
+            "hdfsConcat: constructNewObjectOfPath(%s)", trg);
+        goto done;
+    }
+    jthr = globalClassReference(HADOOP_PATH, env, &jPathClass);
+    if (jthr) {
+        errno = printExceptionAndFree(env, jthr, PRINT_EXC_ALL,
+            "hdfsConcat(trg=%s): globalClassReference", trg);
+        goto done;
+    }
+    jSrcPaths = (*env)->NewObjectArray(env, numSrcs, jPathClass, NULL);
+    if (!jSrcPaths) {
+        errno = printPendingExceptionAndFree(env, PRINT_EXC_ALL,
+            "hdfsConcat(trg=%s): NewObjectArray", trg);
+        goto done;
+    }
+    for (i = 0; i < numSrcs; i++) {
+        jthr = constructNewObjectOfPath(env, srcs[i], &jSrcPath);
+        if (jthr) {
+            errno = printExceptionAndFree(env, jthr, PRINT_EXC_ALL,
+                "hdfsConcat: constructNewObjectOfPath(%s)", srcs[i]);
+            goto done;
+        }
+        (*env)->SetObjectArrayElement(env, jSrcPaths, i, jSrcPath);
+        destroyLocalReference(env, jSrcPath);
+        jSrcPath = NULL;
+        if ((*env)->ExceptionCheck(env)) {
+            errno = printPendingExceptionAndFree(env, PRINT_EXC_ALL,
+                "hdfsConcat(trg=%s): SetObjectArrayElement", trg);
+            goto done;
+        }
+    }
+
+    jthr = invokeMethod(env, &jVal, INSTANCE, jFS, HADOOP_FS, "concat",
+                     JMETHOD2(JPARAM(HADOOP_PATH), JARRPARAM(HADOOP_PATH),
+                              JAVA_VOID),
+                     jTrgPath, jSrcPaths);
+    if (jthr) {
+        errno = printExceptionAndFree(env, jthr, PRINT_EXC_ALL,
+            "hdfsConcat(trg=%s): FileSystem#concat", trg);
+        goto done;
+    }
+    ret = 0;
+
+done:
+    destroyLocalReference(env, jTrgPath);
+    destroyLocalReference(env, jSrcPaths);
+    return ret;
+}
+
 
 
 char* hdfsGetWorkingDirectory(hdfsFS fs, char* buffer, size_t bufferSize)
@@ -1927,6 +2082,96 @@ done:
     return buffer;
 }
 
+int hdfsGetLinkTarget(hdfsFS fs, const char *path, char *buffer, size_t bufferSize)
+{
+    // JAVA EQUIVALENT:
+    //  FileStatus status = fs.getFileLinkStatus(new Path(path));
+    //  if (status.isSymlink()) return status.getSymlink().toString();
+
+    jobject jFS = (jobject)fs;
+    jobject jPath = NULL, jStat = NULL, jTarget = NULL;
+    jstring jTargetString = NULL;
+    const char *jTargetChars = NULL;
+    jthrowable jthr;
+    jvalue jVal;
+    int ret = -1;
+
+    //Get the JNIEnv* corresponding to current thread
+    JNIEnv* env = getJNIEnv();
+    if (env == NULL) {
+      errno = EINTERNAL;
+      return -1;
+    }
+
+    jthr = constructNewObjectOfPath(env, path, &jPath);
+    if (jthr) {
+        errno = printExceptionAndFree(env, jthr, PRINT_EXC_ALL,
+            "hdfsGetLinkTarget: constructNewObjectOfPath(%s)", path);
+        goto done;
+    }
+    // The status of the link itself rather than its target
+    jthr = invokeMethod(env, &jVal, INSTANCE, jFS, HADOOP_FS,
+                     "getFileLinkStatus",
+                     JMETHOD1(JPARAM(HADOOP_PATH), JPARAM(HADOOP_STAT)), jPath);
+    if (jthr) {
+        errno = printExceptionAndFree(env, jthr, NOPRINT_EXC_FILE_NOT_FOUND,
+            "hdfsGetLinkTarget(%s): FileSystem#getFileLinkStatus", path);
+        goto done;
+    }
+    jStat = jVal.l;
+    jthr = invokeMethod(env, &jVal, INSTANCE, jStat, HADOOP_STAT,
+                     "isSymlink", "()Z");
+    if (jthr) {
+        errno = printExceptionAndFree(env, jthr, PRINT_EXC_ALL,
+            "hdfsGetLinkTarget(%s): FileStatus#isSymlink", path);
+        goto done;
+    }
+    if (!jVal.z) {
+        ret = 0;
+        goto done;
+    }
+
+    jthr = invokeMethod(env, &jVal, INSTANCE, jStat, HADOOP_STAT,
+                     "getSymlink", JMETHOD1("", JPARAM(HADOOP_PATH)));
+    if (jthr) {
+        errno = printExceptionAndFree(env, jthr, PRINT_EXC_ALL,
+            "hdfsGetLinkTarget(%s): FileStatus#getSymlink", path);
+        goto done;
+    }
+    jTarget = jVal.l;
+    jthr = invokeMethod(env, &jVal, INSTANCE, jTarget, HADOOP_PATH,
+                     "toString", "()Ljava/lang/String;");
+    if (jthr) {
+        errno = printExceptionAndFree(env, jthr, PRINT_EXC_ALL,
+            "hdfsGetLinkTarget(%s): Path#toString", path);
+        goto done;
+    }
+    jTargetString = jVal.l;
+    jTargetChars = (*env)->GetStringUTFChars(env, jTargetString, NULL);
+    if (!jTargetChars) {
+        errno = printPendingExceptionAndFree(env, PRINT_EXC_ALL,
+            "hdfsGetLinkTarget(%s): GetStringUTFChars", path);
+        goto done;
+    }
+
+    //Copy to user-provided buffer
+    if (snprintf(buffer, bufferSize, "%s", jTargetChars) >= bufferSize) {
+        errno = ENAMETOOLONG;
+        goto done;
+    }
+    ret = 1;
+
+done:
+    if (jTargetChars) {
+        (*env)->ReleaseStringUTFChars(env, jTargetString, jTargetChars);
+    }
+    destroyLocalReference(env, jPath);
+    destroyLocalReference(env, jStat);
+    destroyLocalReference(env, jTarget);
+    destroyLocalReference(env, jTargetString);
+    return ret;
+}
+
 
 
 int hdfsSetWorkingDirectory(hdfsFS fs, const char *path)
@@ -117,6 +117,26 @@ extern  "C" {
     LIBHDFS_EXTERNAL
     int hdfsFileIsOpenForWrite(hdfsFile file);
 
+    /**
+     * Determine if a file uses the direct read, which reads into the native buffer
+     * via ByteBuffer without an extra copy. It's detected when the file is opened
+     * for read.
+     *
+     * @param file     The HDFS file
+     * @return         1 if the file uses the direct read; 0 otherwise
+     */
+    LIBHDFS_EXTERNAL
+    int hdfsFileUsesDirectRead(hdfsFile file);
+
+    /**
+     * Disable the direct read of a file, so that the data is copied from
+     * a java byte array instead.
+     *
+     * @param file     The HDFS file
+     */
+    LIBHDFS_EXTERNAL
+    void hdfsFileDisableDirectRead(hdfsFile file);
+
     struct hdfsReadStatistics {
       uint64_t totalBytesRead;
       uint64_t totalLocalBytesRead;
@@ -382,7 +402,8 @@ extern  "C" {
      * @param fs The configured filesystem handle.
      * @param path The full path to the file.
      * @param flags - an | of bits/fcntl.h file flags - supported flags are O_RDONLY, O_WRONLY (meaning create or overwrite i.e., implies O_TRUNCAT),
-     * O_WRONLY|O_APPEND. Other flags are generally ignored other than (O_RDWR || (O_EXCL & O_CREAT)) which return NULL and set errno equal ENOTSUP.
+     * O_WRONLY|O_EXCL (meaning create but fail with EEXIST if the file exists) and
+     * O_WRONLY|O_APPEND. Other flags are generally ignored other than O_RDWR which returns NULL and sets errno equal ENOTSUP.
      * @param bufferSize Size of buffer for read/write - pass 0 if you want
      * to use the default configured values.
      * @param replication Block replication - pass 0 if you want to use
@@ -601,6 +622,30 @@ extern  "C" {
     LIBHDFS_EXTERNAL
     int hdfsRename(hdfsFS fs, const char* oldPath, const char* newPath);
 
+    /**
+     * hdfsRenameOverwrite - Rename file, replacing the destination atomically
+     * if it exists, which must not be a non-empty directory.
+     * @param fs The configured filesystem handle.
+     * @param oldPath The path of the source file.
+     * @param newPath The path of the destination file.
+     * @return Returns 0 on success, -1 on error.
+     */
+    LIBHDFS_EXTERNAL
+    int hdfsRenameOverwrite(hdfsFS fs, const char* oldPath, const char* newPath);
+
+    /**
+     * hdfsConcat - Concatenate existing files into the target file.
+     * The source files are removed after the concatenation.
+     * @param fs The configured filesystem handle.
+     * @param trg The path of the target file, which must exist.
+     * @param srcs A NULL-terminated array of the source file paths.
+     * All of the files except the last one of the target and sources
+     * must consist of full blocks of the same block size.
+     * @return Returns 0 on success, -1 on error.
+     */
+    LIBHDFS_EXTERNAL
+    int hdfsConcat(hdfsFS fs, const char* trg, const char** srcs);
+
 
     /**
      * hdfsGetWorkingDirectory - Get the current working directory for
@@ -614,6 +659,22 @@ extern  "C" {
     char* hdfsGetWorkingDirectory(hdfsFS fs, char *buffer, size_t bufferSize);
 
 
+    /**
+     * hdfsGetLinkTarget - Get the target of a symbolic link.
+     * @param fs The configured filesystem handle.
+     * @param path The path of the symbolic link, whose last component
+     * is not resolved.
+     * @param buffer The user-buffer to copy the target into, which is
+     * fully qualified by HDFS.
+     * @param bufferSize The length of user-buffer.
+     * @return Returns 1 if the path is a symbolic link, 0 if it's not,
+     * -1 on error, e.g. ENAMETOOLONG if the target doesn't fit the buffer.
+     */
+    LIBHDFS_EXTERNAL
+    int hdfsGetLinkTarget(hdfsFS fs, const char *path, char *buffer,
+                          size_t bufferSize);
+
+
     /**
      * hdfsSetWorkingDirectory - Set the working directory. All relative
      * paths will be resolved relative to it.
@@ -925,6 +986,33 @@ extern  "C" {
     LIBHDFS_EXTERNAL
     void hadoopRzBufferFree(hdfsFile file, struct hadoopRzBuffer *buffer);
 
+    /**
+     * Get the root cause message of the exception thrown by java in the last
+     * call of the calling thread, e.g. "StandbyException: ...".
+     *
+     * The string is owned by libhdfs and only valid until the next call of
+     * the thread, so it should be copied rather than freed by the caller.
+     *
+     * @return           the root cause message, or NULL if the last call threw
+     *                   no exception, or only one translated into a specific
+     *                   errno and not printed.
+     */
+    LIBHDFS_EXTERNAL
+    const char *hdfsGetLastExceptionRootCause(void);
+
+    /**
+     * Get the stack trace of the exception thrown by java in the last call of
+     * the calling thread.
+     *
+     * The string is owned by libhdfs and only valid until the next call of
+     * the thread, so it should be copied rather than freed by the caller.
+     *
+     * @return           the stack trace, or NULL in the same cases as
+     *                   hdfsGetLastExceptionRootCause.
+     */
+    LIBHDFS_EXTERNAL
+    const char *hdfsGetLastExceptionStackTrace(void);
+
 #ifdef __cplusplus
 }
 #endif
@@ -499,6 +499,8 @@ static JNIEnv* getGlobalJNIEnv(void)
 JNIEnv* getJNIEnv(void)
 {
     JNIEnv *env;
+    // Each call starts without the exception of the previous one
+    freeLastException();
     THREAD_LOCAL_STORAGE_GET_QUICK();
     mutexLock(&jvmMutex);
     if (threadLocalStorageGet(&env)) {
@@ -18,6 +18,8 @@
 
 #include "os/thread_local_storage.h"
 
+#include "exception.h"
+
 #include <jni.h>
 #include <pthread.h>
 #include <stdio.h>
@@ -40,6 +42,7 @@ static void hdfsThreadDestructor(void *v)
   JNIEnv *env = v;
   jint ret;
 
+  freeLastException();
   ret = (*env)->GetJavaVM(env, &vm);
   if (ret) {
     fprintf(stderr, "hdfsThreadDestructor: GetJavaVM failed with error %d\n",
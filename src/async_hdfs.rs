@@ -0,0 +1,325 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Async APIs of HdfsFs, enabled by the ``async`` feature
+//!
+//! Each operation runs the blocking libhdfs calls on the blocking thread pool of tokio,
+//! so it must be called within a tokio runtime. It doesn't make libhdfs itself
+//! nonblocking, i.e. every call still occupies a thread of the pool until it returns,
+//! but it keeps the threads of the async runtime unblocked.
+
+use std::panic;
+
+use bytes::Bytes;
+use futures::stream::{self, Stream};
+use tokio::runtime::Handle;
+use tokio::task;
+
+use crate::err::HdfsErr;
+use crate::hdfs::{FileKind, FileStatus, HdfsFile, HdfsFs};
+
+/// Async wrapper of HdfsFs, which is cheap to clone as HdfsFs is.
+#[derive(Clone, Debug)]
+pub struct AsyncHdfsFs {
+    fs: HdfsFs,
+}
+
+impl AsyncHdfsFs {
+    pub fn new(fs: HdfsFs) -> AsyncHdfsFs {
+        AsyncHdfsFs { fs }
+    }
+
+    /// Get the underlying HdfsFs
+    #[inline]
+    pub fn fs(&self) -> &HdfsFs {
+        &self.fs
+    }
+
+    /// Read the whole file, see ``HdfsFs::read_to_vec``
+    pub async fn read_to_vec(&self, path: &str) -> Result<Vec<u8>, HdfsErr> {
+        let path = path.to_owned();
+        self.spawn(move |fs| fs.read_to_vec(&path)).await
+    }
+
+    /// Write the whole data into a file, see ``HdfsFs::write_all``.
+    ///
+    /// The data is owned since it's moved to the blocking thread pool.
+    pub async fn write_all(
+        &self,
+        path: &str,
+        data: Vec<u8>,
+        overwrite: bool,
+    ) -> Result<(), HdfsErr> {
+        let path = path.to_owned();
+        self.spawn(move |fs| fs.write_all(&path, &data, overwrite))
+            .await
+    }
+
+    /// Get the file status
+    pub async fn get_file_status(&self, path: &str) -> Result<FileStatus, HdfsErr> {
+        let path = path.to_owned();
+        self.spawn(move |fs| fs.get_file_status(&path)).await
+    }
+
+    /// Get the file status for each entry under the specified directory
+    pub async fn list_status(&self, path: &str) -> Result<Vec<FileStatus>, HdfsErr> {
+        let path = path.to_owned();
+        self.spawn(move |fs| fs.list_status(&path)).await
+    }
+
+    /// Get the kind of the path, or ``None`` if it doesn't exist
+    pub async fn path_kind(&self, path: &str) -> Result<Option<FileKind>, HdfsErr> {
+        let path = path.to_owned();
+        self.spawn(move |fs| fs.path_kind(&path)).await
+    }
+
+    /// Create a directory and all of its missing parents
+    pub async fn mkdir(&self, path: &str) -> Result<bool, HdfsErr> {
+        let path = path.to_owned();
+        self.spawn(move |fs| fs.mkdir(&path)).await
+    }
+
+    /// Rename a file or a directory
+    pub async fn rename(&self, old_path: &str, new_path: &str) -> Result<bool, HdfsErr> {
+        let (old_path, new_path) = (old_path.to_owned(), new_path.to_owned());
+        self.spawn(move |fs| fs.rename(&old_path, &new_path)).await
+    }
+
+    /// Delete a file or a directory, see ``HdfsFs::delete``
+    pub async fn delete(&self, path: &str, recursive: bool) -> Result<bool, HdfsErr> {
+        let path = path.to_owned();
+        self.spawn(move |fs| fs.delete(&path, recursive)).await
+    }
+
+    /// Read a file as a stream of chunks until the end of the file.
+    ///
+    /// Each chunk is read on the blocking thread pool only when the stream is polled
+    /// for it, so it's naturally back-pressured. Every chunk has ``chunk_size`` bytes
+    /// except the last one, and the block size is a good choice of it for big files.
+    /// The file is closed at the end of the file, after an error, or when the stream
+    /// is dropped.
+    pub fn read_stream(
+        &self,
+        path: &str,
+        chunk_size: usize,
+    ) -> impl Stream<Item = Result<Bytes, HdfsErr>> + Send + 'static {
+        let state = if chunk_size == 0 {
+            ReadState::Failed(HdfsErr::InvalidArgument(format!(
+                "zero chunk size to read {}",
+                path
+            )))
+        } else {
+            ReadState::Unopened(self.fs.clone(), path.to_owned())
+        };
+
+        stream::unfold(state, move |state| next_chunk(state, chunk_size))
+    }
+
+    /// Run the blocking operation on the blocking thread pool
+    async fn spawn<T, F>(&self, op: F) -> Result<T, HdfsErr>
+    where
+        F: FnOnce(HdfsFs) -> Result<T, HdfsErr> + Send + 'static,
+        T: Send + 'static,
+    {
+        let fs = self.fs.clone();
+        run_blocking(move || op(fs)).await
+    }
+}
+
+async fn run_blocking<T, F>(op: F) -> Result<T, HdfsErr>
+where
+    F: FnOnce() -> Result<T, HdfsErr> + Send + 'static,
+    T: Send + 'static,
+{
+    match task::spawn_blocking(op).await {
+        Ok(result) => result,
+        Err(e) if e.is_panic() => panic::resume_unwind(e.into_panic()),
+        // A blocking task is only cancelled when the runtime is shutting down
+        Err(_) => Err(HdfsErr::Unknown),
+    }
+}
+
+enum ReadState {
+    Unopened(HdfsFs, String),
+    Reading(StreamFile),
+    Failed(HdfsErr),
+    Done,
+}
+
+async fn next_chunk(
+    state: ReadState,
+    chunk_size: usize,
+) -> Option<(Result<Bytes, HdfsErr>, ReadState)> {
+    let file = match state {
+        ReadState::Unopened(fs, path) => match run_blocking(move || fs.open(&path)).await
+        {
+            Ok(file) => StreamFile(Some(file)),
+            Err(e) => return Some((Err(e), ReadState::Done)),
+        },
+        ReadState::Reading(file) => file,
+        ReadState::Failed(e) => return Some((Err(e), ReadState::Done)),
+        ReadState::Done => return None,
+    };
+
+    let read = run_blocking(move || {
+        let mut file = file;
+        let mut buf = vec![0u8; chunk_size];
+        let mut read = 0;
+        let inner = file.0.as_mut().unwrap();
+        while read < chunk_size {
+            match inner.read(&mut buf[read..])? {
+                0 => break,
+                len => read += len as usize,
+            }
+        }
+        buf.truncate(read);
+
+        if read == 0 {
+            file.0.take().unwrap().close()?;
+        }
+        Ok((file, buf))
+    })
+    .await;
+
+    match read {
+        Ok((_, buf)) if buf.is_empty() => None,
+        Ok((file, buf)) => Some((Ok(Bytes::from(buf)), ReadState::Reading(file))),
+        Err(e) => Some((Err(e), ReadState::Done)),
+    }
+}
+
+/// The file read by a stream, which is closed on the blocking thread pool
+/// if the stream is dropped before the end of the file
+struct StreamFile(Option<HdfsFile>);
+
+impl Drop for StreamFile {
+    fn drop(&mut self) {
+        if let Some(file) = self.0.take() {
+            match Handle::try_current() {
+                Ok(handle) => {
+                    handle.spawn_blocking(move || drop(file));
+                }
+                Err(_) => drop(file),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Write;
+
+    use futures::StreamExt;
+    use tokio::runtime::Runtime;
+    use uuid::Uuid;
+
+    use super::AsyncHdfsFs;
+    use crate::hdfs::FileKind;
+    use crate::minidfs::get_dfs;
+
+    #[test]
+    fn test_concurrent_read() {
+        let dfs = get_dfs();
+        {
+            let fs = AsyncHdfsFs::new(dfs.get_hdfs().ok().unwrap().as_ref().clone());
+            let test_dir = format!("/{}", Uuid::new_v4());
+
+            let runtime = Runtime::new().unwrap();
+            runtime.block_on(async {
+                assert!(fs.mkdir(&test_dir).await.is_ok());
+                assert_eq!(
+                    Some(FileKind::Directory),
+                    fs.path_kind(&test_dir).await.ok().unwrap()
+                );
+
+                let files: Vec<(String, Vec<u8>)> = (0..8)
+                    .map(|idx| {
+                        let data = (0..(idx + 1) * 10 * 1024)
+                            .map(|i| (i % (idx + 200)) as u8)
+                            .collect();
+                        (format!("{}/f{}", test_dir, idx), data)
+                    })
+                    .collect();
+                for (path, data) in files.iter() {
+                    assert!(fs.write_all(path, data.clone(), false).await.is_ok());
+                }
+                assert_eq!(
+                    files.len(),
+                    fs.list_status(&test_dir).await.ok().unwrap().len()
+                );
+
+                let handles: Vec<_> = files
+                    .iter()
+                    .map(|(path, _)| {
+                        let fs = fs.clone();
+                        let path = path.clone();
+                        tokio::spawn(async move { fs.read_to_vec(&path).await })
+                    })
+                    .collect();
+                for (handle, (_, data)) in handles.into_iter().zip(files.iter()) {
+                    assert_eq!(data, &handle.await.unwrap().ok().unwrap());
+                }
+
+                // Clean up
+                assert!(fs.delete(&test_dir, true).await.ok().unwrap());
+            });
+        }
+    }
+
+    #[test]
+    fn test_read_stream() {
+        let dfs = get_dfs();
+        {
+            let fs = AsyncHdfsFs::new(dfs.get_hdfs().ok().unwrap().as_ref().clone());
+            let test_file = format!("/{}", Uuid::new_v4());
+
+            let data: Vec<u8> = (0..(300 * 1024 + 7)).map(|i| (i % 233) as u8).collect();
+            let mut file = fs.fs().create(&test_file).ok().unwrap();
+            file.write_all(&data).unwrap();
+            assert!(file.close().is_ok());
+
+            let runtime = Runtime::new().unwrap();
+            runtime.block_on(async {
+                let read = fs
+                    .read_stream(&test_file, 64 * 1024)
+                    .fold(Vec::new(), |mut read, chunk| async move {
+                        let chunk = chunk.ok().unwrap();
+                        assert!(!chunk.is_empty() && chunk.len() <= 64 * 1024);
+                        read.extend_from_slice(&chunk);
+                        read
+                    })
+                    .await;
+                assert_eq!(data, read);
+
+                // Drop the stream early
+                let mut stream = Box::pin(fs.read_stream(&test_file, 1024));
+                let chunk = stream.next().await.unwrap().ok().unwrap();
+                assert_eq!(&data[..1024], &chunk[..]);
+                drop(stream);
+
+                // Missing file
+                let missing = format!("/{}", Uuid::new_v4());
+                let mut stream = Box::pin(fs.read_stream(&missing, 1024));
+                assert!(stream.next().await.unwrap().is_err());
+                assert!(stream.next().await.is_none());
+
+                // Clean up
+                assert!(fs.delete(&test_file, false).await.ok().unwrap());
+            });
+        }
+    }
+}
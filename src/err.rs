@@ -15,6 +15,18 @@
 // specific language governing permissions and limitations
 // under the License.
 
+use std::error::Error;
+use std::ffi::CStr;
+use std::fmt::{Display, Formatter};
+use std::io;
+
+use log::debug;
+
+use crate::native::{hdfsGetLastExceptionRootCause, hdfsGetLastExceptionStackTrace};
+
+/// The errno set by libhdfs for a java exception without a dedicated errno
+const EINTERNAL: i32 = 255;
+
 /// Errors which can occur during accessing Hdfs cluster
 #[derive(Debug)]
 pub enum HdfsErr {
@@ -27,4 +39,227 @@ pub enum HdfsErr {
     CannotConnectToNameNode(String),
     /// URL
     InvalidUrl(String),
+    /// file path
+    PermissionDenied(String),
+    /// errno set by libhdfs which has no dedicated variant
+    Io(i32),
+    /// reason why the argument is invalid
+    InvalidArgument(String),
+    /// name node address and the number of other references to the connection
+    ConnectionInUse(String, usize),
+    /// reason why the operation is unsupported
+    Unsupported(String),
+    /// reason why the operation is not allowed by the mode the file is opened with
+    InvalidHandleMode(String),
+    /// directory path
+    DirectoryNotEmpty(String),
+    /// directory path which is used as a file
+    IsDirectory(String),
+    /// file path which can't be passed to libhdfs, e.g. containing a NUL byte
+    InvalidPath(String),
+    /// reason why the data read is invalid, e.g. a line which is not UTF-8
+    InvalidData(String),
+    /// error of the writer which the data read is copied into
+    Sink(io::Error),
+    /// error of the reader which the data written is copied from
+    Source(io::Error),
+    /// reason why the operation didn't complete in time
+    Timeout(String),
+    /// file path, the expected length and the actual one
+    LengthMismatch(String, i64, i64),
+    /// path whose symbolic links form a loop, or are too many to follow
+    SymlinkLoop(String),
+    /// root cause of the java exception thrown by HDFS, e.g. "StandbyException: ..."
+    Exception {
+        message: String,
+    },
+}
+
+impl HdfsErr {
+    /// Create an error from the errno set by a failed libhdfs call on the given path.
+    ///
+    /// libhdfs translates the java exceptions into errno, e.g. ``FileNotFoundException``
+    /// into ``ENOENT``. So it should be called right after the native call.
+    pub(crate) fn from_errno(path: &str) -> HdfsErr {
+        let err = match io::Error::last_os_error().raw_os_error() {
+            Some(libc::ENOENT) => HdfsErr::FileNotFound(path.to_owned()),
+            Some(libc::EACCES) => HdfsErr::PermissionDenied(path.to_owned()),
+            Some(libc::EEXIST) => HdfsErr::FileAlreadyExists(path.to_owned()),
+            Some(0) | None => HdfsErr::Unknown,
+            Some(EINTERNAL) => match last_exception_root_cause() {
+                Some(message) => HdfsErr::Exception { message },
+                None => HdfsErr::Io(EINTERNAL),
+            },
+            Some(errno) => HdfsErr::Io(errno),
+        };
+
+        // Logged at debug only, since the caller may handle it, e.g. ``path_kind``
+        #[cfg(feature = "ffi_log")]
+        debug!("Native call on {} failed: {:?}", path, err);
+        err
+    }
+
+    /// Log the error of a native call on the path at error with ``ffi_log``, where it's
+    /// returned to the caller rather than handled.
+    #[inline]
+    pub(crate) fn logged(self, path: &str) -> HdfsErr {
+        #[cfg(feature = "ffi_log")]
+        log::error!("Native call on {} failed: {:?}", path, self);
+        #[cfg(not(feature = "ffi_log"))]
+        let _ = path;
+        self
+    }
+}
+
+impl Display for HdfsErr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HdfsErr::Unknown => write!(f, "unknown error of libhdfs"),
+            HdfsErr::FileNotFound(path) => write!(f, "file not found: {}", path),
+            HdfsErr::FileAlreadyExists(path) => {
+                write!(f, "file already exists: {}", path)
+            }
+            HdfsErr::CannotConnectToNameNode(namenode) => {
+                write!(f, "cannot connect to the name node {}", namenode)
+            }
+            HdfsErr::InvalidUrl(url) => write!(f, "invalid url: {}", url),
+            HdfsErr::PermissionDenied(path) => write!(f, "permission denied: {}", path),
+            HdfsErr::Io(errno) => write!(
+                f,
+                "libhdfs failed with errno {}: {}",
+                errno,
+                io::Error::from_raw_os_error(*errno)
+            ),
+            HdfsErr::InvalidArgument(reason) => write!(f, "invalid argument: {}", reason),
+            HdfsErr::ConnectionInUse(namenode, refs) => write!(
+                f,
+                "connection to {} is still used by {} other references",
+                namenode, refs
+            ),
+            HdfsErr::Unsupported(reason) => write!(f, "unsupported: {}", reason),
+            HdfsErr::InvalidHandleMode(reason) => {
+                write!(f, "invalid file handle mode: {}", reason)
+            }
+            HdfsErr::DirectoryNotEmpty(path) => {
+                write!(f, "directory not empty: {}", path)
+            }
+            HdfsErr::IsDirectory(path) => write!(f, "is a directory: {}", path),
+            HdfsErr::InvalidPath(path) => write!(f, "invalid path: {}", path),
+            HdfsErr::InvalidData(reason) => write!(f, "invalid data: {}", reason),
+            HdfsErr::Sink(e) => write!(f, "failed to write into the sink: {}", e),
+            HdfsErr::Source(e) => write!(f, "failed to read from the source: {}", e),
+            HdfsErr::Timeout(reason) => write!(f, "timed out: {}", reason),
+            HdfsErr::LengthMismatch(path, expected, actual) => write!(
+                f,
+                "length of {} is {} rather than the expected {}",
+                path, actual, expected
+            ),
+            HdfsErr::SymlinkLoop(path) => {
+                write!(f, "too many levels of symbolic links: {}", path)
+            }
+            HdfsErr::Exception { message } => write!(f, "java exception: {}", message),
+        }
+    }
+}
+
+impl Error for HdfsErr {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            HdfsErr::Sink(e) | HdfsErr::Source(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// An ``io::Error`` is taken as the error of the sink, e.g. the writer which the data
+/// read is copied into. The error of a source is converted explicitly by ``Source``.
+impl From<io::Error> for HdfsErr {
+    fn from(e: io::Error) -> Self {
+        HdfsErr::Sink(e)
+    }
+}
+
+/// Get the root cause of the last java exception in the current thread,
+/// and log its stack trace for debugging.
+fn last_exception_root_cause() -> Option<String> {
+    // The strings are owned by libhdfs, so they're copied rather than freed
+    let root_cause = copy_c_str(unsafe { hdfsGetLastExceptionRootCause() })?;
+    if let Some(stack_trace) = copy_c_str(unsafe { hdfsGetLastExceptionStackTrace() }) {
+        debug!("{}", stack_trace);
+    }
+    Some(root_cause)
+}
+
+fn copy_c_str(ptr: *const std::os::raw::c_char) -> Option<String> {
+    if ptr.is_null() {
+        None
+    } else {
+        Some(
+            unsafe { CStr::from_ptr(ptr) }
+                .to_string_lossy()
+                .into_owned(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::error::Error;
+    use std::io;
+
+    use super::HdfsErr;
+
+    #[test]
+    fn test_display() {
+        let errs = vec![
+            HdfsErr::Unknown,
+            HdfsErr::FileNotFound("/a".to_owned()),
+            HdfsErr::FileAlreadyExists("/a".to_owned()),
+            HdfsErr::CannotConnectToNameNode("hdfs://localhost:9000".to_owned()),
+            HdfsErr::InvalidUrl("hdfs//a".to_owned()),
+            HdfsErr::PermissionDenied("/a".to_owned()),
+            HdfsErr::Io(libc::EIO),
+            HdfsErr::InvalidArgument("zero buffer size".to_owned()),
+            HdfsErr::ConnectionInUse("hdfs://localhost:9000".to_owned(), 2),
+            HdfsErr::Unsupported("truncate".to_owned()),
+            HdfsErr::InvalidHandleMode("write a file opened for read".to_owned()),
+            HdfsErr::DirectoryNotEmpty("/a".to_owned()),
+            HdfsErr::IsDirectory("/a".to_owned()),
+            HdfsErr::InvalidPath("/a\0".to_owned()),
+            HdfsErr::InvalidData("line 1 is not UTF-8".to_owned()),
+            HdfsErr::Sink(io::Error::other("broken pipe")),
+            HdfsErr::Source(io::Error::other("broken pipe")),
+            HdfsErr::Timeout("/a doesn't grow".to_owned()),
+            HdfsErr::LengthMismatch("/a".to_owned(), 10, 5),
+            HdfsErr::SymlinkLoop("/a".to_owned()),
+            HdfsErr::Exception {
+                message: "StandbyException: Operation category READ is not supported in state standby".to_owned(),
+            },
+        ];
+        for err in errs.iter() {
+            assert!(!err.to_string().is_empty());
+        }
+        assert_eq!("file not found: /a", errs[1].to_string());
+        assert!(errs[15].source().is_some());
+        assert!(errs[1].source().is_none());
+    }
+
+    #[test]
+    fn test_question_mark() {
+        fn hdfs_err() -> Result<(), Box<dyn Error>> {
+            Err(HdfsErr::FileNotFound("/a".to_owned()))?;
+            Ok(())
+        }
+        fn io_err() -> Result<(), HdfsErr> {
+            Err(io::Error::other("broken pipe"))?;
+            Ok(())
+        }
+
+        let err = hdfs_err().unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<HdfsErr>(),
+            Some(HdfsErr::FileNotFound(_))
+        ));
+        assert!(matches!(io_err(), Err(HdfsErr::Sink(_))));
+    }
 }
@@ -0,0 +1,90 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! An abstraction of the common operations of a filesystem, implemented by HdfsFs
+//!
+//! The logic written against ``&dyn FileSystem`` can be tested without a cluster by
+//! ``MockFs``, which keeps the files in memory.
+
+use std::io::{Read, Write};
+
+use crate::err::HdfsErr;
+use crate::hdfs::{FileStatus, HdfsFs};
+
+/// The common operations of a filesystem, following the semantics of HDFS
+pub trait FileSystem: Send + Sync {
+    /// Open a file for read
+    fn open(&self, path: &str) -> Result<Box<dyn Read + Send>, HdfsErr>;
+
+    /// Create a new file with its missing parents, which fails with ``FileAlreadyExists``
+    /// if the path exists. The file is closed when the writer is dropped, so it should
+    /// be flushed to get the errors of the write.
+    fn create(&self, path: &str) -> Result<Box<dyn Write + Send>, HdfsErr>;
+
+    /// Checks if a given path exists
+    fn exist(&self, path: &str) -> bool;
+
+    /// Delete a file or a directory, and return whether anything was deleted
+    fn delete(&self, path: &str, recursive: bool) -> Result<bool, HdfsErr>;
+
+    /// Get the file status for each entry under the specified directory
+    fn list_status(&self, path: &str) -> Result<Vec<FileStatus>, HdfsErr>;
+
+    /// Get the file status
+    fn get_file_status(&self, path: &str) -> Result<FileStatus, HdfsErr>;
+
+    /// Rename a file or a directory, which is moved into ``new_path`` if it's an existing
+    /// directory
+    fn rename(&self, old_path: &str, new_path: &str) -> Result<bool, HdfsErr>;
+
+    /// Create a directory and all of its missing parents
+    fn mkdir(&self, path: &str) -> Result<bool, HdfsErr>;
+}
+
+impl FileSystem for HdfsFs {
+    fn open(&self, path: &str) -> Result<Box<dyn Read + Send>, HdfsErr> {
+        Ok(Box::new(HdfsFs::open(self, path)?))
+    }
+
+    fn create(&self, path: &str) -> Result<Box<dyn Write + Send>, HdfsErr> {
+        Ok(Box::new(HdfsFs::create(self, path)?))
+    }
+
+    fn exist(&self, path: &str) -> bool {
+        HdfsFs::exist(self, path)
+    }
+
+    fn delete(&self, path: &str, recursive: bool) -> Result<bool, HdfsErr> {
+        HdfsFs::delete(self, path, recursive)
+    }
+
+    fn list_status(&self, path: &str) -> Result<Vec<FileStatus>, HdfsErr> {
+        HdfsFs::list_status(self, path)
+    }
+
+    fn get_file_status(&self, path: &str) -> Result<FileStatus, HdfsErr> {
+        HdfsFs::get_file_status(self, path)
+    }
+
+    fn rename(&self, old_path: &str, new_path: &str) -> Result<bool, HdfsErr> {
+        HdfsFs::rename(self, old_path, new_path)
+    }
+
+    fn mkdir(&self, path: &str) -> Result<bool, HdfsErr> {
+        HdfsFs::mkdir(self, path)
+    }
+}
@@ -0,0 +1,195 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Glob pattern matching of a single path segment
+//!
+//! Supported syntax:
+//! - ``*`` matches any sequence of characters
+//! - ``?`` matches any single character
+//! - ``[abc]``, ``[a-z]`` match a character in the class, ``[!a-z]`` or ``[^a-z]`` negate it
+//! - ``\`` escapes the next character
+
+/// Check whether a path segment contains any glob wildcard
+pub(crate) fn has_wildcard(segment: &str) -> bool {
+    segment.contains(['*', '?', '['])
+}
+
+/// Check whether the name matches the glob pattern of a single path segment
+pub(crate) fn matches(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+
+    let (mut p, mut n) = (0, 0);
+    // The position of the last '*' in pattern and the position in name it's matched from
+    let mut star: Option<(usize, usize)> = None;
+
+    while n < name.len() {
+        if p < pattern.len() {
+            match pattern[p] {
+                '*' => {
+                    star = Some((p, n));
+                    p += 1;
+                    continue;
+                }
+                '?' => {
+                    p += 1;
+                    n += 1;
+                    continue;
+                }
+                '[' => match match_class(&pattern, p, name[n]) {
+                    Some((true, next)) => {
+                        p = next;
+                        n += 1;
+                        continue;
+                    }
+                    Some((false, _)) => {}
+                    // An unclosed class is matched literally
+                    None if name[n] == '[' => {
+                        p += 1;
+                        n += 1;
+                        continue;
+                    }
+                    None => {}
+                },
+                '\\' if p + 1 < pattern.len() => {
+                    if pattern[p + 1] == name[n] {
+                        p += 2;
+                        n += 1;
+                        continue;
+                    }
+                }
+                c => {
+                    if c == name[n] {
+                        p += 1;
+                        n += 1;
+                        continue;
+                    }
+                }
+            }
+        }
+
+        // Mismatch, let the last '*' consume one more character
+        match star {
+            Some((star_p, star_n)) => {
+                p = star_p + 1;
+                n = star_n + 1;
+                star = Some((star_p, star_n + 1));
+            }
+            None => return false,
+        }
+    }
+
+    while p < pattern.len() && pattern[p] == '*' {
+        p += 1;
+    }
+    p == pattern.len()
+}
+
+/// Match a character against the class starting at ``pattern[start] == '['``.
+///
+/// Return whether it's matched and the position after the class,
+/// or ``None`` if the class is not closed.
+fn match_class(pattern: &[char], start: usize, c: char) -> Option<(bool, usize)> {
+    let mut idx = start + 1;
+    let negated = matches!(pattern.get(idx), Some('!') | Some('^'));
+    if negated {
+        idx += 1;
+    }
+
+    let mut matched = false;
+    let mut first = true;
+    loop {
+        let cur = *pattern.get(idx)?;
+        // ']' is a literal if it's the first character of the class
+        if cur == ']' && !first {
+            idx += 1;
+            break;
+        }
+        first = false;
+
+        if pattern.get(idx + 1) == Some(&'-')
+            && matches!(pattern.get(idx + 2), Some(&end) if end != ']')
+        {
+            let end = pattern[idx + 2];
+            if cur <= c && c <= end {
+                matched = true;
+            }
+            idx += 3;
+        } else {
+            if cur == c {
+                matched = true;
+            }
+            idx += 1;
+        }
+    }
+
+    Some((matched != negated, idx))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{has_wildcard, matches};
+
+    #[test]
+    fn test_has_wildcard() {
+        assert!(has_wildcard("*.parquet"));
+        assert!(has_wildcard("part-?"));
+        assert!(has_wildcard("[ab]"));
+        assert!(!has_wildcard("data.parquet"));
+    }
+
+    #[test]
+    fn test_star() {
+        assert!(matches("*", "anything"));
+        assert!(matches("*", ""));
+        assert!(matches("*.parquet", "a.parquet"));
+        assert!(matches("*.parquet", ".parquet"));
+        assert!(!matches("*.parquet", "a.parquet.crc"));
+        assert!(matches("part-*-*.csv", "part-0-1.csv"));
+        assert!(!matches("part-*-*.csv", "part-0.csv"));
+    }
+
+    #[test]
+    fn test_question_mark() {
+        assert!(matches("part-?", "part-1"));
+        assert!(!matches("part-?", "part-"));
+        assert!(!matches("part-?", "part-12"));
+        assert!(matches("??", "ab"));
+    }
+
+    #[test]
+    fn test_class() {
+        assert!(matches("[ab].txt", "a.txt"));
+        assert!(!matches("[ab].txt", "c.txt"));
+        assert!(matches("part-[0-9]", "part-5"));
+        assert!(!matches("part-[0-9]", "part-x"));
+        assert!(matches("part-[!0-9]", "part-x"));
+        assert!(matches("part-[^0-9]", "part-x"));
+        assert!(!matches("part-[!0-9]", "part-5"));
+        assert!(matches("[]]", "]"));
+        assert!(matches("[a-]", "-"));
+        // unclosed class is literal
+        assert!(matches("a[b", "a[b"));
+    }
+
+    #[test]
+    fn test_escape() {
+        assert!(matches("\\*", "*"));
+        assert!(!matches("\\*", "a"));
+        assert!(matches("a\\?", "a?"));
+    }
+}
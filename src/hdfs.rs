@@ -16,30 +16,87 @@
 // under the License.
 
 //! it's a modified version of hdfs-rs
-use std::collections::HashMap;
+#[cfg(feature = "handle_tracking")]
+use std::collections::BTreeMap;
+use std::collections::{HashMap, HashSet};
+use std::env;
 use std::ffi::{CStr, CString};
-use std::fmt::{Debug, Formatter};
+use std::fmt::{Debug, Display, Formatter};
+use std::io;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::iter;
 use std::marker::PhantomData;
-use std::rc::Rc;
+use std::mem;
+use std::mem::MaybeUninit;
+use std::process;
+use std::ptr;
 use std::string::String;
+use std::sync::atomic::{AtomicUsize, Ordering};
+#[cfg(feature = "handle_tracking")]
+use std::sync::Mutex;
 use std::sync::{Arc, RwLock};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use lazy_static::lazy_static;
 use libc::{c_char, c_int, c_short, c_void, time_t};
-use log::info;
+use log::{info, warn};
 use url::Url;
 
 pub use crate::err::HdfsErr;
+use crate::glob;
 use crate::native::*;
+use crate::path;
+use crate::reader::HdfsSliceReader;
+use crate::retry::{RetryPolicy, RetryingHdfsFs};
 
-const O_RDONLY: c_int = 0;
-const O_WRONLY: c_int = 1;
-const O_APPEND: c_int = 1024;
+/// Open a file for read
+pub const O_RDONLY: c_int = 0;
+/// Create or overwrite a file for write
+pub const O_WRONLY: c_int = 1;
+/// Open an existing file for write at its end, combined with ``O_WRONLY``
+pub const O_APPEND: c_int = 1024;
+/// Fail to create a file if it exists rather than overwrite it, combined with ``O_WRONLY``
+pub const O_EXCL: c_int = libc::O_EXCL;
+
+/// The block size should be a multiple of the checksum chunk size, 512 bytes by default
+const BLOCK_SIZE_UNIT: i64 = 512;
+
+/// The minimal growth of the buffer when a file is read beyond its length
+const MIN_READ_GROWTH: usize = 8 * 1024;
+
+/// The initial buffer size of the line iterator, which grows for a longer line
+const LINE_BUF_SIZE: usize = 64 * 1024;
+
+/// The number of paths from which ``exist_many`` checks them in parallel
+const EXIST_PARALLEL_THRESHOLD: usize = 32;
+
+/// The maximal number of threads checking the paths for ``exist_many``
+const EXIST_MAX_THREADS: usize = 8;
+
+/// The suffix of the temporary file written before renamed to the target, as the one of Hadoop
+const TEMP_FILE_SUFFIX: &str = "._COPYING_";
+
+/// The sequence number making the temporary files written before the renames unique
+static TEMP_FILE_SEQ: AtomicUsize = AtomicUsize::new(0);
+
+/// The sequence number identifying the open files tracked by ``open_handle_count``
+#[cfg(feature = "handle_tracking")]
+static HANDLE_SEQ: AtomicUsize = AtomicUsize::new(0);
+
+/// The maximal number of symbolic links followed to resolve a path, as the one of Hadoop
+const MAX_PATH_LINKS: usize = 32;
 
 lazy_static! {
     static ref HDFS_MANAGER: HdfsManager = HdfsManager::new();
 }
 
+#[cfg(feature = "handle_tracking")]
+lazy_static! {
+    /// The paths of the open files of every connection by their ids
+    static ref OPEN_HANDLES: Mutex<BTreeMap<usize, String>> = Mutex::new(BTreeMap::new());
+}
+
 /// Create instance of HdfsFs with a global cache.
 /// For each namenode uri, only one instance will be created
 pub fn get_hdfs_by_full_path(path: &str) -> Result<Arc<HdfsFs>, HdfsErr> {
@@ -77,9 +134,13 @@ impl HdfsManager {
     }
 
     fn get_hdfs_by_full_path(&self, path: &str) -> Result<Arc<HdfsFs>, HdfsErr> {
-        let namenode_uri = match path {
-            "default" => "default".to_owned(),
-            _ => get_namenode_uri(path)?,
+        let (name_node, port) = match path {
+            "default" => ("default".to_owned(), None),
+            _ => parse_namenode(path)?,
+        };
+        let namenode_uri = match port {
+            Some(port) => format!("{}:{}", name_node, port),
+            None => name_node.clone(),
         };
 
         // Get if already exists
@@ -95,23 +156,27 @@ impl HdfsManager {
         let ret = if let Some(hdfs_fs) = cache.get(&namenode_uri) {
             hdfs_fs.clone()
         } else {
+            let cstr_uri = CString::new(name_node.as_bytes())
+                .map_err(|_| HdfsErr::InvalidUrl(namenode_uri.clone()))?;
             let hdfs_fs = unsafe {
-                let hdfs_builder = hdfsNewBuilder();
-                let cstr_uri = CString::new(namenode_uri.as_bytes()).unwrap();
-                hdfsBuilderSetNameNode(hdfs_builder, cstr_uri.as_ptr());
+                let hdfs_builder = native_call!(hdfsNewBuilder());
+                // Not shared with the FileSystem cache of JVM, since it's disconnected
+                // once the last reference is dropped
+                native_call!(hdfsBuilderSetForceNewInstance(hdfs_builder));
+                // A nameservice is set without any port
+                native_call!(hdfsBuilderSetNameNode(hdfs_builder, cstr_uri.as_ptr()));
+                if let Some(port) = port {
+                    native_call!(hdfsBuilderSetNameNodePort(hdfs_builder, port as tPort));
+                }
                 info!("Connecting to Namenode ({})", &namenode_uri);
-                hdfsBuilderConnect(hdfs_builder)
+                native_call!(hdfsBuilderConnect(hdfs_builder))
             };
 
             if hdfs_fs.is_null() {
                 return Err(HdfsErr::CannotConnectToNameNode(namenode_uri.clone()));
             }
 
-            let hdfs_fs = Arc::new(HdfsFs {
-                url: namenode_uri.clone(),
-                raw: hdfs_fs,
-                _marker: PhantomData,
-            });
+            let hdfs_fs = Arc::new(HdfsFs::new(namenode_uri.clone(), None, hdfs_fs));
             cache.insert(namenode_uri.clone(), hdfs_fs.clone());
             hdfs_fs
         };
@@ -141,13 +206,329 @@ impl HdfsManager {
     }
 }
 
+/// Builder of a HdfsFs connection, wrapping the ``hdfsBuilder`` of libhdfs
+///
+/// Each connection built by it is a new instance rather than the one cached by the JVM,
+/// so that the configuration overrides always take effect.
+///
+/// ```ignore
+/// use hdfs::hdfs::HdfsFsBuilder;
+///
+/// let fs = HdfsFsBuilder::new()
+///     .set_name_node("hdfs://localhost")
+///     .set_port(8020)
+///     .set_user("hadoop")
+///     .set_conf("dfs.client.read.shortcircuit", "false")
+///     .connect()
+///     .ok()
+///     .unwrap();
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct HdfsFsBuilder {
+    name_node: Option<String>,
+    port: Option<u16>,
+    user: Option<String>,
+    confs: Vec<(String, String)>,
+}
+
+impl HdfsFsBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the namenode, e.g. ``hdfs://localhost``, ``file:///`` or ``default``.
+    /// The local filesystem will be used if it's not set.
+    ///
+    /// A namenode without any port, e.g. ``hdfs://mycluster``, may also be a nameservice,
+    /// see ``set_name_service``.
+    pub fn set_name_node(&mut self, name_node: &str) -> &mut Self {
+        self.name_node = Some(name_node.to_owned());
+        self
+    }
+
+    /// Set the logical nameservice of a high-availability cluster, e.g. ``mycluster``
+    /// for ``hdfs://mycluster``, which is connected without any port.
+    ///
+    /// The nameservice is resolved to its namenodes by the client configuration, so the
+    /// ``core-site.xml`` and ``hdfs-site.xml`` defining it must be on the ``CLASSPATH``.
+    pub fn set_name_service(&mut self, name_service: &str) -> &mut Self {
+        self.name_node = Some(format!("{}://{}", HDFS_FS_SCHEME, name_service));
+        self.port = None;
+        self
+    }
+
+    /// Set the namenode port, which should not be set if the namenode has contained one
+    pub fn set_port(&mut self, port: u16) -> &mut Self {
+        self.port = Some(port);
+        self
+    }
+
+    /// Set the user name used to connect
+    pub fn set_user(&mut self, user: &str) -> &mut Self {
+        self.user = Some(user.to_owned());
+        self
+    }
+
+    /// Set a configuration for the connection, e.g. ``dfs.replication``
+    pub fn set_conf(&mut self, key: &str, value: &str) -> &mut Self {
+        self.confs.push((key.to_owned(), value.to_owned()));
+        self
+    }
+
+    /// Set many configurations for the connection in one call, e.g. the ones of a
+    /// secure cluster. They are applied in the order of the keys, to be reproducible
+    /// as the order of a ``HashMap`` isn't deterministic.
+    pub fn set_confs(&mut self, confs: &HashMap<String, String>) -> &mut Self {
+        let mut confs: Vec<(&String, &String)> = confs.iter().collect();
+        confs.sort();
+        for (key, value) in confs {
+            self.set_conf(key, value);
+        }
+        self
+    }
+
+    /// Connect to the filesystem
+    pub fn connect(&self) -> Result<HdfsFs, HdfsErr> {
+        let url = match (&self.name_node, self.port) {
+            (Some(name_node), Some(port)) => format!("{}:{}", name_node, port),
+            (Some(name_node), None) => name_node.clone(),
+            (None, _) => "file:///".to_owned(),
+        };
+
+        // The builder only keeps the pointers, so the strings must outlive the connection
+        let cstr_name_node = self
+            .name_node
+            .as_ref()
+            .map(|name_node| {
+                CString::new(name_node.as_bytes())
+                    .map_err(|_| HdfsErr::InvalidUrl(name_node.clone()))
+            })
+            .transpose()?;
+        let cstr_user = self
+            .user
+            .as_ref()
+            .map(|user| to_c_string_arg(user, "user name"))
+            .transpose()?;
+        let cstr_confs = self
+            .confs
+            .iter()
+            .map(|(key, value)| {
+                Ok((
+                    to_c_string_arg(key, "configuration key")?,
+                    to_c_string_arg(value, "configuration value")?,
+                ))
+            })
+            .collect::<Result<Vec<(CString, CString)>, HdfsErr>>()?;
+
+        let hdfs_fs = unsafe {
+            let hdfs_builder = native_call!(hdfsNewBuilder());
+            if hdfs_builder.is_null() {
+                return Err(HdfsErr::CannotConnectToNameNode(url));
+            }
+            native_call!(hdfsBuilderSetForceNewInstance(hdfs_builder));
+            if let Some(cstr_name_node) = &cstr_name_node {
+                native_call!(hdfsBuilderSetNameNode(
+                    hdfs_builder,
+                    cstr_name_node.as_ptr()
+                ));
+            }
+            if let Some(port) = self.port {
+                native_call!(hdfsBuilderSetNameNodePort(hdfs_builder, port as tPort));
+            }
+            if let Some(cstr_user) = &cstr_user {
+                native_call!(hdfsBuilderSetUserName(hdfs_builder, cstr_user.as_ptr()));
+            }
+            for (cstr_key, cstr_value) in cstr_confs.iter() {
+                if native_call!(hdfsBuilderConfSetStr(
+                    hdfs_builder,
+                    cstr_key.as_ptr(),
+                    cstr_value.as_ptr(),
+                )) != 0
+                {
+                    native_call!(hdfsFreeBuilder(hdfs_builder));
+                    return Err(HdfsErr::CannotConnectToNameNode(url));
+                }
+            }
+            info!("Connecting to Namenode ({})", &url);
+            // The builder is freed by hdfsBuilderConnect
+            native_call!(hdfsBuilderConnect(hdfs_builder))
+        };
+
+        if hdfs_fs.is_null() {
+            return Err(HdfsErr::CannotConnectToNameNode(url));
+        }
+
+        Ok(HdfsFs::new(url, self.user.clone(), hdfs_fs))
+    }
+}
+
+/// Options to open a file, as ``std::fs::OpenOptions``, which are mapped to the flags
+/// of ``hdfsOpenFile`` and opened by ``HdfsFs::open_with``.
+///
+/// HDFS can't write a file in place, so a file is either opened for read, created for
+/// write, or appended. The supported combinations are:
+/// - ``read``
+/// - ``write`` with ``create``, which overwrites an existing file
+/// - ``write`` with ``create_new``, which fails if the file exists
+/// - ``append``, with or without ``write``, for an existing file
+///
+/// ```ignore
+/// use hdfs::hdfs::HdfsOpenOptions;
+///
+/// let file = fs
+///     .open_with("/data/log", HdfsOpenOptions::new().append(true))
+///     .ok()
+///     .unwrap();
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct HdfsOpenOptions {
+    read: bool,
+    write: bool,
+    append: bool,
+    create: bool,
+    create_new: bool,
+    buffer_size: i32,
+    replication: u16,
+    block_size: i64,
+}
+
+impl HdfsOpenOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Open the file for read
+    pub fn read(&mut self, read: bool) -> &mut Self {
+        self.read = read;
+        self
+    }
+
+    /// Open the file for write, together with ``create``, ``create_new`` or ``append``
+    pub fn write(&mut self, write: bool) -> &mut Self {
+        self.write = write;
+        self
+    }
+
+    /// Open an existing file for write at its end
+    pub fn append(&mut self, append: bool) -> &mut Self {
+        self.append = append;
+        self
+    }
+
+    /// Create the file, or overwrite it if it exists
+    pub fn create(&mut self, create: bool) -> &mut Self {
+        self.create = create;
+        self
+    }
+
+    /// Create the file, which fails atomically if it exists
+    pub fn create_new(&mut self, create_new: bool) -> &mut Self {
+        self.create_new = create_new;
+        self
+    }
+
+    /// Set the buffer size of the stream, 0 for the default one
+    pub fn buffer_size(&mut self, buffer_size: i32) -> &mut Self {
+        self.buffer_size = buffer_size;
+        self
+    }
+
+    /// Set the replication of a created file, 0 for the default one
+    pub fn replication(&mut self, replication: u16) -> &mut Self {
+        self.replication = replication;
+        self
+    }
+
+    /// Set the block size of a created file, 0 for the default one
+    pub fn block_size(&mut self, block_size: i64) -> &mut Self {
+        self.block_size = block_size;
+        self
+    }
+
+    /// Get the flags of ``hdfsOpenFile``, or ``InvalidArgument`` for an unsupported
+    /// combination of the options
+    pub fn flags(&self) -> Result<i32, HdfsErr> {
+        let write = self.write || self.append;
+        let invalid = if self.read && write {
+            Some("read and write")
+        } else if self.append && (self.create || self.create_new) {
+            Some("append and create")
+        } else if self.create && self.create_new {
+            Some("create and create_new")
+        } else if !write && (self.create || self.create_new) {
+            Some("create without write")
+        } else if self.write && !self.append && !self.create && !self.create_new {
+            Some("write without create, create_new or append")
+        } else if !self.read && !write {
+            Some("neither read nor write")
+        } else {
+            None
+        };
+        if let Some(invalid) = invalid {
+            return Err(HdfsErr::InvalidArgument(format!(
+                "unsupported open options of {}",
+                invalid
+            )));
+        }
+
+        Ok(if self.read {
+            O_RDONLY
+        } else if self.append {
+            O_WRONLY | O_APPEND
+        } else if self.create_new {
+            O_WRONLY | O_EXCL
+        } else {
+            O_WRONLY
+        })
+    }
+}
+
+/// The native connection, which is disconnected when dropped
+struct RawHdfsFs(hdfsFS);
+
+impl Drop for RawHdfsFs {
+    fn drop(&mut self) {
+        if unsafe { native_call!(hdfsDisconnect(self.0)) } != 0 {
+            warn!("Fail to disconnect: {}", io::Error::last_os_error());
+        }
+    }
+}
+
+impl RawHdfsFs {
+    /// Disconnect explicitly rather than when dropped
+    fn disconnect(self) -> c_int {
+        let ret = unsafe { native_call!(hdfsDisconnect(self.0)) };
+        mem::forget(self);
+        ret
+    }
+}
+
+/// The native ``hdfsFS`` is a global reference to a java ``FileSystem``, which is
+/// thread safe, and libhdfs attaches the calling thread to the JVM on demand.
+unsafe impl Send for RawHdfsFs {}
+
+unsafe impl Sync for RawHdfsFs {}
+
 /// Hdfs Filesystem
 ///
-/// It is basically thread safe because the native API for hdfsFs is thread-safe.
+/// It's ``Send`` and ``Sync``, so that it can be shared by threads, e.g. by ``Arc``,
+/// and all of its methods can be called concurrently, as the native API for hdfsFs
+/// is thread-safe. However, concurrent operations on the same path race with each
+/// other as they do in HDFS, e.g. a ``create`` of a path being deleted by another thread.
+///
+/// It's cheap to clone, and all of the clones share the same native connection,
+/// which is disconnected when the last of them is dropped.
 #[derive(Clone)]
 pub struct HdfsFs {
     url: String,
-    raw: hdfsFS,
+    /// The user connected as, or ``None`` for the one of the current process
+    user: Option<String>,
+    raw: Arc<RawHdfsFs>,
+    /// The chunk size of the read and write helpers shared by the clones, 0 if not set
+    io_buffer_size: Arc<AtomicUsize>,
+    /// The paths of the open files by their ids, shared by the clones
+    #[cfg(feature = "handle_tracking")]
+    open_handles: Arc<Mutex<BTreeMap<usize, String>>>,
     _marker: PhantomData<()>,
 }
 
@@ -158,6 +539,18 @@ impl Debug for HdfsFs {
 }
 
 impl HdfsFs {
+    fn new(url: String, user: Option<String>, raw: hdfsFS) -> HdfsFs {
+        HdfsFs {
+            url,
+            user,
+            raw: Arc::new(RawHdfsFs(raw)),
+            io_buffer_size: Arc::new(AtomicUsize::new(0)),
+            #[cfg(feature = "handle_tracking")]
+            open_handles: Arc::new(Mutex::new(BTreeMap::new())),
+            _marker: PhantomData,
+        }
+    }
+
     /// Get HDFS namenode url
     #[inline]
     pub fn url(&self) -> &str {
@@ -167,18 +560,199 @@ impl HdfsFs {
     /// Get a raw pointer of JNI API's HdfsFs
     #[inline]
     pub fn raw(&self) -> hdfsFS {
-        self.raw
+        self.raw.0
+    }
+
+    /// Set the chunk size of each native read or write of the helpers, e.g. ``read_to_vec``,
+    /// ``copy_to_writer``, ``write_all`` and ``write_from_reader``, or 0 to reset it.
+    ///
+    /// It's shared by all of the clones. When not set, the helpers read or write in chunks
+    /// of the block size of the file, i.e. of the file written into for a write.
+    pub fn set_default_io_buffer_size(&self, size: usize) {
+        self.io_buffer_size.store(size, Ordering::Relaxed);
+    }
+
+    /// Get the chunk size of the helpers, or ``None`` if it's not set
+    pub fn default_io_buffer_size(&self) -> Option<usize> {
+        match self.io_buffer_size.load(Ordering::Relaxed) {
+            0 => None,
+            size => Some(size),
+        }
+    }
+
+    /// The chunk size of the helpers on a file of the block size
+    fn io_chunk_size(&self, block_size: usize) -> usize {
+        self.default_io_buffer_size().unwrap_or(block_size).max(1)
+    }
+
+    /// Get the number of the files opened by any connection of the process which are
+    /// not closed or dropped yet, e.g. to detect the leaks of a service.
+    #[cfg(feature = "handle_tracking")]
+    pub fn open_handle_count() -> usize {
+        OPEN_HANDLES.lock().unwrap().len()
+    }
+
+    /// Get the paths of the files counted by ``open_handle_count``, in the order they
+    /// are opened, to find the leaked ones.
+    #[cfg(feature = "handle_tracking")]
+    pub fn open_handles() -> Vec<String> {
+        OPEN_HANDLES.lock().unwrap().values().cloned().collect()
+    }
+
+    /// Get the number of the files opened by this connection or its clones which are
+    /// not closed or dropped yet, e.g. to assert that a test leaks none of them.
+    #[cfg(feature = "handle_tracking")]
+    pub fn connection_open_handle_count(&self) -> usize {
+        self.open_handles.lock().unwrap().len()
+    }
+
+    /// Get the paths of the files counted by ``connection_open_handle_count``, in the
+    /// order they are opened.
+    #[cfg(feature = "handle_tracking")]
+    pub fn connection_open_handles(&self) -> Vec<String> {
+        self.open_handles
+            .lock()
+            .unwrap()
+            .values()
+            .cloned()
+            .collect()
+    }
+
+    #[cfg(feature = "handle_tracking")]
+    fn track_handle(&self, path: &str) -> usize {
+        let id = HANDLE_SEQ.fetch_add(1, Ordering::Relaxed);
+        OPEN_HANDLES.lock().unwrap().insert(id, path.to_owned());
+        self.open_handles
+            .lock()
+            .unwrap()
+            .insert(id, path.to_owned());
+        id
+    }
+
+    #[cfg(feature = "handle_tracking")]
+    fn untrack_handle(&self, id: usize) {
+        self.open_handles.lock().unwrap().remove(&id);
+        OPEN_HANDLES.lock().unwrap().remove(&id);
+    }
+
+    /// Get a wrapper whose operations are retried by the policy on transient failures,
+    /// e.g. during a failover of the namenode. It shares the connection of this one.
+    pub fn with_retry(&self, policy: RetryPolicy) -> RetryingHdfsFs {
+        RetryingHdfsFs::new(self.clone(), policy)
+    }
+
+    /// Check whether the connection is still usable, e.g. after a failover of the namenode,
+    /// by getting the status of the root.
+    ///
+    /// Any failure is reported as ``CannotConnectToNameNode``, whose cause is logged.
+    pub fn ping(&self) -> Result<(), HdfsErr> {
+        match self.get_file_status("/") {
+            Ok(_) => Ok(()),
+            Err(e) => {
+                warn!("Failed to ping {}: {:?}", self.url, e);
+                Err(HdfsErr::CannotConnectToNameNode(self.url.clone()))
+            }
+        }
+    }
+
+    /// Disconnect from the filesystem, which is only allowed when there are no other clones.
+    ///
+    /// The handle is consumed even if it fails, so that it can't be used any more:
+    ///
+    /// ```compile_fail
+    /// use hdfs::hdfs::HdfsFsBuilder;
+    ///
+    /// let fs = HdfsFsBuilder::new().connect().ok().unwrap();
+    /// fs.disconnect().ok().unwrap();
+    /// fs.exist("/");
+    /// ```
+    ///
+    /// A cached instance can only be disconnected after it's removed from the cache by
+    /// ``unload_hdfs_cache``.
+    pub fn disconnect(self) -> Result<(), HdfsErr> {
+        let url = self.url;
+        let raw = Arc::try_unwrap(self.raw).map_err(|raw| {
+            HdfsErr::ConnectionInUse(url.clone(), Arc::strong_count(&raw) - 1)
+        })?;
+
+        if raw.disconnect() == 0 {
+            Ok(())
+        } else {
+            Err(HdfsErr::from_errno(&url).logged(&url))
+        }
+    }
+
+    /// Get the cached instance of HdfsFs for the namenode of the specified path,
+    /// which is created if not existing yet.
+    /// For each namenode uri, all of the returned instances share the same connection.
+    pub fn get_cached(path: &str) -> Result<HdfsFs, HdfsErr> {
+        HDFS_MANAGER
+            .get_hdfs_by_full_path(path)
+            .map(|hdfs_fs| hdfs_fs.as_ref().clone())
+    }
+
+    /// Connect to a namenode as the specified user.
+    ///
+    /// #### Params
+    /// * ```namenode``` - The namenode, e.g. ``localhost`` or ``hdfs://localhost``.
+    /// * ```port``` - The namenode port. 0 means to use the port in ``namenode`` or the default one.
+    /// * ```user``` - The user name to connect as.
+    pub fn connect_as_user(
+        namenode: &str,
+        port: u16,
+        user: &str,
+    ) -> Result<HdfsFs, HdfsErr> {
+        if user.is_empty() {
+            return Err(HdfsErr::InvalidArgument(
+                "user name should not be empty".to_owned(),
+            ));
+        }
+
+        let mut url = if namenode.contains("://") {
+            namenode.to_owned()
+        } else {
+            format!("{}://{}", HDFS_FS_SCHEME, namenode)
+        };
+        if port > 0 {
+            url.push_str(&format!(":{}", port));
+        }
+
+        let hdfs_fs = unsafe {
+            let cstr_namenode = CString::new(namenode)
+                .map_err(|_| HdfsErr::InvalidUrl(namenode.to_owned()))?;
+            let cstr_user = to_c_string_arg(user, "user name")?;
+            info!("Connecting to Namenode ({}) as {}", &url, user);
+            native_call!(
+                hdfsConnectAsUserNewInstance(
+                    cstr_namenode.as_ptr(),
+                    port as tPort,
+                    cstr_user.as_ptr(),
+                ),
+                namenode,
+                port,
+                user
+            )
+        };
+
+        if hdfs_fs.is_null() {
+            return Err(HdfsErr::CannotConnectToNameNode(url));
+        }
+
+        Ok(HdfsFs::new(url, Some(user.to_owned()), hdfs_fs))
     }
 
     /// Create HdfsFile from hdfsFile
     fn new_hdfs_file(&self, path: &str, file: hdfsFile) -> Result<HdfsFile, HdfsErr> {
         if file.is_null() {
-            Err(HdfsErr::Unknown)
+            Err(HdfsErr::from_errno(path).logged(path))
         } else {
             Ok(HdfsFile {
                 fs: self.clone(),
                 path: path.to_owned(),
                 file,
+                closed: false,
+                #[cfg(feature = "handle_tracking")]
+                handle_id: self.track_handle(path),
                 _marker: PhantomData,
             })
         }
@@ -197,545 +771,2912 @@ impl HdfsFs {
         buf_size: i32,
     ) -> Result<HdfsFile, HdfsErr> {
         let file = unsafe {
-            let cstr_path = CString::new(path).unwrap();
-            hdfsOpenFile(
-                self.raw,
-                cstr_path.as_ptr(),
-                O_RDONLY,
-                buf_size as c_int,
-                0,
-                0,
+            let cstr_path = to_c_string(path)?;
+            native_call!(
+                hdfsOpenFile(
+                    self.raw(),
+                    cstr_path.as_ptr(),
+                    O_RDONLY,
+                    buf_size as c_int,
+                    0,
+                    0,
+                ),
+                path
             )
         };
 
         self.new_hdfs_file(path, file)
     }
 
-    /// Get the file status, including file size, last modified time, etc
-    pub fn get_file_status(&self, path: &str) -> Result<FileStatus, HdfsErr> {
-        let ptr = unsafe {
-            let cstr_path = CString::new(path).unwrap();
-            hdfsGetPathInfo(self.raw, cstr_path.as_ptr())
+    /// Open a file with all of the parameters of ``hdfsOpenFile``.
+    ///
+    /// #### Params
+    /// * ```flags``` - ``O_RDONLY``, ``O_WRONLY``, ``O_WRONLY | O_EXCL`` or ``O_WRONLY | O_APPEND``.
+    /// * ```buffer_size``` - The buffer size of the stream. 0 means the default one.
+    /// * ```replication``` - The replication of a created file. 0 means the default one.
+    /// * ```block_size``` - The block size of a created file, which should be a multiple
+    ///   of 512 bytes. 0 means the default one.
+    pub fn open_with_params(
+        &self,
+        path: &str,
+        flags: i32,
+        buffer_size: i32,
+        replication: u16,
+        block_size: i64,
+    ) -> Result<HdfsFile, HdfsErr> {
+        if buffer_size < 0 {
+            return Err(HdfsErr::InvalidArgument(format!(
+                "negative buffer size {} to open {}",
+                buffer_size, path
+            )));
+        }
+        if replication > i16::MAX as u16 {
+            return Err(HdfsErr::InvalidArgument(format!(
+                "invalid replication {} to open {}",
+                replication, path
+            )));
+        }
+        if block_size < 0
+            || block_size > tSize::MAX as i64
+            || block_size % BLOCK_SIZE_UNIT != 0
+        {
+            return Err(HdfsErr::InvalidArgument(format!(
+                "invalid block size {} to open {}, which should be a multiple of {}",
+                block_size, path, BLOCK_SIZE_UNIT
+            )));
+        }
+
+        let file = unsafe {
+            let cstr_path = to_c_string(path)?;
+            native_call!(
+                hdfsOpenFile(
+                    self.raw(),
+                    cstr_path.as_ptr(),
+                    flags as c_int,
+                    buffer_size as c_int,
+                    replication as c_short,
+                    block_size as tSize,
+                ),
+                path
+            )
         };
 
-        if ptr.is_null() {
-            Err(HdfsErr::Unknown)
-        } else {
-            Ok(FileStatus::new(ptr))
-        }
+        self.new_hdfs_file(path, file)
     }
 
-    /// Get the file status for each entry under the specified directory
-    pub fn list_status(&self, path: &str) -> Result<Vec<FileStatus>, HdfsErr> {
-        let mut entry_num: c_int = 0;
+    /// Open a file with the options, see ``HdfsOpenOptions``
+    pub fn open_with(
+        &self,
+        path: &str,
+        opts: &HdfsOpenOptions,
+    ) -> Result<HdfsFile, HdfsErr> {
+        self.open_with_params(
+            path,
+            opts.flags()?,
+            opts.buffer_size,
+            opts.replication,
+            opts.block_size,
+        )
+    }
 
-        let ptr = unsafe {
-            let cstr_path = CString::new(path).unwrap();
-            hdfsListDirectory(self.raw, cstr_path.as_ptr(), &mut entry_num)
+    /// Open a file for read, which is decompressed by the codec given by its extension,
+    /// i.e. gzip for ``.gz``, zstd for ``.zst`` and bzip2 for ``.bz2``, or read as it is
+    /// for any other extension. A file of concatenated gzip or bzip2 members is read
+    /// as a whole.
+    ///
+    /// A corrupted file is only reported by an error of the read.
+    #[cfg(feature = "compression")]
+    pub fn open_decompressed(&self, path: &str) -> Result<Box<dyn Read>, HdfsErr> {
+        let file = self.open(path)?;
+        let extension = path::file_name(path)
+            .and_then(|name| name.rsplit_once('.').map(|(_, ext)| ext.to_owned()));
+
+        let reader: Box<dyn Read> = match extension.as_deref() {
+            Some("gz") => Box::new(flate2::read::MultiGzDecoder::new(file)),
+            Some("zst") => {
+                Box::new(zstd::stream::read::Decoder::new(file).map_err(|e| {
+                    HdfsErr::InvalidData(format!(
+                        "failed to read {} by zstd: {}",
+                        path, e
+                    ))
+                })?)
+            }
+            Some("bz2") => Box::new(bzip2::read::MultiBzDecoder::new(file)),
+            _ => Box::new(file),
         };
+        Ok(reader)
+    }
 
-        let mut list = Vec::new();
-
-        if ptr.is_null() {
-            return Ok(list);
+    /// Open a file for read with its length, e.g. to size the buffer up front.
+    ///
+    /// The length is fetched once before the file is opened, so it doesn't include the
+    /// data appended later. It fails with ``IsDirectory`` for a directory.
+    pub fn open_with_len(&self, path: &str) -> Result<(HdfsFile, i64), HdfsErr> {
+        let status = self.get_file_status(path)?;
+        if status.is_directory() {
+            return Err(HdfsErr::IsDirectory(path.to_owned()));
         }
 
-        let shared_ptr = Rc::new(HdfsFileInfoPtr::new_array(ptr, entry_num));
+        Ok((self.open(path)?, status.len() as i64))
+    }
 
-        for idx in 0..entry_num {
-            list.push(FileStatus::from_array(shared_ptr.clone(), idx as u32));
+    /// Read the whole file, which is meant for small files like configs or metadata.
+    pub fn read_to_vec(&self, path: &str) -> Result<Vec<u8>, HdfsErr> {
+        let status = self.get_file_status(path)?;
+        if status.is_directory() {
+            return Err(HdfsErr::IsDirectory(path.to_owned()));
         }
 
-        Ok(list)
-    }
+        let mut file = self.open(path)?;
+        let mut data = vec![0u8; status.len()];
+        let chunk_size = self.io_chunk_size(status.block_size());
+        let mut read = 0;
+        loop {
+            // The file may grow after its status is got
+            if read == data.len() {
+                data.resize(read + MIN_READ_GROWTH.max(read / 2), 0);
+            }
+            let end = data.len().min(read.saturating_add(chunk_size));
+            match file.read(&mut data[read..end])? {
+                0 => break,
+                len => read += len as usize,
+            }
+        }
+        data.truncate(read);
 
-    /// Get the default blocksize.
-    pub fn default_blocksize(&self) -> Result<usize, HdfsErr> {
-        let block_sz = unsafe { hdfsGetDefaultBlockSize(self.raw) };
+        file.close()?;
+        Ok(data)
+    }
 
-        if block_sz > 0 {
-            Ok(block_sz as usize)
-        } else {
-            Err(HdfsErr::Unknown)
-        }
+    /// Check whether a file is read by the direct read, which is the read path taking
+    /// the short-circuit read of a local datanode if it's enabled by the configuration,
+    /// see ``HdfsFile::uses_direct_read``.
+    ///
+    /// The file is opened for the check, and closed before returning.
+    pub fn supports_local_read(&self, path: &str) -> Result<bool, HdfsErr> {
+        let file = self.open(path)?;
+        let direct_read = file.uses_direct_read();
+        file.close()?;
+        Ok(direct_read)
     }
 
-    /// Get the default blocksize at the filesystem indicated by a given path.
-    pub fn block_size(&self, path: &str) -> Result<usize, HdfsErr> {
-        let block_sz = unsafe {
-            let cstr_path = CString::new(path).unwrap();
-            hdfsGetDefaultBlockSizeAtPath(self.raw, cstr_path.as_ptr())
-        };
+    /// Copy the whole file into the writer in chunks of the block size, and return the
+    /// number of bytes copied. An error of the writer is returned as ``Sink``.
+    ///
+    /// Unlike ``read_to_vec``, only a chunk is kept in memory, even for a large file.
+    pub fn copy_to_writer(&self, path: &str, w: &mut dyn Write) -> Result<u64, HdfsErr> {
+        let status = self.get_file_status(path)?;
+        if status.is_directory() {
+            return Err(HdfsErr::IsDirectory(path.to_owned()));
+        }
 
-        if block_sz > 0 {
-            Ok(block_sz as usize)
-        } else {
-            Err(HdfsErr::Unknown)
+        // Not more than the file for a small file, which may still grow
+        let chunk_size = self.default_io_buffer_size().unwrap_or_else(|| {
+            status.block_size().min(status.len().max(MIN_READ_GROWTH))
+        });
+        let mut buf = vec![0u8; chunk_size.max(1)];
+        let mut file = self.open(path)?;
+        let mut copied = 0u64;
+        loop {
+            match file.read(&mut buf)? as usize {
+                0 => break,
+                len => {
+                    w.write_all(&buf[..len]).map_err(HdfsErr::Sink)?;
+                    copied += len as u64;
+                }
+            }
         }
-    }
 
-    /// Return the raw capacity of the filesystem.
-    pub fn capacity(&self) -> Result<usize, HdfsErr> {
-        let block_sz = unsafe { hdfsGetCapacity(self.raw) };
+        file.close()?;
+        Ok(copied)
+    }
 
-        if block_sz > 0 {
-            Ok(block_sz as usize)
-        } else {
-            Err(HdfsErr::Unknown)
-        }
+    /// Read the first ``n`` bytes of a file, or the whole file if it's shorter than ``n``,
+    /// e.g. to sniff the magic number of its format. See ``read_range``.
+    pub fn head(&self, path: &str, n: usize) -> Result<Vec<u8>, HdfsErr> {
+        self.read_range(path, 0, n)
     }
 
-    /// Return the total raw size of all files in the filesystem.
-    pub fn used(&self) -> Result<usize, HdfsErr> {
-        let block_sz = unsafe { hdfsGetUsed(self.raw) };
+    /// Read the last ``n`` bytes of a file, or the whole file if it's shorter than ``n``.
+    ///
+    /// The file is read by ``read_range`` from the offset of ``n`` bytes before its end.
+    pub fn tail(&self, path: &str, n: usize) -> Result<Vec<u8>, HdfsErr> {
+        let len = self.get_file_status(path)?.len();
+        self.read_range(path, len.saturating_sub(n) as i64, n)
+    }
 
-        if block_sz > 0 {
-            Ok(block_sz as usize)
-        } else {
-            Err(HdfsErr::Unknown)
+    /// Read ``len`` bytes of a file from the offset, or the bytes until the end of the file
+    /// if the range extends past it, e.g. the footer of a columnar file.
+    ///
+    /// The buffer is allocated for the bytes available only, so a range as large as
+    /// ``usize::MAX`` reads the rest of the file.
+    pub fn read_range(
+        &self,
+        path: &str,
+        offset: i64,
+        len: usize,
+    ) -> Result<Vec<u8>, HdfsErr> {
+        if offset < 0 {
+            return Err(HdfsErr::InvalidArgument(format!(
+                "negative offset {} to read {}",
+                offset, path
+            )));
         }
+
+        let (file, available) = self.open_range(path, offset as u64, len as u64)?;
+        let mut data = vec![0u8; available as usize];
+        let read = file.pread(offset, &mut data)?;
+        data.truncate(read);
+
+        file.close()?;
+        Ok(data)
     }
 
-    /// Checks if a given path exsits on the filesystem
-    pub fn exist(&self, path: &str) -> bool {
-        (unsafe {
-            let cstr_path = CString::new(path).unwrap();
-            hdfsExists(self.raw, cstr_path.as_ptr())
-        } == 0)
+    /// Open a range of a file as a reader of ``Read + Seek`` which starts at 0, e.g. to pass
+    /// a row group of a columnar file to a parser, see ``HdfsSliceReader``.
+    ///
+    /// The range is shortened to the end of the file if it extends past it, so that
+    /// ``SeekFrom::End`` is relative to the last byte available.
+    pub fn open_slice(
+        &self,
+        path: &str,
+        offset: i64,
+        len: i64,
+    ) -> Result<HdfsSliceReader, HdfsErr> {
+        if offset < 0 || len < 0 {
+            return Err(HdfsErr::InvalidArgument(format!(
+                "negative range {}+{} to read {}",
+                offset, len, path
+            )));
+        }
+
+        let (file, available) = self.open_range(path, offset as u64, len as u64)?;
+        Ok(HdfsSliceReader::new(
+            Arc::new(file),
+            offset as u64,
+            available,
+        ))
     }
 
-    /// Get hostnames where a particular block (determined by
-    /// pos & blocksize) of a file is stored. The last element in the array
-    /// is NULL. Due to replication, a single block could be present on
-    /// multiple hosts.
-    pub fn get_hosts(
+    /// Open a file for a range of it, and return the file with the length of the range
+    /// shortened to the end of the file. It fails with ``IsDirectory`` for a directory.
+    fn open_range(
         &self,
         path: &str,
-        start: usize,
-        length: usize,
-    ) -> Result<BlockHosts, HdfsErr> {
-        let ptr = unsafe {
-            let cstr_path = CString::new(path).unwrap();
-            hdfsGetHosts(
-                self.raw,
-                cstr_path.as_ptr(),
-                start as tOffset,
-                length as tOffset,
-            )
-        };
+        offset: u64,
+        len: u64,
+    ) -> Result<(HdfsFile, u64), HdfsErr> {
+        let (file, file_len) = self.open_with_len(path)?;
+        Ok((file, (file_len as u64).saturating_sub(offset).min(len)))
+    }
 
-        if !ptr.is_null() {
-            Ok(BlockHosts { ptr })
-        } else {
-            Err(HdfsErr::Unknown)
-        }
+    /// Get an iterator of the UTF-8 lines of a file, without the ending ``\n`` or ``\r\n``.
+    ///
+    /// A line which is not UTF-8 is yielded as ``InvalidData``, and the following lines
+    /// are still iterated, while a read error ends the iteration. The last line may have
+    /// no ending. The file is closed at its end or when the iterator is dropped.
+    pub fn read_lines(
+        &self,
+        path: &str,
+    ) -> Result<impl Iterator<Item = Result<String, HdfsErr>>, HdfsErr> {
+        let file = self.open(path)?;
+        Ok(LineIter {
+            path: path.to_owned(),
+            file: Some(file),
+            buf: vec![0u8; LINE_BUF_SIZE],
+            start: 0,
+            end: 0,
+            line_num: 0,
+        })
     }
 
-    #[inline]
-    pub fn create(&self, path: &str) -> Result<HdfsFile, HdfsErr> {
-        self.create_with_params(path, false, 0, 0, 0)
+    /// Write the whole data into a file, which is created or overwritten.
+    ///
+    /// The data is written into a unique temporary file ``{path}.{id}._COPYING_`` first,
+    /// which is renamed to the path on success and deleted on failure, so that readers
+    /// never see a partially written file. An existing file is replaced atomically by
+    /// ``rename_overwrite``, so it's kept if the rename fails.
+    pub fn write_all(
+        &self,
+        path: &str,
+        data: &[u8],
+        overwrite: bool,
+    ) -> Result<(), HdfsErr> {
+        self.write_via_tmp_file(path, overwrite, |file| {
+            let chunk_size = self.io_chunk_size(file.get_file_status()?.block_size());
+            file.write_chunked(data, chunk_size)?;
+            Ok(data.len() as u64)
+        })
+        .map(|_| ())
     }
 
-    #[inline]
-    pub fn create_with_overwrite(
+    /// Write the data read from the reader until its end into a file, which is created
+    /// or overwritten, and return the number of bytes written.
+    ///
+    /// The data is read and written in chunks, so only a chunk is kept in memory. As
+    /// ``write_all``, the file is written via a temporary file. An error of the reader
+    /// is returned as ``Source``.
+    pub fn write_from_reader(
         &self,
         path: &str,
+        r: &mut dyn Read,
         overwrite: bool,
-    ) -> Result<HdfsFile, HdfsErr> {
-        self.create_with_params(path, overwrite, 0, 0, 0)
+    ) -> Result<u64, HdfsErr> {
+        self.write_via_tmp_file(path, overwrite, |file| {
+            let chunk_size = self.io_chunk_size(file.get_file_status()?.block_size());
+            let mut buf = vec![0u8; chunk_size];
+            let mut copied = 0u64;
+            loop {
+                let read_len = match r.read(&mut buf) {
+                    Ok(0) => return Ok(copied),
+                    Ok(len) => len,
+                    Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                    Err(e) => return Err(HdfsErr::Source(e)),
+                };
+
+                file.write_chunked(&buf[..read_len], chunk_size)?;
+                copied += read_len as u64;
+            }
+        })
     }
 
-    pub fn create_with_params(
+    /// Write a file by ``write`` into a temporary file, which is renamed to the path
+    /// on success and deleted on failure
+    fn write_via_tmp_file<F>(
         &self,
         path: &str,
         overwrite: bool,
-        buf_size: i32,
-        replica_num: i16,
-        block_size: i32,
-    ) -> Result<HdfsFile, HdfsErr> {
-        if !overwrite && self.exist(path) {
-            return Err(HdfsErr::FileAlreadyExists(path.to_owned()));
+        write: F,
+    ) -> Result<u64, HdfsErr>
+    where
+        F: FnOnce(&mut HdfsFile) -> Result<u64, HdfsErr>,
+    {
+        match self.path_kind(path)? {
+            Some(FileKind::Directory) => {
+                return Err(HdfsErr::IsDirectory(path.to_owned()))
+            }
+            Some(FileKind::File) if !overwrite => {
+                return Err(HdfsErr::FileAlreadyExists(path.to_owned()))
+            }
+            _ => {}
         }
 
-        let file = unsafe {
-            let cstr_path = CString::new(path).unwrap();
-            hdfsOpenFile(
-                self.raw,
-                cstr_path.as_ptr(),
-                O_WRONLY,
-                buf_size as c_int,
-                replica_num as c_short,
-                block_size as tSize,
-            )
+        let tmp_path = tmp_path_of(path);
+        let written = match self.write_tmp_file(&tmp_path, write) {
+            Ok(written) => written,
+            Err(e) => {
+                self.discard_tmp_file(&tmp_path);
+                return Err(e);
+            }
         };
 
-        self.new_hdfs_file(path, file)
-    }
+        let renamed = if overwrite {
+            self.rename_overwrite(&tmp_path, path)
+        } else {
+            self.rename(&tmp_path, path)
+        };
+        if let Err(e) = renamed {
+            self.discard_tmp_file(&tmp_path);
+            return Err(e);
+        }
 
-    /// set permission
-    pub fn chmod(&self, path: &str, mode: i16) -> bool {
-        (unsafe {
-            let cstr_path = CString::new(path).unwrap();
-            hdfsChmod(self.raw, cstr_path.as_ptr(), mode as c_short)
-        }) == 0
-    }
-
-    pub fn chown(&self, path: &str, owner: &str, group: &str) -> bool {
-        (unsafe {
-            let cstr_path = CString::new(path).unwrap();
-            let cstr_owner = CString::new(owner).unwrap();
-            let cstr_group = CString::new(group).unwrap();
-            hdfsChown(
-                self.raw,
-                cstr_path.as_ptr(),
-                cstr_owner.as_ptr(),
-                cstr_group.as_ptr(),
-            )
-        }) == 0
+        Ok(written)
     }
 
-    /// Open a file for append
-    pub fn append(&self, path: &str) -> Result<HdfsFile, HdfsErr> {
-        if !self.exist(path) {
-            return Err(HdfsErr::FileNotFound(path.to_owned()));
+    fn discard_tmp_file(&self, tmp_path: &str) {
+        if let Err(e) = self.delete(tmp_path, false) {
+            warn!("Fail to delete temporary file {}: {:?}", tmp_path, e);
         }
+    }
 
-        let file = unsafe {
-            let cstr_path = CString::new(path).unwrap();
-            hdfsOpenFile(self.raw, cstr_path.as_ptr(), O_APPEND, 0, 0, 0)
-        };
+    fn write_tmp_file<F>(&self, tmp_path: &str, write: F) -> Result<u64, HdfsErr>
+    where
+        F: FnOnce(&mut HdfsFile) -> Result<u64, HdfsErr>,
+    {
+        let mut file = self.create_with_overwrite(tmp_path, true)?;
+        let written = write(&mut file)?;
+        file.close()?;
+        Ok(written)
+    }
 
-        self.new_hdfs_file(path, file)
+    /// Get the file status, including file size, last modified time, etc
+    pub fn get_file_status(&self, path: &str) -> Result<FileStatus, HdfsErr> {
+        self.file_status(path).map_err(|e| e.logged(path))
     }
 
-    /// create a directory
-    pub fn mkdir(&self, path: &str) -> Result<bool, HdfsErr> {
-        if unsafe {
-            let cstr_path = CString::new(path).unwrap();
-            hdfsCreateDirectory(self.raw, cstr_path.as_ptr())
-        } == 0
-        {
-            Ok(true)
-        } else {
-            Err(HdfsErr::Unknown)
+    /// Get the file status as ``get_file_status``, without logging the error at error,
+    /// for the probes handling a missing path
+    fn file_status(&self, path: &str) -> Result<FileStatus, HdfsErr> {
+        let ptr = unsafe {
+            let cstr_path = to_c_string(path)?;
+            native_call!(hdfsGetPathInfo(self.raw(), cstr_path.as_ptr()), path)
+        };
+
+        if ptr.is_null() {
+            return Err(HdfsErr::from_errno(path));
         }
+
+        let info_ptr = HdfsFileInfoPtr::new(ptr);
+        Ok(FileStatus::from_raw(unsafe { &*info_ptr.ptr }))
     }
 
-    /// Rename file.
-    pub fn rename(&self, old_path: &str, new_path: &str) -> Result<bool, HdfsErr> {
-        if unsafe {
-            let cstr_old_path = CString::new(old_path).unwrap();
-            let cstr_new_path = CString::new(new_path).unwrap();
-            hdfsRename(self.raw, cstr_old_path.as_ptr(), cstr_new_path.as_ptr())
-        } == 0
-        {
-            Ok(true)
-        } else {
-            Err(HdfsErr::Unknown)
+    /// Wait until the length of a file reaches ``min_len`` by polling its status every
+    /// ``poll_interval``, and return the length, or ``Timeout`` after ``timeout``.
+    ///
+    /// The length reported by the namenode for a file being written only grows when a
+    /// block is completed, or by ``hsync`` with the length updated, or when it's closed.
+    pub fn wait_for_length(
+        &self,
+        path: &str,
+        min_len: i64,
+        timeout: Duration,
+        poll_interval: Duration,
+    ) -> Result<i64, HdfsErr> {
+        // No deadline for a timeout too large, e.g. ``Duration::MAX`` to wait forever
+        let deadline = Instant::now().checked_add(timeout);
+        loop {
+            let len = self.get_file_status(path)?.len() as i64;
+            if len >= min_len {
+                return Ok(len);
+            }
+
+            let sleep = match deadline {
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        return Err(HdfsErr::Timeout(format!(
+                            "length of {} is {} rather than {} after {:?}",
+                            path, len, min_len, timeout
+                        )));
+                    }
+                    poll_interval.min(deadline - now)
+                }
+                None => poll_interval,
+            };
+            thread::sleep(sleep);
         }
     }
 
-    /// Set the replication of the specified file to the supplied value
-    pub fn set_replication(&self, path: &str, num: i16) -> Result<bool, HdfsErr> {
-        if unsafe {
-            let cstr_path = CString::new(path).unwrap();
-            hdfsSetReplication(self.raw, cstr_path.as_ptr(), num as i16)
-        } == 0
-        {
-            Ok(true)
-        } else {
-            Err(HdfsErr::Unknown)
-        }
+    /// Get the file status for each entry under the specified directory
+    pub fn list_status(&self, path: &str) -> Result<Vec<FileStatus>, HdfsErr> {
+        self.list_iter(path)?.collect()
     }
 
-    /// Delete file.
-    pub fn delete(&self, path: &str, recursive: bool) -> Result<bool, HdfsErr> {
-        if unsafe {
-            let cstr_path = CString::new(path).unwrap();
-            hdfsDelete(self.raw, cstr_path.as_ptr(), recursive as c_int)
-        } == 0
-        {
-            Ok(true)
-        } else {
-            Err(HdfsErr::Unknown)
-        }
+    /// Get the file status for each directory under the specified directory
+    pub fn list_dirs(&self, path: &str) -> Result<Vec<FileStatus>, HdfsErr> {
+        self.list_kind(path, FileKind::Directory)
     }
-}
 
-/// since HDFS client handles are completely thread safe, here we implement Send+Sync trait for HdfsFs
-unsafe impl Send for HdfsFs {}
+    /// Get the file status for each file under the specified directory
+    pub fn list_files(&self, path: &str) -> Result<Vec<FileStatus>, HdfsErr> {
+        self.list_kind(path, FileKind::File)
+    }
 
-unsafe impl Sync for HdfsFs {}
+    fn list_kind(&self, path: &str, kind: FileKind) -> Result<Vec<FileStatus>, HdfsErr> {
+        self.list_status_filtered(path, |status| status.kind() == kind)
+    }
 
-/// open hdfs file
-#[derive(Clone)]
-pub struct HdfsFile {
-    fs: HdfsFs,
-    path: String,
-    file: hdfsFile,
-    _marker: PhantomData<()>,
-}
+    /// Get the file status for each entry under the specified directory which satisfies
+    /// the predicate.
+    ///
+    /// Each native entry is copied into a single scratch status reusing its strings for
+    /// the predicate, and only the matching ones are cloned out of it, so no status is
+    /// allocated for the entries which are filtered out.
+    pub fn list_status_filtered<P>(
+        &self,
+        path: &str,
+        pred: P,
+    ) -> Result<Vec<FileStatus>, HdfsErr>
+    where
+        P: Fn(&FileStatus) -> bool,
+    {
+        let mut iter = self.list_file_status(path, false)?;
+        let mut scratch = FileStatus::empty();
+        let mut list = Vec::new();
+        while let Some(info) = iter.next_info() {
+            scratch.fill_from_raw(info);
+            if pred(&scratch) {
+                list.push(scratch.clone());
+            }
+        }
 
-impl Debug for HdfsFile {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct("HdfsFile")
-            .field("url", &self.fs.url)
-            .field("path", &self.path)
-            .finish()
+        Ok(list)
     }
-}
 
-impl HdfsFile {
-    /// Get HdfsFs
-    #[inline]
-    pub fn fs(&self) -> &HdfsFs {
-        &self.fs
+    /// Similar to ``list_status``, but fails with ``InvalidPath`` if the name of any
+    /// entry is not UTF-8, rather than replacing the invalid bytes with ``U+FFFD``.
+    pub fn list_status_strict(&self, path: &str) -> Result<Vec<FileStatus>, HdfsErr> {
+        self.list_file_status(path, true)?.collect()
     }
 
-    /// Return a file path
-    #[inline]
-    pub fn path(&self) -> &str {
-        &self.path
+    /// Get an iterator of the file status for each entry under the specified directory.
+    ///
+    /// The entries are fetched by libhdfs at once, but each of them is only converted
+    /// when iterated. The native entries are freed when the iterator is dropped.
+    pub fn list_iter(
+        &self,
+        path: &str,
+    ) -> Result<impl Iterator<Item = Result<FileStatus, HdfsErr>>, HdfsErr> {
+        self.list_file_status(path, false)
     }
 
-    pub fn available(&self) -> Result<bool, HdfsErr> {
-        if unsafe { hdfsAvailable(self.fs.raw, self.file) } == 0 {
-            Ok(true)
-        } else {
-            Err(HdfsErr::Unknown)
-        }
-    }
+    fn list_file_status(
+        &self,
+        path: &str,
+        strict: bool,
+    ) -> Result<FileStatusIter, HdfsErr> {
+        // libhdfs only sets the entry number on success
+        let mut entry_num: c_int = -1;
 
-    /// Close the opened file
-    pub fn close(&self) -> Result<bool, HdfsErr> {
-        if unsafe { hdfsCloseFile(self.fs.raw, self.file) } == 0 {
-            Ok(true)
-        } else {
-            Err(HdfsErr::Unknown)
+        let ptr = unsafe {
+            let cstr_path = to_c_string(path)?;
+            native_call!(
+                hdfsListDirectory(self.raw(), cstr_path.as_ptr(), &mut entry_num),
+                path
+            )
+        };
+
+        if ptr.is_null() {
+            // A null pointer is also returned for an empty directory
+            return if entry_num == 0 {
+                Ok(FileStatusIter {
+                    info_ptr: None,
+                    idx: 0,
+                    strict,
+                })
+            } else {
+                Err(HdfsErr::from_errno(path).logged(path))
+            };
         }
-    }
 
-    /// Flush the data.
-    pub fn flush(&self) -> bool {
-        (unsafe { hdfsFlush(self.fs.raw, self.file) }) == 0
+        Ok(FileStatusIter {
+            info_ptr: Some(HdfsFileInfoPtr::new_array(ptr, entry_num)),
+            idx: 0,
+            strict,
+        })
     }
 
-    /// Flush out the data in client's user buffer. After the return of this
-    /// call, new readers will see the data.
-    pub fn hflush(&self) -> bool {
-        (unsafe { hdfsHFlush(self.fs.raw, self.file) }) == 0
-    }
+    /// Get the file status for each entry of the whole subtree under the specified directory.
+    ///
+    /// The entries are listed depth-first, each directory followed by its descendants.
+    /// Symbolic links are not followed, and each directory is visited at most once.
+    pub fn list_status_recursive(&self, path: &str) -> Result<Vec<FileStatus>, HdfsErr> {
+        let mut visited = HashSet::new();
+        let mut list = Vec::new();
+        self.list_status_into(path, &mut visited, &mut list)?;
 
-    /// Similar to posix fsync, Flush out the data in client's
-    /// user buffer. all the way to the disk device (but the disk may have
-    /// it in its cache).
-    pub fn hsync(&self) -> bool {
-        (unsafe { hdfsHSync(self.fs.raw, self.file) }) == 0
+        Ok(list)
     }
 
-    /// Determine if a file is open for read.
-    pub fn is_readable(&self) -> bool {
-        (unsafe { hdfsFileIsOpenForRead(self.file) }) == 1
+    fn list_status_into(
+        &self,
+        path: &str,
+        visited: &mut HashSet<String>,
+        list: &mut Vec<FileStatus>,
+    ) -> Result<(), HdfsErr> {
+        for status in self.list_status(path)? {
+            let sub_dir =
+                if status.is_directory() && visited.insert(status.name().to_owned()) {
+                    Some(status.name().to_owned())
+                } else {
+                    None
+                };
+            list.push(status);
+
+            if let Some(sub_dir) = sub_dir {
+                self.list_status_into(&sub_dir, visited, list)?;
+            }
+        }
+
+        Ok(())
     }
 
-    /// Determine if a file is open for write.
-    pub fn is_writable(&self) -> bool {
-        (unsafe { hdfsFileIsOpenForWrite(self.file) }) == 1
+    /// Get the file status of each file under the directory, or of its whole subtree if
+    /// ``recursive``, which has been modified since the time, e.g. for an incremental ETL.
+    ///
+    /// The modification times of libhdfs are in seconds, so ``since`` is truncated to
+    /// seconds as well. A file modified within the same second as ``since`` is always
+    /// returned, so that no file is missed, though it may be returned twice by the
+    /// successive calls.
+    pub fn list_modified_since(
+        &self,
+        dir: &str,
+        since: SystemTime,
+        recursive: bool,
+    ) -> Result<Vec<FileStatus>, HdfsErr> {
+        let since = match since.duration_since(UNIX_EPOCH) {
+            Ok(elapsed) => UNIX_EPOCH + Duration::from_secs(elapsed.as_secs()),
+            Err(_) => since,
+        };
+        let modified =
+            |status: &FileStatus| status.is_file() && status.last_modified() >= since;
+
+        if recursive {
+            Ok(self
+                .list_status_recursive(dir)?
+                .into_iter()
+                .filter(modified)
+                .collect())
+        } else {
+            self.list_status_filtered(dir, modified)
+        }
     }
 
-    /// Get the file status, including file size, last modified time, etc
-    pub fn get_file_status(&self) -> Result<FileStatus, HdfsErr> {
-        self.fs.get_file_status(self.path())
+    /// Get the total size and the counts of the files and the directories of the subtree
+    /// under the path, like ``hdfs dfs -count``. The directory itself is counted as well.
+    ///
+    /// libhdfs has no call of the content summary, so it's computed by walking the tree.
+    pub fn content_summary(&self, path: &str) -> Result<ContentSummary, HdfsErr> {
+        let root = self.get_file_status(path)?;
+        if root.is_file() {
+            return Ok(ContentSummary {
+                length: root.len() as i64,
+                file_count: 1,
+                directory_count: 0,
+            });
+        }
+
+        let mut summary = ContentSummary {
+            length: 0,
+            file_count: 0,
+            directory_count: 1,
+        };
+        for status in self.list_status_recursive(path)? {
+            if status.is_directory() {
+                summary.directory_count += 1;
+            } else {
+                summary.file_count += 1;
+                summary.length += status.len() as i64;
+            }
+        }
+
+        Ok(summary)
     }
 
-    /// Get the current offset in the file, in bytes.
-    pub fn pos(&self) -> Result<u64, HdfsErr> {
-        let pos = unsafe { hdfsTell(self.fs.raw, self.file) };
+    /// Get the file status for each path matching the glob pattern, sorted by name.
+    ///
+    /// Each path segment may contain ``*``, ``?`` and ``[...]`` wildcards, which never
+    /// match across ``/``. An empty vector is returned if nothing matches.
+    pub fn glob(&self, pattern: &str) -> Result<Vec<FileStatus>, HdfsErr> {
+        // Keep the scheme and authority, e.g. hdfs://localhost:9000, untouched
+        let (prefix, path) = path::split_authority(pattern);
 
-        if pos > 0 {
-            Ok(pos as u64)
+        let root = if !prefix.is_empty() || path.starts_with('/') {
+            format!("{}/", prefix)
         } else {
-            Err(HdfsErr::Unknown)
+            String::new()
+        };
+        // Each candidate path with its status once it's known from a listing
+        let mut candidates: Vec<(String, Option<FileStatus>)> = vec![(root, None)];
+
+        for segment in path.split('/').filter(|s| !s.is_empty()) {
+            let mut next = Vec::new();
+
+            if glob::has_wildcard(segment) {
+                for (candidate, status) in candidates {
+                    let dir = if candidate.is_empty() {
+                        "."
+                    } else {
+                        &candidate
+                    };
+                    let is_directory = match status {
+                        Some(status) => status.is_directory(),
+                        None => match self.file_status(dir) {
+                            Ok(status) => status.is_directory(),
+                            Err(HdfsErr::FileNotFound(_)) => false,
+                            Err(e) => return Err(e.logged(dir)),
+                        },
+                    };
+                    if !is_directory {
+                        continue;
+                    }
+
+                    for child in self.list_status(dir)? {
+                        let name = child.name().rsplit('/').next().unwrap_or("");
+                        if glob::matches(segment, name) {
+                            next.push((child.name().to_owned(), Some(child)));
+                        }
+                    }
+                }
+            } else {
+                for (candidate, _) in candidates {
+                    let joined = if candidate.is_empty() || candidate.ends_with('/') {
+                        format!("{}{}", candidate, segment)
+                    } else {
+                        format!("{}/{}", candidate, segment)
+                    };
+                    next.push((joined, None));
+                }
+            }
+
+            if next.is_empty() {
+                return Ok(Vec::new());
+            }
+            candidates = next;
+        }
+
+        let mut list = Vec::new();
+        for (candidate, status) in candidates {
+            match status {
+                Some(status) => list.push(status),
+                None => match self.file_status(&candidate) {
+                    Ok(status) => list.push(status),
+                    Err(HdfsErr::FileNotFound(_)) => {}
+                    Err(e) => return Err(e.logged(&candidate)),
+                },
+            }
         }
+        list.sort_by(|a, b| a.name().cmp(b.name()));
+
+        Ok(list)
     }
 
-    /// Read data from an open file.
-    pub fn read(&self, buf: &mut [u8]) -> Result<i32, HdfsErr> {
-        let read_len = unsafe {
-            hdfsRead(
-                self.fs.raw,
-                self.file,
-                buf.as_ptr() as *mut c_void,
-                buf.len() as tSize,
-            )
-        };
+    /// Get the default block size of the filesystem.
+    pub fn default_block_size(&self) -> Result<i64, HdfsErr> {
+        let block_sz = unsafe { native_call!(hdfsGetDefaultBlockSize(self.raw())) };
 
-        if read_len > 0 {
-            Ok(read_len as i32)
+        if block_sz > 0 {
+            Ok(block_sz)
         } else {
-            Err(HdfsErr::Unknown)
+            Err(HdfsErr::from_errno(&self.url).logged(&self.url))
         }
     }
 
-    /// Positional read of data from an open file.
-    pub fn read_with_pos(&self, pos: i64, buf: &mut [u8]) -> Result<i32, HdfsErr> {
-        let read_len = unsafe {
-            hdfsPread(
-                self.fs.raw,
-                self.file,
-                pos as tOffset,
-                buf.as_ptr() as *mut c_void,
-                buf.len() as tSize,
+    /// Get the default block size at the filesystem indicated by a given path.
+    pub fn default_block_size_at_path(&self, path: &str) -> Result<i64, HdfsErr> {
+        let block_sz = unsafe {
+            let cstr_path = to_c_string(path)?;
+            native_call!(
+                hdfsGetDefaultBlockSizeAtPath(self.raw(), cstr_path.as_ptr()),
+                path
             )
         };
 
-        if read_len > 0 {
-            Ok(read_len as i32)
+        if block_sz > 0 {
+            Ok(block_sz)
         } else {
-            Err(HdfsErr::Unknown)
+            Err(HdfsErr::from_errno(path).logged(path))
         }
     }
 
-    /// Seek to given offset in file.
-    pub fn seek(&self, offset: u64) -> bool {
-        (unsafe { hdfsSeek(self.fs.raw, self.file, offset as tOffset) }) == 0
+    /// Get the block size of the specified file, which may differ from the default one
+    /// if the file was created with a custom block size. It's 0 for a directory.
+    pub fn block_size(&self, path: &str) -> Result<i64, HdfsErr> {
+        Ok(self.get_file_status(path)?.block_size() as i64)
     }
 
-    /// Write data into an open file.
-    pub fn write(&self, buf: &[u8]) -> Result<i32, HdfsErr> {
-        let written_len = unsafe {
-            hdfsWrite(
-                self.fs.raw,
-                self.file,
-                buf.as_ptr() as *mut c_void,
-                buf.len() as tSize,
-            )
-        };
+    /// Return the raw capacity of the filesystem.
+    /// It's only supported by a distributed filesystem rather than the local one.
+    pub fn capacity(&self) -> Result<i64, HdfsErr> {
+        self.check_distributed("capacity")?;
+        let capacity = unsafe { native_call!(hdfsGetCapacity(self.raw())) };
 
-        if written_len > 0 {
-            Ok(written_len)
+        if capacity >= 0 {
+            Ok(capacity)
         } else {
-            Err(HdfsErr::Unknown)
+            Err(HdfsErr::from_errno(&self.url).logged(&self.url))
         }
     }
-}
 
-/// since HdfsFile is only the pointer to the file on Hdfs, here we implement Send+Sync trait
-unsafe impl Send for HdfsFile {}
+    /// Return the total raw size of all files in the filesystem.
+    /// It's only supported by a distributed filesystem rather than the local one.
+    pub fn used(&self) -> Result<i64, HdfsErr> {
+        self.check_distributed("used")?;
+        let used = unsafe { native_call!(hdfsGetUsed(self.raw())) };
 
-unsafe impl Sync for HdfsFile {}
+        if used >= 0 {
+            Ok(used)
+        } else {
+            Err(HdfsErr::from_errno(&self.url).logged(&self.url))
+        }
+    }
 
-/// Interface that represents the client side information for a file or directory.
-pub struct FileStatus {
-    raw: Rc<HdfsFileInfoPtr>,
-    idx: u32,
-    _marker: PhantomData<()>,
-}
+    /// Get a df-style summary of the space of the filesystem.
+    /// It's only supported by a distributed filesystem rather than the local one.
+    pub fn stats(&self) -> Result<FsStats, HdfsErr> {
+        let capacity = self.capacity()?;
+        let used = self.used()?;
+        Ok(FsStats {
+            capacity,
+            used,
+            // The used space is reported separately, so it may exceed a shrunk capacity
+            remaining: (capacity - used).max(0),
+            default_block_size: self.default_block_size()?,
+        })
+    }
 
-impl FileStatus {
-    /// create FileStatus from *const hdfsFileInfo
-    #[inline]
-    fn new(ptr: *const hdfsFileInfo) -> FileStatus {
-        FileStatus {
-            raw: Rc::new(HdfsFileInfoPtr::new(ptr)),
-            idx: 0,
-            _marker: PhantomData,
+    /// libhdfs reports the space of the local disk rather than fails for the local filesystem
+    fn check_distributed(&self, operation: &str) -> Result<(), HdfsErr> {
+        match Url::parse(&self.url) {
+            Ok(url) if url.scheme() == LOCAL_FS_SCHEME => Err(HdfsErr::Unsupported(
+                format!("{} of the local filesystem {}", operation, self.url),
+            )),
+            _ => Ok(()),
         }
     }
 
-    /// create FileStatus from *const hdfsFileInfo which points
-    /// to dynamically allocated array.
-    #[inline]
-    fn from_array(raw: Rc<HdfsFileInfoPtr>, idx: u32) -> FileStatus {
-        FileStatus {
-            raw,
-            idx,
-            _marker: PhantomData,
+    /// Get the working directory of this connection, against which relative paths
+    /// are resolved
+    pub fn working_directory(&self) -> Result<String, HdfsErr> {
+        let mut buf: Vec<u8> = vec![0; 256];
+
+        loop {
+            let ptr = unsafe {
+                native_call!(hdfsGetWorkingDirectory(
+                    self.raw(),
+                    buf.as_mut_ptr() as *mut c_char,
+                    buf.len(),
+                ))
+            };
+
+            if !ptr.is_null() {
+                let dir = unsafe { CStr::from_ptr(ptr) };
+                return Ok(dir.to_string_lossy().into_owned());
+            }
+
+            // Retry with a larger buffer if it's truncated
+            match io::Error::last_os_error().raw_os_error() {
+                Some(libc::ENAMETOOLONG) => buf.resize(buf.len() * 2, 0),
+                _ => return Err(HdfsErr::from_errno(&self.url).logged(&self.url)),
+            }
         }
     }
 
-    /// Get the pointer to hdfsFileInfo
-    #[inline]
-    fn ptr(&self) -> *const hdfsFileInfo {
-        unsafe { self.raw.ptr.offset(self.idx as isize) }
+    /// Get the fully qualified real path of a path, whose symbolic links are all resolved,
+    /// as ``realpath``. Each component is looked up by the namenode, so a missing one
+    /// fails with ``FileNotFound``.
+    ///
+    /// It fails with ``SymlinkLoop`` if more than 32 links are followed, which is the
+    /// limit of Hadoop and is always reached by a loop of the links.
+    pub fn canonicalize(&self, path: &str) -> Result<String, HdfsErr> {
+        let qualified = self.qualify(path)?;
+        resolve_links(&qualified, |link| self.link_target(link))
     }
 
-    /// Get the name of the file
-    #[inline]
-    pub fn name(&self) -> &str {
-        let slice = unsafe { CStr::from_ptr((*self.ptr()).mName) }.to_bytes();
-        std::str::from_utf8(slice).unwrap()
-    }
+    /// Get the fully qualified target of a symbolic link, or ``None`` if the path is not
+    /// a link
+    fn link_target(&self, path: &str) -> Result<Option<String>, HdfsErr> {
+        let cstr_path = to_c_string(path)?;
+        let mut buf: Vec<u8> = vec![0; 256];
+
+        loop {
+            let res = unsafe {
+                native_call!(
+                    hdfsGetLinkTarget(
+                        self.raw(),
+                        cstr_path.as_ptr(),
+                        buf.as_mut_ptr() as *mut c_char,
+                        buf.len(),
+                    ),
+                    path
+                )
+            };
 
-    /// Is this a file?
-    #[inline]
-    pub fn is_file(&self) -> bool {
-        match unsafe { &*self.ptr() }.mKind {
-            tObjectKind::kObjectKindFile => true,
-            tObjectKind::kObjectKindDirectory => false,
+            match res {
+                0 => return Ok(None),
+                1 => {
+                    let target = unsafe { CStr::from_ptr(buf.as_ptr() as *const c_char) };
+                    return Ok(Some(target.to_string_lossy().into_owned()));
+                }
+                // Retry with a larger buffer if it's truncated
+                _ => match io::Error::last_os_error().raw_os_error() {
+                    Some(libc::ENAMETOOLONG) => buf.resize(buf.len() * 2, 0),
+                    _ => return Err(HdfsErr::from_errno(path).logged(path)),
+                },
+            }
         }
     }
 
-    /// Is this a directory?
-    #[inline]
-    pub fn is_directory(&self) -> bool {
-        match unsafe { &*self.ptr() }.mKind {
-            tObjectKind::kObjectKindFile => false,
-            tObjectKind::kObjectKindDirectory => true,
+    /// Get the fully qualified uri of a path, e.g. ``hdfs://host:9000/user/a/b`` for ``b``,
+    /// as ``Path.makeQualified`` of Hadoop, see ``path`` for the rules.
+    ///
+    /// The path is qualified by the working directory, which is kept by the client,
+    /// so it doesn't access the namenode, nor check whether the path exists.
+    pub fn qualify(&self, path: &str) -> Result<String, HdfsErr> {
+        if path.is_empty() {
+            return Err(HdfsErr::InvalidPath(path.to_owned()));
         }
+        Ok(path::qualify(&self.working_directory()?, path))
     }
 
-    /// Get the owner of the file
-    #[inline]
-    pub fn owner(&self) -> &str {
-        let slice = unsafe { CStr::from_ptr((*self.ptr()).mOwner) }.to_bytes();
-        std::str::from_utf8(slice).unwrap()
-    }
-
-    /// Get the group associated with the file
-    #[inline]
-    pub fn group(&self) -> &str {
-        let slice = unsafe { CStr::from_ptr((*self.ptr()).mGroup) }.to_bytes();
-        std::str::from_utf8(slice).unwrap()
+    /// Set the working directory of this connection, against which relative paths
+    /// are resolved
+    pub fn set_working_directory(&self, path: &str) -> Result<bool, HdfsErr> {
+        if unsafe {
+            let cstr_path = to_c_string(path)?;
+            native_call!(
+                hdfsSetWorkingDirectory(self.raw(), cstr_path.as_ptr()),
+                path
+            )
+        } == 0
+        {
+            Ok(true)
+        } else {
+            Err(HdfsErr::from_errno(path).logged(path))
+        }
     }
 
-    /// Get the permissions associated with the file
-    #[inline]
-    pub fn permission(&self) -> i16 {
-        unsafe { &*self.ptr() }.mPermissions as i16
+    /// Checks if a given path exsits on the filesystem
+    pub fn exist(&self, path: &str) -> bool {
+        // An invalid path doesn't exist
+        let cstr_path = match to_c_string(path) {
+            Ok(cstr_path) => cstr_path,
+            Err(_) => return false,
+        };
+        (unsafe { native_call!(hdfsExists(self.raw(), cstr_path.as_ptr()), path) } == 0)
     }
 
-    /// Get the length of this file, in bytes.
-    #[allow(clippy::len_without_is_empty)]
-    #[inline]
-    pub fn len(&self) -> usize {
-        unsafe { &*self.ptr() }.mSize as usize
-    }
+    /// Check if each of the given paths exists, with the results in the input order.
+    ///
+    /// libhdfs has no batch primitive, so a large batch is checked by a few threads
+    /// sharing this connection, each for a contiguous chunk of the paths.
+    pub fn exist_many(&self, paths: &[&str]) -> Vec<bool> {
+        if paths.len() < EXIST_PARALLEL_THRESHOLD {
+            return paths.iter().map(|path| self.exist(path)).collect();
+        }
 
-    /// Get the block size of the file.
-    #[inline]
-    pub fn block_size(&self) -> usize {
-        unsafe { &*self.ptr() }.mBlockSize as usize
+        let threads = EXIST_MAX_THREADS.min(paths.len() / (EXIST_PARALLEL_THRESHOLD / 2));
+        let chunk_size = paths.len().div_ceil(threads);
+        thread::scope(|scope| {
+            let handles: Vec<_> = paths
+                .chunks(chunk_size)
+                .map(|chunk| {
+                    scope.spawn(move || {
+                        chunk
+                            .iter()
+                            .map(|path| self.exist(path))
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .flat_map(|handle| handle.join().unwrap())
+                .collect()
+        })
     }
 
-    /// Get the replication factor of a file.
-    #[inline]
-    pub fn replica_count(&self) -> i16 {
-        unsafe { &*self.ptr() }.mReplication as i16
+    /// Get the kind of the path, or ``None`` if it doesn't exist,
+    /// with a single round-trip to the namenode.
+    pub fn path_kind(&self, path: &str) -> Result<Option<FileKind>, HdfsErr> {
+        match self.file_status(path) {
+            Ok(status) => Ok(Some(status.kind())),
+            Err(HdfsErr::FileNotFound(_)) => Ok(None),
+            Err(e) => Err(e.logged(path)),
+        }
     }
 
-    /// Get the last modification time for the file in seconds
-    #[inline]
-    pub fn last_modified(&self) -> time_t {
-        unsafe { &*self.ptr() }.mLastMod
-    }
+    /// Get the hostnames of the datanodes storing each block of a file in the range
+    /// of ``[start, start + length)``. Due to replication, a single block could be present
+    /// on multiple hosts.
+    pub fn get_hosts(
+        &self,
+        path: &str,
+        start: i64,
+        length: i64,
+    ) -> Result<Vec<Vec<String>>, HdfsErr> {
+        if start < 0 || length < 0 {
+            return Err(HdfsErr::InvalidArgument(format!(
+                "invalid range with start {} and length {} for {}",
+                start, length, path
+            )));
+        }
+        if length == 0 {
+            return Ok(Vec::new());
+        }
 
-    /// Get the last access time for the file in seconds
-    #[inline]
-    pub fn last_access(&self) -> time_t {
-        unsafe { &*self.ptr() }.mLastAccess
-    }
-}
+        let ptr = unsafe {
+            let cstr_path = to_c_string(path)?;
+            native_call!(
+                hdfsGetHosts(
+                    self.raw(),
+                    cstr_path.as_ptr(),
+                    start as tOffset,
+                    length as tOffset,
+                ),
+                path
+            )
+        };
+
+        if ptr.is_null() {
+            return Err(HdfsErr::from_errno(path).logged(path));
+        }
+
+        // Both the block array and each host array are terminated by NULL
+        let mut blocks = Vec::new();
+        unsafe {
+            let mut block_idx = 0;
+            while !(*ptr.offset(block_idx)).is_null() {
+                let host_ptr = *ptr.offset(block_idx);
+                let mut hosts = Vec::new();
+                let mut host_idx = 0;
+                while !(*host_ptr.offset(host_idx)).is_null() {
+                    hosts.push(
+                        CStr::from_ptr(*host_ptr.offset(host_idx))
+                            .to_string_lossy()
+                            .into_owned(),
+                    );
+                    host_idx += 1;
+                }
+                blocks.push(hosts);
+                block_idx += 1;
+            }
+            native_call!(hdfsFreeHosts(ptr));
+        }
+
+        Ok(blocks)
+    }
+
+    /// Get the location of each block of a file, with the range of the block in the
+    /// file and the hosts storing it, e.g. to plan the splits of the file.
+    ///
+    /// The blocks are fetched in strides of the block size of the file, where the last
+    /// block may be shorter. An empty file has no block.
+    pub fn block_locations(&self, path: &str) -> Result<Vec<BlockLocation>, HdfsErr> {
+        let status = self.get_file_status(path)?;
+        if status.is_directory() {
+            return Err(HdfsErr::IsDirectory(path.to_owned()));
+        }
+
+        let len = status.len() as i64;
+        let block_size = (status.block_size() as i64).max(1);
+        let mut locations = Vec::new();
+        let mut offset = 0;
+        while offset < len {
+            let length = block_size.min(len - offset);
+            let hosts = self
+                .get_hosts(path, offset, length)?
+                .into_iter()
+                .next()
+                .unwrap_or_default();
+            locations.push(BlockLocation {
+                offset,
+                length,
+                hosts,
+            });
+            offset += length;
+        }
+
+        Ok(locations)
+    }
+
+    /// Get a reader of each block of a file with its location, e.g. to read the blocks
+    /// in parallel by the workers on the hosts storing them.
+    ///
+    /// All of the readers share the file opened for read by ``pread``, which is closed
+    /// when the last of them is dropped.
+    pub fn block_readers(
+        &self,
+        path: &str,
+    ) -> Result<Vec<(BlockLocation, HdfsSliceReader)>, HdfsErr> {
+        let locations = self.block_locations(path)?;
+        let file = Arc::new(self.open(path)?);
+        Ok(locations
+            .into_iter()
+            .map(|location| {
+                let reader = HdfsSliceReader::new(
+                    file.clone(),
+                    location.offset as u64,
+                    location.length as u64,
+                );
+                (location, reader)
+            })
+            .collect())
+    }
+
+    #[inline]
+    pub fn create(&self, path: &str) -> Result<HdfsFile, HdfsErr> {
+        self.create_with_params(path, false, 0, 0, 0)
+    }
+
+    #[inline]
+    pub fn create_with_overwrite(
+        &self,
+        path: &str,
+        overwrite: bool,
+    ) -> Result<HdfsFile, HdfsErr> {
+        self.create_with_params(path, overwrite, 0, 0, 0)
+    }
+
+    /// Create a new file only if the path doesn't exist, otherwise
+    /// ``HdfsErr::FileAlreadyExists`` is returned.
+    ///
+    /// The check is done by the namenode atomically with the creation, so only one of
+    /// the concurrent callers creating the same path succeeds.
+    pub fn create_new(&self, path: &str) -> Result<HdfsFile, HdfsErr> {
+        self.create_with_params(path, false, 0, 0, 0)
+    }
+
+    pub fn create_with_params(
+        &self,
+        path: &str,
+        overwrite: bool,
+        buf_size: i32,
+        replica_num: i16,
+        block_size: i32,
+    ) -> Result<HdfsFile, HdfsErr> {
+        // The namenode rejects to create an existing file atomically
+        let flags = if overwrite {
+            O_WRONLY
+        } else {
+            O_WRONLY | O_EXCL
+        };
+        let file = unsafe {
+            let cstr_path = to_c_string(path)?;
+            native_call!(
+                hdfsOpenFile(
+                    self.raw(),
+                    cstr_path.as_ptr(),
+                    flags,
+                    buf_size as c_int,
+                    replica_num as c_short,
+                    block_size as tSize,
+                ),
+                path
+            )
+        };
+
+        self.new_hdfs_file(path, file)
+    }
+
+    /// Create a new file with both the replication and the block size, which are passed
+    /// to ``hdfsOpenFile`` at once rather than set after the file is created.
+    ///
+    /// It fails with ``InvalidArgument`` for a zero replication or a block size which is
+    /// not positive, unlike ``open_with_params`` taking them as the defaults.
+    pub fn create_configured(
+        &self,
+        path: &str,
+        replication: u16,
+        block_size: i64,
+    ) -> Result<HdfsFile, HdfsErr> {
+        if replication == 0 {
+            return Err(HdfsErr::InvalidArgument(format!(
+                "zero replication to create {}",
+                path
+            )));
+        }
+        if block_size <= 0 {
+            return Err(HdfsErr::InvalidArgument(format!(
+                "non-positive block size {} to create {}",
+                block_size, path
+            )));
+        }
+
+        self.open_with_params(path, O_WRONLY | O_EXCL, 0, replication, block_size)
+    }
+
+    /// Set the permission of the specified file or directory.
+    ///
+    /// The mode is the permission bits as an integer, so ``rwxr-xr-x`` should be passed
+    /// as the octal literal ``0o755`` rather than the decimal ``755``.
+    pub fn chmod(&self, path: &str, mode: i16) -> Result<bool, HdfsErr> {
+        if unsafe {
+            let cstr_path = to_c_string(path)?;
+            native_call!(
+                hdfsChmod(self.raw(), cstr_path.as_ptr(), mode as c_short),
+                path
+            )
+        } == 0
+        {
+            Ok(true)
+        } else {
+            Err(HdfsErr::from_errno(path).logged(path))
+        }
+    }
+
+    /// Set the typed permissions of the specified file or directory, see ``chmod``
+    pub fn set_permissions(
+        &self,
+        path: &str,
+        permissions: &Permissions,
+    ) -> Result<bool, HdfsErr> {
+        self.chmod(path, permissions.mode())
+    }
+
+    /// Set the owner and group of the specified file or directory.
+    ///
+    /// ``None`` leaves the owner or group unchanged, and nothing is done if both are ``None``.
+    pub fn chown(
+        &self,
+        path: &str,
+        owner: Option<&str>,
+        group: Option<&str>,
+    ) -> Result<bool, HdfsErr> {
+        if owner.is_none() && group.is_none() {
+            return Ok(true);
+        }
+        if owner == Some("") || group == Some("") {
+            return Err(HdfsErr::InvalidArgument(format!(
+                "empty owner or group for {}",
+                path
+            )));
+        }
+
+        let cstr_owner = owner.map(to_c_string).transpose()?;
+        let cstr_group = group.map(to_c_string).transpose()?;
+        if unsafe {
+            let cstr_path = to_c_string(path)?;
+            native_call!(
+                hdfsChown(
+                    self.raw(),
+                    cstr_path.as_ptr(),
+                    cstr_owner
+                        .as_ref()
+                        .map_or(ptr::null(), |owner| owner.as_ptr()),
+                    cstr_group
+                        .as_ref()
+                        .map_or(ptr::null(), |group| group.as_ptr()),
+                ),
+                path
+            )
+        } == 0
+        {
+            Ok(true)
+        } else {
+            Err(HdfsErr::from_errno(path).logged(path))
+        }
+    }
+
+    /// Set the modification and access times of the specified file or directory.
+    ///
+    /// Both times are in seconds since the epoch, as libhdfs expects, rather than the
+    /// milliseconds used by Hadoop internally. ``-1`` leaves the corresponding time unchanged.
+    pub fn set_times(&self, path: &str, mtime: i64, atime: i64) -> Result<bool, HdfsErr> {
+        if unsafe {
+            let cstr_path = to_c_string(path)?;
+            native_call!(
+                hdfsUtime(
+                    self.raw(),
+                    cstr_path.as_ptr(),
+                    mtime as tTime,
+                    atime as tTime,
+                ),
+                path
+            )
+        } == 0
+        {
+            Ok(true)
+        } else {
+            Err(HdfsErr::from_errno(path).logged(path))
+        }
+    }
+
+    /// Open an existing file for write at its end
+    pub fn append(&self, path: &str) -> Result<HdfsFile, HdfsErr> {
+        if !self.exist(path) {
+            return Err(HdfsErr::FileNotFound(path.to_owned()));
+        }
+
+        // libhdfs opens the file for read unless O_WRONLY is set
+        let file = unsafe {
+            let cstr_path = to_c_string(path)?;
+            native_call!(
+                hdfsOpenFile(
+                    self.raw(),
+                    cstr_path.as_ptr(),
+                    O_WRONLY | O_APPEND,
+                    0,
+                    0,
+                    0
+                ),
+                path
+            )
+        };
+
+        self.new_hdfs_file(path, file)
+    }
+
+    /// create a directory
+    pub fn mkdir(&self, path: &str) -> Result<bool, HdfsErr> {
+        if unsafe {
+            let cstr_path = to_c_string(path)?;
+            native_call!(hdfsCreateDirectory(self.raw(), cstr_path.as_ptr()), path)
+        } == 0
+        {
+            Ok(true)
+        } else {
+            Err(HdfsErr::from_errno(path).logged(path))
+        }
+    }
+
+    /// Create a directory with its missing parents, and then set its permission to ``mode``
+    /// regardless of the umask, even if it exists already.
+    /// The missing parents are created with the default permission.
+    pub fn mkdir_with_mode(&self, path: &str, mode: i16) -> Result<bool, HdfsErr> {
+        self.mkdir(path)?;
+        self.chmod(path, mode)
+    }
+
+    /// Rename a file or directory within this filesystem atomically.
+    ///
+    /// Following the HDFS semantics, if ``new_path`` is an existing directory, the source is
+    /// moved into it as ``new_path/<source name>``. ``FileAlreadyExists`` is returned if the
+    /// final destination exists already. Use ``HdfsUtil::mv`` to move across filesystems.
+    pub fn rename(&self, old_path: &str, new_path: &str) -> Result<bool, HdfsErr> {
+        if unsafe {
+            let cstr_old_path = to_c_string(old_path)?;
+            let cstr_new_path = to_c_string(new_path)?;
+            native_call!(
+                hdfsRename(self.raw(), cstr_old_path.as_ptr(), cstr_new_path.as_ptr()),
+                old_path,
+                new_path
+            )
+        } == 0
+        {
+            return Ok(true);
+        }
+
+        let err = HdfsErr::from_errno(old_path);
+        // HDFS only reports a failed rename without the reason
+        if let HdfsErr::Io(libc::EIO) = err {
+            if let Some(dst) = self.existing_rename_destination(old_path, new_path) {
+                return Err(HdfsErr::FileAlreadyExists(dst).logged(old_path));
+            }
+        }
+        Err(err.logged(old_path))
+    }
+
+    /// Rename a file or directory, replacing the destination atomically if it exists,
+    /// which fails for a non-empty directory. Different from ``rename``, the source is
+    /// never moved into the destination directory.
+    ///
+    /// It's atomic for HDFS, while the other filesystems may delete the destination
+    /// before the rename.
+    pub fn rename_overwrite(
+        &self,
+        old_path: &str,
+        new_path: &str,
+    ) -> Result<bool, HdfsErr> {
+        if unsafe {
+            let cstr_old_path = to_c_string(old_path)?;
+            let cstr_new_path = to_c_string(new_path)?;
+            native_call!(
+                hdfsRenameOverwrite(
+                    self.raw(),
+                    cstr_old_path.as_ptr(),
+                    cstr_new_path.as_ptr()
+                ),
+                old_path,
+                new_path
+            )
+        } == 0
+        {
+            Ok(true)
+        } else {
+            Err(HdfsErr::from_errno(old_path).logged(old_path))
+        }
+    }
+
+    /// Replace the content of a file atomically, so that a reader sees either the old
+    /// content or the new one, but never a partial one. The file is created if missing.
+    ///
+    /// The data is written into a unique temporary file next to the path, in chunks as
+    /// ``write_all``, which is synced by ``hsync`` and then renamed over the path by
+    /// ``rename_overwrite``.
+    /// The temporary file is deleted on failure.
+    pub fn overwrite_atomic(&self, path: &str, data: &[u8]) -> Result<(), HdfsErr> {
+        if self.path_kind(path)? == Some(FileKind::Directory) {
+            return Err(HdfsErr::IsDirectory(path.to_owned()));
+        }
+
+        let tmp_path = tmp_path_of(path);
+        let written = self.write_tmp_file(&tmp_path, |file| {
+            let chunk_size = self.io_chunk_size(file.get_file_status()?.block_size());
+            file.write_chunked(data, chunk_size)?;
+            file.hsync()?;
+            Ok(data.len() as u64)
+        });
+
+        if let Err(e) = written.and_then(|_| self.rename_overwrite(&tmp_path, path)) {
+            self.discard_tmp_file(&tmp_path);
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    /// Move each file matching the glob pattern into the directory, which is created if
+    /// it's missing, and return the number of the files moved, like ``hdfs dfs -mv``.
+    ///
+    /// Directories matching the pattern are not moved. It fails with ``FileAlreadyExists``
+    /// before moving any file if a destination exists or two files have the same name,
+    /// except for a file which is already in the directory, which is left untouched.
+    pub fn move_glob(&self, pattern: &str, dst_dir: &str) -> Result<u64, HdfsErr> {
+        let files: Vec<FileStatus> = self
+            .glob(pattern)?
+            .into_iter()
+            .filter(|status| status.is_file())
+            .collect();
+        self.mkdir(dst_dir)?;
+
+        let mut names = HashSet::new();
+        let mut moves = Vec::new();
+        for status in files.iter() {
+            let name = path::file_name(status.name()).unwrap_or_default();
+            let dst = path::join(dst_dir, &name);
+            if !names.insert(name) {
+                return Err(HdfsErr::FileAlreadyExists(dst));
+            }
+
+            match self.file_status(&dst) {
+                // The listed names are fully qualified
+                Ok(existing) if existing.name() == status.name() => continue,
+                Ok(_) => return Err(HdfsErr::FileAlreadyExists(dst)),
+                Err(HdfsErr::FileNotFound(_)) => {}
+                Err(e) => return Err(e.logged(&dst)),
+            }
+            moves.push((status.name(), dst));
+        }
+
+        for (src, dst) in moves.iter() {
+            self.rename(src, dst)?;
+        }
+        Ok(moves.len() as u64)
+    }
+
+    /// Rename each file directly under the directory to the name given by the closure,
+    /// within the same directory, and return the number of the files renamed.
+    ///
+    /// The closure is called with the name of each file, and returns ``None`` or the same
+    /// name to leave it untouched. It fails before renaming any file with
+    /// ``InvalidArgument`` for a new name which is not a single path segment, or with
+    /// ``FileAlreadyExists`` if two files get the same name, or a new name is taken by
+    /// any entry of the directory, including a file which is renamed too.
+    pub fn rename_each<F>(&self, dir: &str, f: F) -> Result<u64, HdfsErr>
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        let entries = self.list_status(dir)?;
+        let existing: HashSet<String> = entries
+            .iter()
+            .filter_map(|status| path::file_name(status.name()))
+            .collect();
+
+        let mut new_names = HashSet::new();
+        let mut renames = Vec::new();
+        for status in entries.iter().filter(|status| status.is_file()) {
+            let name = path::file_name(status.name()).unwrap_or_default();
+            let new_name = match f(&name) {
+                Some(new_name) if new_name != name => new_name,
+                _ => continue,
+            };
+            if new_name.is_empty()
+                || new_name.contains('/')
+                || new_name == "."
+                || new_name == ".."
+            {
+                return Err(HdfsErr::InvalidArgument(format!(
+                    "invalid new name {:?} of {}",
+                    new_name,
+                    status.name()
+                )));
+            }
+
+            let dst = path::join(dir, &new_name);
+            if existing.contains(&new_name) || !new_names.insert(new_name) {
+                return Err(HdfsErr::FileAlreadyExists(dst));
+            }
+            renames.push((status.name(), dst));
+        }
+
+        for (src, dst) in renames.iter() {
+            self.rename(src, dst)?;
+        }
+        Ok(renames.len() as u64)
+    }
+
+    fn existing_rename_destination(
+        &self,
+        old_path: &str,
+        new_path: &str,
+    ) -> Option<String> {
+        let status = self.file_status(new_path).ok()?;
+        if !status.is_directory() {
+            return Some(new_path.to_owned());
+        }
+
+        let name = old_path.trim_end_matches('/').rsplit('/').next()?;
+        let dst = format!("{}/{}", new_path.trim_end_matches('/'), name);
+        if self.exist(&dst) {
+            Some(dst)
+        } else {
+            None
+        }
+    }
+
+    /// Concatenate the source files into the end of the existing target file in order,
+    /// and the source files will be removed.
+    ///
+    /// HDFS requires all of the files to have the same block size, and each of them
+    /// except the last source to consist of full blocks only.
+    pub fn concat(&self, target: &str, sources: &[&str]) -> Result<bool, HdfsErr> {
+        if sources.is_empty() {
+            return Err(HdfsErr::InvalidArgument(format!(
+                "no source files to concatenate into {}",
+                target
+            )));
+        }
+
+        // The CStrings must outlive the null-terminated pointer array
+        let cstr_sources = sources
+            .iter()
+            .map(|source| to_c_string(source))
+            .collect::<Result<Vec<CString>, HdfsErr>>()?;
+        let mut source_ptrs: Vec<*const c_char> = cstr_sources
+            .iter()
+            .map(|source| source.as_ptr())
+            .chain(iter::once(ptr::null()))
+            .collect();
+
+        if unsafe {
+            let cstr_target = to_c_string(target)?;
+            native_call!(
+                hdfsConcat(self.raw(), cstr_target.as_ptr(), source_ptrs.as_mut_ptr()),
+                target,
+                sources
+            )
+        } == 0
+        {
+            Ok(true)
+        } else {
+            Err(HdfsErr::from_errno(target).logged(target))
+        }
+    }
+
+    /// Set the replication of the specified file to the supplied value,
+    /// which should be in the range of ``1..=i16::MAX``
+    pub fn set_replication(&self, path: &str, replication: u16) -> Result<bool, HdfsErr> {
+        if replication == 0 || replication > i16::MAX as u16 {
+            return Err(HdfsErr::InvalidArgument(format!(
+                "invalid replication {} for {}",
+                replication, path
+            )));
+        }
+
+        if unsafe {
+            let cstr_path = to_c_string(path)?;
+            native_call!(
+                hdfsSetReplication(self.raw(), cstr_path.as_ptr(), replication as i16),
+                path
+            )
+        } == 0
+        {
+            Ok(true)
+        } else {
+            Err(HdfsErr::from_errno(path).logged(path))
+        }
+    }
+
+    /// Set the replication of every file in the subtree under the path, or of the path
+    /// itself if it's a file, and return the number of the files changed.
+    ///
+    /// Directories are skipped since they have no replication. A file that fails to
+    /// be changed doesn't stop the others, and the first error is returned at the end.
+    pub fn set_replication_recursive(
+        &self,
+        path: &str,
+        replication: u16,
+    ) -> Result<u64, HdfsErr> {
+        let root = self.get_file_status(path)?;
+        if root.is_file() {
+            return self.set_replication(path, replication).map(|_| 1);
+        }
+
+        let mut changed = 0;
+        let mut first_err = None;
+        for status in self.list_status_recursive(path)? {
+            if status.is_directory() {
+                continue;
+            }
+            match self.set_replication(status.name(), replication) {
+                Ok(_) => changed += 1,
+                // The replication is invalid for all of the files
+                Err(e @ HdfsErr::InvalidArgument(_)) => return Err(e),
+                Err(e) => {
+                    warn!("Fail to set the replication of {}: {:?}", status.name(), e);
+                    first_err.get_or_insert(e);
+                }
+            }
+        }
+
+        match first_err {
+            Some(e) => Err(e),
+            None => Ok(changed),
+        }
+    }
+
+    /// Truncate the specified file to the new length.
+    ///
+    /// Return ``true`` if the file has been truncated and can be reused immediately, e.g.
+    /// for append. Return ``false`` if the NameNode has started to recover the last block
+    /// in background, and the following updates of the file should wait for it to complete.
+    pub fn truncate(&self, path: &str, new_length: i64) -> Result<bool, HdfsErr> {
+        if new_length < 0 {
+            return Err(HdfsErr::InvalidArgument(format!(
+                "negative length {} to truncate {}",
+                new_length, path
+            )));
+        }
+
+        match unsafe {
+            let cstr_path = to_c_string(path)?;
+            native_call!(
+                hdfsTruncateFile(self.raw(), cstr_path.as_ptr(), new_length as tOffset),
+                path
+            )
+        } {
+            1 => Ok(true),
+            0 => Ok(false),
+            _ => Err(HdfsErr::from_errno(path).logged(path)),
+        }
+    }
+
+    /// Delete a file or a directory, and return whether anything was deleted,
+    /// i.e. ``false`` if the path doesn't exist.
+    ///
+    /// A non-empty directory is only deleted recursively, otherwise
+    /// ``HdfsErr::DirectoryNotEmpty`` is returned.
+    pub fn delete(&self, path: &str, recursive: bool) -> Result<bool, HdfsErr> {
+        if unsafe {
+            let cstr_path = to_c_string(path)?;
+            native_call!(
+                hdfsDelete(self.raw(), cstr_path.as_ptr(), recursive as c_int),
+                path
+            )
+        } == 0
+        {
+            return Ok(true);
+        }
+
+        let err = HdfsErr::from_errno(path);
+        // HDFS reports a missing path or a non-empty directory without a dedicated errno
+        let err = match self.file_status(path) {
+            Err(HdfsErr::FileNotFound(_)) => return Ok(false),
+            Ok(status) if !recursive && status.is_directory() => {
+                match self.list_iter(path).map(|mut iter| iter.next().is_some()) {
+                    Ok(true) => HdfsErr::DirectoryNotEmpty(path.to_owned()),
+                    _ => err,
+                }
+            }
+            _ => err,
+        };
+        Err(err.logged(path))
+    }
+
+    /// Get the trash directory of the user, ``/user/<user>/.Trash``, as the default trash
+    /// policy of HDFS.
+    ///
+    /// The user is the one connected as, or the one of the current process given by
+    /// ``HADOOP_USER_NAME`` or ``USER`` as the simple authentication of Hadoop.
+    pub fn trash_root(&self) -> Result<String, HdfsErr> {
+        let user = match &self.user {
+            Some(user) => user.clone(),
+            None => env::var("HADOOP_USER_NAME")
+                .or_else(|_| env::var("USER"))
+                .map_err(|_| {
+                    HdfsErr::Unsupported(format!(
+                        "trash of an unknown user on {}",
+                        self.url
+                    ))
+                })?,
+        };
+        Ok(format!("/user/{}/.Trash", user))
+    }
+
+    /// Move a file or a directory into the trash rather than delete it permanently,
+    /// and return whether anything was moved, i.e. ``false`` if the path doesn't exist.
+    ///
+    /// As ``hdfs dfs -rm``, the path is moved to ``<trash root>/Current/<path>``, and the
+    /// current time in milliseconds is appended to its name if the destination exists
+    /// already. The trash is emptied by the namenode after the retention configured by
+    /// ``fs.trash.interval`` there. A path in the trash can't be moved into it again.
+    pub fn move_to_trash(&self, path: &str) -> Result<bool, HdfsErr> {
+        let (_, dir_path) = path::split_authority(path);
+        let abs_path = if dir_path.starts_with('/') {
+            path::normalize(dir_path)
+        } else {
+            let working_dir = self.working_directory()?;
+            path::normalize(path::split_authority(&path::join(&working_dir, dir_path)).1)
+        };
+
+        let trash_root = self.trash_root()?;
+        if abs_path == "/" {
+            return Err(HdfsErr::InvalidArgument(format!(
+                "cannot move the root of {} to the trash",
+                self.url
+            )));
+        }
+        if abs_path == trash_root || abs_path.starts_with(&format!("{}/", trash_root)) {
+            return Err(HdfsErr::InvalidArgument(format!(
+                "{} is in the trash already",
+                path
+            )));
+        }
+        if !self.exist(path) {
+            return Ok(false);
+        }
+
+        let mut trash_path = format!("{}/Current{}", trash_root, abs_path);
+        if let Some(parent) = path::parent(&trash_path) {
+            self.mkdir(&parent)?;
+        }
+        if self.exist(&trash_path) {
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |now| now.as_millis());
+            trash_path = format!("{}{}", trash_path, now);
+        }
+        self.rename(path, &trash_path)
+    }
+
+    /// Delete a file or a directory recursively without stopping at the first failure,
+    /// and return every path which fails to be deleted or listed.
+    ///
+    /// The descendants are deleted before their directories, and a directory with any
+    /// descendant failing to be deleted is left untouched rather than reported again.
+    /// A directory failing to be listed is reported, and its subtree is skipped.
+    pub fn delete_collect_errors(
+        &self,
+        path: &str,
+    ) -> Result<(), Vec<(String, HdfsErr)>> {
+        let root = self
+            .get_file_status(path)
+            .map_err(|e| vec![(path.to_owned(), e)])?;
+
+        let mut failures: Vec<(String, HdfsErr)> = Vec::new();
+        self.delete_collect_into(&root, &mut failures);
+
+        if failures.is_empty() {
+            Ok(())
+        } else {
+            Err(failures)
+        }
+    }
+
+    /// Delete the entry after its descendants, and return whether it's deleted
+    fn delete_collect_into(
+        &self,
+        status: &FileStatus,
+        failures: &mut Vec<(String, HdfsErr)>,
+    ) -> bool {
+        let name = status.name();
+        if status.is_directory() {
+            let children = match self.list_status(name) {
+                Ok(children) => children,
+                Err(e) => {
+                    failures.push((name.to_owned(), e));
+                    return false;
+                }
+            };
+            let mut all_deleted = true;
+            for child in children.iter() {
+                all_deleted &= self.delete_collect_into(child, failures);
+            }
+            if !all_deleted {
+                return false;
+            }
+        }
+
+        match self.delete(name, false) {
+            Ok(_) => true,
+            Err(e) => {
+                failures.push((name.to_owned(), e));
+                false
+            }
+        }
+    }
+}
+
+/// open hdfs file, which is closed when dropped if it's not closed explicitly
+///
+/// It's ``Send``, so that a file can be opened by one thread and used by another.
+/// It's also ``Sync``, but only the positional reads, i.e. ``pread`` and ``read_with_pos``,
+/// and the queries of the file are safe to call from threads sharing it.
+/// The operations on the file cursor, i.e. ``read``, ``write`` and ``seek``, take
+/// ``&mut self``, since the native stream is not safe for concurrent use.
+pub struct HdfsFile {
+    fs: HdfsFs,
+    path: String,
+    file: hdfsFile,
+    closed: bool,
+    /// The id of the file tracked by the connection
+    #[cfg(feature = "handle_tracking")]
+    handle_id: usize,
+    _marker: PhantomData<()>,
+}
+
+impl Debug for HdfsFile {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HdfsFile")
+            .field("url", &self.fs.url)
+            .field("path", &self.path)
+            .finish()
+    }
+}
+
+impl HdfsFile {
+    /// Get HdfsFs
+    #[inline]
+    pub fn fs(&self) -> &HdfsFs {
+        &self.fs
+    }
+
+    /// Return a file path
+    #[inline]
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Get the number of bytes that can be read from the file without blocking.
+    /// It's only valid for a file opened for read.
+    pub fn available(&self) -> Result<i32, HdfsErr> {
+        let available =
+            unsafe { native_call!(hdfsAvailable(self.fs.raw(), self.file), self.path) };
+
+        if available >= 0 {
+            Ok(available)
+        } else {
+            Err(HdfsErr::from_errno(&self.path).logged(&self.path))
+        }
+    }
+
+    /// Close the file, and return whether it succeeds, e.g. whether the data written
+    /// has been flushed by the final flush on close.
+    ///
+    /// The file is consumed, since the handle is released by libhdfs even if it fails.
+    /// A file not closed explicitly is closed when dropped, where a failure is only logged.
+    pub fn close(mut self) -> Result<(), HdfsErr> {
+        self.close_native()
+    }
+
+    fn close_native(&mut self) -> Result<(), HdfsErr> {
+        if self.closed {
+            return Ok(());
+        }
+        self.closed = true;
+        #[cfg(feature = "handle_tracking")]
+        self.fs.untrack_handle(self.handle_id);
+
+        if unsafe { native_call!(hdfsCloseFile(self.fs.raw(), self.file), self.path) }
+            == 0
+        {
+            Ok(())
+        } else {
+            Err(HdfsErr::from_errno(&self.path).logged(&self.path))
+        }
+    }
+
+    /// Flush the data.
+    pub fn flush(&self) -> bool {
+        (unsafe { native_call!(hdfsFlush(self.fs.raw(), self.file), self.path) }) == 0
+    }
+
+    /// Flush out the data in client's user buffer. After the return of this
+    /// call, new readers will see the data.
+    /// It's only valid for a file opened for write.
+    pub fn hflush(&self) -> Result<bool, HdfsErr> {
+        if unsafe { native_call!(hdfsHFlush(self.fs.raw(), self.file), self.path) } == 0 {
+            Ok(true)
+        } else {
+            Err(HdfsErr::from_errno(&self.path).logged(&self.path))
+        }
+    }
+
+    /// Similar to posix fsync, Flush out the data in client's
+    /// user buffer. all the way to the disk device (but the disk may have
+    /// it in its cache).
+    /// It's only valid for a file opened for write.
+    pub fn hsync(&self) -> Result<bool, HdfsErr> {
+        if unsafe { native_call!(hdfsHSync(self.fs.raw(), self.file), self.path) } == 0 {
+            Ok(true)
+        } else {
+            Err(HdfsErr::from_errno(&self.path).logged(&self.path))
+        }
+    }
+
+    /// Sync the data written by ``hsync``, and check that the length of the file visible
+    /// to others is the expected one, or fail with ``LengthMismatch``.
+    ///
+    /// The length got from the namenode only covers the complete blocks of a file under
+    /// construction, so if it's shorter, a new reader checks by ``pread`` that the last
+    /// expected byte is visible and the one after it is not. The actual length is only
+    /// reported up to ``i32::MAX`` by libhdfs, so a mismatch beyond it fails with
+    /// ``Unsupported``. It's only valid for a file opened for write.
+    pub fn sync_and_verify(&self, expected_len: i64) -> Result<(), HdfsErr> {
+        self.check_open_for_write()?;
+        self.hsync()?;
+
+        let len = self.get_file_status()?.len() as i64;
+        if len == expected_len {
+            return Ok(());
+        }
+        if len > expected_len {
+            return Err(HdfsErr::LengthMismatch(
+                self.path.clone(),
+                expected_len,
+                len,
+            ));
+        }
+
+        let reader = self.fs.open(&self.path)?;
+        let verified = reader.has_len(expected_len).and_then(|has_len| {
+            if has_len {
+                return Ok(());
+            }
+            match reader.available()? as i64 {
+                visible_len if visible_len < i32::MAX as i64 => Err(
+                    HdfsErr::LengthMismatch(self.path.clone(), expected_len, visible_len),
+                ),
+                _ => Err(HdfsErr::Unsupported(format!(
+                    "length of {} beyond i32::MAX is not {}",
+                    self.path, expected_len
+                ))),
+            }
+        });
+        reader.close()?;
+        verified
+    }
+
+    /// Whether the file open for read has the length, by the ``pread`` of the byte before
+    /// it and the one at it
+    fn has_len(&self, len: i64) -> Result<bool, HdfsErr> {
+        let mut byte = [0u8; 1];
+        if len > 0 && self.pread(len - 1, &mut byte)? != 1 {
+            return Ok(false);
+        }
+        Ok(self.pread(len, &mut byte)? == 0)
+    }
+
+    /// Determine if a file is open for read.
+    pub fn is_open_for_read(&self) -> bool {
+        (unsafe { native_call!(hdfsFileIsOpenForRead(self.file), self.path) }) == 1
+    }
+
+    /// Determine if a file is open for write.
+    pub fn is_open_for_write(&self) -> bool {
+        (unsafe { native_call!(hdfsFileIsOpenForWrite(self.file), self.path) }) == 1
+    }
+
+    /// Determine if the file uses the direct read, which reads into the native buffer
+    /// without copying from a java byte array.
+    ///
+    /// It's detected when the file is opened for read, and supported by HDFS streams.
+    /// It benefits the most from the short-circuit read of a local datanode,
+    /// i.e. with ``dfs.client.read.shortcircuit`` enabled.
+    pub fn uses_direct_read(&self) -> bool {
+        (unsafe { native_call!(hdfsFileUsesDirectRead(self.file), self.path) }) == 1
+    }
+
+    /// Disable the direct read of the file, e.g. to compare it with the default read path.
+    pub fn disable_direct_read(&self) {
+        unsafe { native_call!(hdfsFileDisableDirectRead(self.file), self.path) }
+    }
+
+    fn check_open_for_read(&self) -> Result<(), HdfsErr> {
+        if self.is_open_for_read() {
+            Ok(())
+        } else {
+            Err(HdfsErr::InvalidHandleMode(format!(
+                "{} is not open for read",
+                self.path
+            )))
+        }
+    }
+
+    fn check_open_for_write(&self) -> Result<(), HdfsErr> {
+        if self.is_open_for_write() {
+            Ok(())
+        } else {
+            Err(HdfsErr::InvalidHandleMode(format!(
+                "{} is not open for write",
+                self.path
+            )))
+        }
+    }
+
+    /// Get the file status, including file size, last modified time, etc
+    pub fn get_file_status(&self) -> Result<FileStatus, HdfsErr> {
+        self.fs.get_file_status(self.path())
+    }
+
+    /// Get the raw handle of libhdfs, as an escape hatch to call the native functions
+    /// which are not wrapped, together with ``HdfsFs::raw``.
+    ///
+    /// The handle is still owned by this file, which closes it when closed or dropped,
+    /// so it must not be closed by ``hdfsCloseFile`` or used after that.
+    ///
+    /// ```no_run
+    /// use hdfs::hdfs::HdfsFsBuilder;
+    /// use hdfs::native::hdfsFileIsOpenForRead;
+    ///
+    /// let fs = HdfsFsBuilder::new().connect().ok().unwrap();
+    /// let file = fs.open("/tmp/data").ok().unwrap();
+    /// assert_eq!(1, unsafe { hdfsFileIsOpenForRead(file.raw()) });
+    /// ```
+    #[inline]
+    pub fn raw(&self) -> hdfsFile {
+        self.file
+    }
+
+    /// Get the current offset in the file, in bytes.
+    pub fn pos(&self) -> Result<u64, HdfsErr> {
+        let pos = unsafe { native_call!(hdfsTell(self.fs.raw(), self.file), self.path) };
+
+        if pos >= 0 {
+            Ok(pos as u64)
+        } else {
+            Err(HdfsErr::from_errno(&self.path).logged(&self.path))
+        }
+    }
+
+    /// Read data from an open file. It returns 0 at the end of the file.
+    pub fn read(&mut self, buf: &mut [u8]) -> Result<i32, HdfsErr> {
+        self.check_open_for_read()?;
+        let read_len = unsafe {
+            native_call!(
+                hdfsRead(
+                    self.fs.raw(),
+                    self.file,
+                    buf.as_mut_ptr() as *mut c_void,
+                    buf.len().min(tSize::MAX as usize) as tSize,
+                ),
+                self.path,
+                buf.len()
+            )
+        };
+
+        if read_len >= 0 {
+            Ok(read_len as i32)
+        } else {
+            Err(HdfsErr::from_errno(&self.path).logged(&self.path))
+        }
+    }
+
+    /// Read data into an uninitialized buffer, which saves zeroing a large buffer before
+    /// each read, and return the number of bytes read. It returns 0 at the end of the file.
+    ///
+    /// libhdfs only writes into the buffer, so on ``Ok(n)``, ``buf[..n]`` is initialized
+    /// and can be assumed so, e.g. by ``MaybeUninit::assume_init``, while the rest of the
+    /// buffer is left untouched. Nothing is guaranteed to be initialized on an error.
+    /// The cursor is moved as ``read``, so it takes ``&mut self`` too.
+    pub fn read_uninit(&mut self, buf: &mut [MaybeUninit<u8>]) -> Result<usize, HdfsErr> {
+        self.check_open_for_read()?;
+        let read_len = unsafe {
+            native_call!(
+                hdfsRead(
+                    self.fs.raw(),
+                    self.file,
+                    buf.as_mut_ptr() as *mut c_void,
+                    buf.len().min(tSize::MAX as usize) as tSize,
+                ),
+                self.path,
+                buf.len()
+            )
+        };
+
+        if read_len >= 0 {
+            Ok(read_len as usize)
+        } else {
+            Err(HdfsErr::from_errno(&self.path).logged(&self.path))
+        }
+    }
+
+    /// Positional read of data from an open file.
+    pub fn read_with_pos(&self, pos: i64, buf: &mut [u8]) -> Result<i32, HdfsErr> {
+        self.check_open_for_read()?;
+        let read_len = unsafe {
+            native_call!(
+                hdfsPread(
+                    self.fs.raw(),
+                    self.file,
+                    pos as tOffset,
+                    buf.as_ptr() as *mut c_void,
+                    buf.len() as tSize,
+                ),
+                self.path,
+                pos
+            )
+        };
+
+        if read_len > 0 {
+            Ok(read_len as i32)
+        } else {
+            Err(HdfsErr::from_errno(&self.path).logged(&self.path))
+        }
+    }
+
+    /// Read data from the given position without moving the file cursor,
+    /// so that a file opened for read can be shared by concurrent readers.
+    ///
+    /// It keeps reading until the buffer is filled or the end of the file is reached,
+    /// and returns the number of bytes read. 0 means the position is at the end of the file.
+    pub fn pread(&self, position: i64, buf: &mut [u8]) -> Result<usize, HdfsErr> {
+        self.check_open_for_read()?;
+        if position < 0 {
+            return Err(HdfsErr::InvalidArgument(format!(
+                "negative position {} to read {}",
+                position, self.path
+            )));
+        }
+
+        let mut read = 0;
+        while read < buf.len() {
+            let remaining = &mut buf[read..];
+            let read_len = unsafe {
+                native_call!(
+                    hdfsPread(
+                        self.fs.raw(),
+                        self.file,
+                        (position + read as i64) as tOffset,
+                        remaining.as_mut_ptr() as *mut c_void,
+                        remaining.len().min(tSize::MAX as usize) as tSize,
+                    ),
+                    self.path,
+                    position
+                )
+            };
+
+            match read_len {
+                0 => break,
+                len if len > 0 => read += len as usize,
+                _ => return Err(HdfsErr::from_errno(&self.path).logged(&self.path)),
+            }
+        }
+
+        Ok(read)
+    }
+
+    /// Seek to given offset in file, which is only supported for a file open for read.
+    pub fn seek(&mut self, offset: u64) -> Result<bool, HdfsErr> {
+        self.check_open_for_read()?;
+
+        if unsafe {
+            native_call!(
+                hdfsSeek(self.fs.raw(), self.file, offset as tOffset),
+                self.path,
+                offset
+            )
+        } == 0
+        {
+            Ok(true)
+        } else {
+            Err(HdfsErr::from_errno(&self.path).logged(&self.path))
+        }
+    }
+
+    /// Seek back to the start of a file open for read, to read it again.
+    ///
+    /// It takes ``&mut self`` as ``seek``, since the cursor is shared by the handle.
+    pub fn rewind(&mut self) -> Result<(), HdfsErr> {
+        self.seek(0).map(|_| ())
+    }
+
+    /// Write the whole data into an open file, by native writes of at most ``chunk_size``
+    /// bytes each.
+    pub(crate) fn write_chunked(
+        &mut self,
+        data: &[u8],
+        chunk_size: usize,
+    ) -> Result<(), HdfsErr> {
+        let chunk_size = chunk_size.clamp(1, tSize::MAX as usize);
+        let mut written = 0;
+        while written < data.len() {
+            let end = data.len().min(written.saturating_add(chunk_size));
+            written += self.write(&data[written..end])? as usize;
+        }
+        Ok(())
+    }
+
+    /// Write data into an open file.
+    pub fn write(&mut self, buf: &[u8]) -> Result<i32, HdfsErr> {
+        self.check_open_for_write()?;
+        let written_len = unsafe {
+            native_call!(
+                hdfsWrite(
+                    self.fs.raw(),
+                    self.file,
+                    buf.as_ptr() as *mut c_void,
+                    buf.len() as tSize,
+                ),
+                self.path,
+                buf.len()
+            )
+        };
+
+        if written_len > 0 {
+            Ok(written_len)
+        } else {
+            Err(HdfsErr::from_errno(&self.path).logged(&self.path))
+        }
+    }
+}
+
+/// A single call may return fewer bytes than requested even before the end of the file,
+/// so callers should rely on helpers like ``read_exact`` or ``read_to_end``.
+impl Read for HdfsFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.check_open_for_read().map_err(unsupported_io_error)?;
+        let read_len = unsafe {
+            native_call!(
+                hdfsRead(
+                    self.fs.raw(),
+                    self.file,
+                    buf.as_mut_ptr() as *mut c_void,
+                    buf.len().min(tSize::MAX as usize) as tSize,
+                ),
+                self.path,
+                buf.len()
+            )
+        };
+
+        if read_len >= 0 {
+            Ok(read_len as usize)
+        } else {
+            // libhdfs translates the java exception into errno
+            Err(io::Error::last_os_error())
+        }
+    }
+}
+
+/// ``hdfsWrite`` may accept only part of the buffer, so the rest is written until
+/// the whole slice is consumed or an error occurs.
+impl Write for HdfsFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.check_open_for_write().map_err(unsupported_io_error)?;
+        let mut written = 0;
+        while written < buf.len() {
+            let remaining = &buf[written..];
+            let written_len = unsafe {
+                native_call!(
+                    hdfsWrite(
+                        self.fs.raw(),
+                        self.file,
+                        remaining.as_ptr() as *const c_void,
+                        remaining.len().min(tSize::MAX as usize) as tSize,
+                    ),
+                    self.path,
+                    buf.len()
+                )
+            };
+
+            match written_len {
+                len if len > 0 => written += len as usize,
+                // report the bytes already written, the error will show up again
+                _ if written > 0 => break,
+                0 => return Err(io::Error::from(io::ErrorKind::WriteZero)),
+                _ => return Err(io::Error::last_os_error()),
+            }
+        }
+
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if unsafe { native_call!(hdfsFlush(self.fs.raw(), self.file), self.path) } == 0 {
+            Ok(())
+        } else {
+            Err(io::Error::last_os_error())
+        }
+    }
+}
+
+/// libhdfs only seeks to an absolute offset, so ``SeekFrom::End`` is resolved by the file length.
+/// HDFS only supports seeking a stream opened for read.
+impl Seek for HdfsFile {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.check_open_for_read().map_err(unsupported_io_error)?;
+
+        let (base, offset) = match pos {
+            SeekFrom::Start(offset) => (0, offset as i64),
+            SeekFrom::Current(offset) => {
+                let cur = unsafe {
+                    native_call!(hdfsTell(self.fs.raw(), self.file), self.path)
+                };
+                if cur < 0 {
+                    return Err(io::Error::last_os_error());
+                }
+                (cur, offset)
+            }
+            SeekFrom::End(offset) => {
+                let len = self
+                    .get_file_status()
+                    .map_err(|e| io::Error::other(format!("{:?}", e)))?
+                    .len();
+                (len as i64, offset)
+            }
+        };
+
+        let target = match base.checked_add(offset) {
+            Some(target) if target >= 0 => target,
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "invalid seek to a negative or overflowing position",
+                ))
+            }
+        };
+
+        if unsafe {
+            native_call!(
+                hdfsSeek(self.fs.raw(), self.file, target as tOffset),
+                self.path,
+                offset
+            )
+        } == 0
+        {
+            Ok(target as u64)
+        } else {
+            Err(io::Error::last_os_error())
+        }
+    }
+}
+
+fn unsupported_io_error(e: HdfsErr) -> io::Error {
+    io::Error::new(io::ErrorKind::Unsupported, format!("{:?}", e))
+}
+
+impl Drop for HdfsFile {
+    fn drop(&mut self) {
+        if let Err(e) = self.close_native() {
+            warn!("Fail to close file {} when dropped: {:?}", self.path, e);
+        }
+    }
+}
+
+/// The native file is a global reference to a java stream, which can be used by any thread.
+/// Mutation of the cursor requires ``&mut self``, and the positional read is synchronized
+/// by the java stream.
+unsafe impl Send for HdfsFile {}
+
+unsafe impl Sync for HdfsFile {}
+
+/// The location of a block of a file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockLocation {
+    /// The offset of the block in the file
+    pub offset: i64,
+    /// The length of the block, which may be shorter than the block size for the last one
+    pub length: i64,
+    /// The hostnames of the datanodes storing the block
+    pub hosts: Vec<String>,
+}
+
+/// The space summary of a filesystem, in bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FsStats {
+    /// The raw capacity
+    pub capacity: i64,
+    /// The raw size of all files
+    pub used: i64,
+    /// The raw space which is not used, i.e. ``capacity - used``
+    pub remaining: i64,
+    pub default_block_size: i64,
+}
+
+/// The summary of the subtree under a path
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ContentSummary {
+    /// The total size of the files, in bytes
+    pub length: i64,
+    pub file_count: u64,
+    pub directory_count: u64,
+}
+
+/// The read, write and execute permissions of a class of users
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PermissionBits {
+    pub read: bool,
+    pub write: bool,
+    pub execute: bool,
+}
+
+impl PermissionBits {
+    fn from_bits(bits: i16) -> PermissionBits {
+        PermissionBits {
+            read: bits & 0o4 != 0,
+            write: bits & 0o2 != 0,
+            execute: bits & 0o1 != 0,
+        }
+    }
+
+    fn bits(&self) -> i16 {
+        (self.read as i16) << 2 | (self.write as i16) << 1 | self.execute as i16
+    }
+}
+
+/// The permissions of a file or a directory, as the ``FsPermission`` of Hadoop.
+///
+/// HDFS has no setuid or setgid bits, so the sticky bit is the only one beyond ``rwx``,
+/// which only allows the owner of an entry of a directory to delete or rename it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Permissions {
+    pub owner: PermissionBits,
+    pub group: PermissionBits,
+    pub other: PermissionBits,
+    pub sticky: bool,
+}
+
+impl Permissions {
+    /// The bits of the mode which are valid for HDFS
+    pub const MODE_MASK: i16 = 0o1777;
+
+    /// Parse the raw mode, e.g. ``0o1755`` for ``rwxr-xr-t``, which fails with
+    /// ``InvalidArgument`` if any bit is out of ``MODE_MASK``.
+    pub fn from_mode(mode: i16) -> Result<Permissions, HdfsErr> {
+        if mode & !Permissions::MODE_MASK != 0 {
+            return Err(HdfsErr::InvalidArgument(format!(
+                "invalid permission mode {:#o}",
+                mode
+            )));
+        }
+
+        Ok(Permissions {
+            owner: PermissionBits::from_bits(mode >> 6),
+            group: PermissionBits::from_bits(mode >> 3),
+            other: PermissionBits::from_bits(mode),
+            sticky: mode & 0o1000 != 0,
+        })
+    }
+
+    /// Get the raw mode, which is parsed back to the same permissions by ``from_mode``
+    pub fn mode(&self) -> i16 {
+        (self.sticky as i16) << 9
+            | self.owner.bits() << 6
+            | self.group.bits() << 3
+            | self.other.bits()
+    }
+}
+
+/// Formatted as ``ls -l``, e.g. ``rwxr-xr-t``, where the execute bit of others is shown
+/// as ``t`` or ``T`` with or without it if the sticky bit is set.
+impl Display for Permissions {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let classes = [self.owner, self.group, self.other];
+        for (idx, bits) in classes.iter().enumerate() {
+            let execute = match (idx == 2 && self.sticky, bits.execute) {
+                (true, true) => 't',
+                (true, false) => 'T',
+                (false, true) => 'x',
+                (false, false) => '-',
+            };
+            write!(
+                f,
+                "{}{}{}",
+                if bits.read { 'r' } else { '-' },
+                if bits.write { 'w' } else { '-' },
+                execute
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// The kind of a file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileKind {
+    File,
+    Directory,
+}
+
+/// Interface that represents the client side information for a file or directory.
+///
+/// It's copied out of the ``hdfsFileInfo`` of libhdfs, so it's independent of the native memory.
+#[derive(Debug, Clone)]
+pub struct FileStatus {
+    name: String,
+    kind: FileKind,
+    len: usize,
+    block_size: usize,
+    replica_count: u16,
+    owner: String,
+    group: String,
+    permission: i16,
+    last_modified: SystemTime,
+    last_access: SystemTime,
+}
+
+impl FileStatus {
+    /// create FileStatus by copying hdfsFileInfo
+    fn from_raw(info: &hdfsFileInfo) -> FileStatus {
+        FileStatus {
+            name: c_str_to_string(info.mName),
+            kind: to_file_kind(info.mKind),
+            len: info.mSize.max(0) as usize,
+            block_size: info.mBlockSize.max(0) as usize,
+            replica_count: info.mReplication.max(0) as u16,
+            owner: c_str_to_string(info.mOwner),
+            group: c_str_to_string(info.mGroup),
+            permission: info.mPermissions,
+            last_modified: to_system_time(info.mLastMod),
+            last_access: to_system_time(info.mLastAccess),
+        }
+    }
+
+    /// An empty status to be filled by ``fill_from_raw``
+    fn empty() -> FileStatus {
+        FileStatus {
+            name: String::new(),
+            kind: FileKind::File,
+            len: 0,
+            block_size: 0,
+            replica_count: 0,
+            owner: String::new(),
+            group: String::new(),
+            permission: 0,
+            last_modified: UNIX_EPOCH,
+            last_access: UNIX_EPOCH,
+        }
+    }
+
+    /// Overwrite this status by copying hdfsFileInfo, reusing the allocated strings
+    fn fill_from_raw(&mut self, info: &hdfsFileInfo) {
+        c_str_into_string(info.mName, &mut self.name);
+        self.kind = to_file_kind(info.mKind);
+        self.len = info.mSize.max(0) as usize;
+        self.block_size = info.mBlockSize.max(0) as usize;
+        self.replica_count = info.mReplication.max(0) as u16;
+        c_str_into_string(info.mOwner, &mut self.owner);
+        c_str_into_string(info.mGroup, &mut self.group);
+        self.permission = info.mPermissions;
+        self.last_modified = to_system_time(info.mLastMod);
+        self.last_access = to_system_time(info.mLastAccess);
+    }
+
+    /// Create the status of an entry which is not got from libhdfs, e.g. of ``MockFs``.
+    /// A file has a single replica of the default block size of HDFS.
+    #[cfg(feature = "test_util")]
+    pub(crate) fn new(
+        name: String,
+        kind: FileKind,
+        len: usize,
+        last_modified: SystemTime,
+    ) -> FileStatus {
+        let (block_size, replica_count, permission) = match kind {
+            FileKind::File => (128 * 1024 * 1024, 1, 0o644),
+            FileKind::Directory => (0, 0, 0o755),
+        };
+        FileStatus {
+            name,
+            kind,
+            len,
+            block_size,
+            replica_count,
+            owner: String::new(),
+            group: String::new(),
+            permission,
+            last_modified,
+            last_access: last_modified,
+        }
+    }
+
+    /// Similar to ``from_raw``, but fails if the name is not UTF-8
+    fn from_raw_strict(info: &hdfsFileInfo) -> Result<FileStatus, HdfsErr> {
+        c_str_to_string_strict(info.mName)?;
+        Ok(FileStatus::from_raw(info))
+    }
+
+    /// Get the name of the file
+    #[inline]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Get the kind of the file
+    #[inline]
+    pub fn kind(&self) -> FileKind {
+        self.kind
+    }
+
+    /// Is this a file?
+    #[inline]
+    pub fn is_file(&self) -> bool {
+        self.kind == FileKind::File
+    }
+
+    /// Is this a directory?
+    #[inline]
+    pub fn is_directory(&self) -> bool {
+        self.kind == FileKind::Directory
+    }
+
+    /// Get the owner of the file
+    #[inline]
+    pub fn owner(&self) -> &str {
+        &self.owner
+    }
+
+    /// Get the group associated with the file
+    #[inline]
+    pub fn group(&self) -> &str {
+        &self.group
+    }
+
+    /// Get the permissions associated with the file
+    #[inline]
+    pub fn permission(&self) -> i16 {
+        self.permission
+    }
+
+    /// Get the typed permissions associated with the file, ignoring any extended bit
+    /// out of ``Permissions::MODE_MASK``
+    pub fn permissions(&self) -> Permissions {
+        // It never fails after masked
+        Permissions::from_mode(self.permission & Permissions::MODE_MASK)
+            .unwrap_or_default()
+    }
+
+    /// Get the length of this file, in bytes.
+    #[allow(clippy::len_without_is_empty)]
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Get the block size of the file.
+    #[inline]
+    pub fn block_size(&self) -> usize {
+        self.block_size
+    }
+
+    /// Get the replication factor of a file.
+    #[inline]
+    pub fn replica_count(&self) -> u16 {
+        self.replica_count
+    }
+
+    /// Get the last modification time for the file, in the precision of seconds
+    #[inline]
+    pub fn last_modified(&self) -> SystemTime {
+        self.last_modified
+    }
+
+    /// Get the last access time for the file, in the precision of seconds
+    #[inline]
+    pub fn last_access(&self) -> SystemTime {
+        self.last_access
+    }
+}
+
+/// Iterator of the file status in a native array of hdfsFileInfo
+struct FileStatusIter {
+    info_ptr: Option<HdfsFileInfoPtr>,
+    idx: i32,
+    /// Whether a name which is not UTF-8 is an error
+    strict: bool,
+}
+
+impl FileStatusIter {
+    /// Get the next native entry without converting it
+    fn next_info(&mut self) -> Option<&hdfsFileInfo> {
+        let info_ptr = self.info_ptr.as_ref()?;
+        if self.idx >= info_ptr.len {
+            return None;
+        }
+
+        let info = unsafe { &*info_ptr.ptr.offset(self.idx as isize) };
+        self.idx += 1;
+        Some(info)
+    }
+}
+
+impl Iterator for FileStatusIter {
+    type Item = Result<FileStatus, HdfsErr>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let strict = self.strict;
+        let info = self.next_info()?;
+        if strict {
+            Some(FileStatus::from_raw_strict(info))
+        } else {
+            Some(Ok(FileStatus::from_raw(info)))
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self
+            .info_ptr
+            .as_ref()
+            .map_or(0, |info_ptr| (info_ptr.len - self.idx) as usize);
+        (remaining, Some(remaining))
+    }
+}
+
+/// Iterator of the lines of a file, buffering the data read but not yet split
+struct LineIter {
+    path: String,
+    /// ``None`` once the file is read to the end or fails to read
+    file: Option<HdfsFile>,
+    buf: Vec<u8>,
+    /// The range of the buffered data which is not yet yielded
+    start: usize,
+    end: usize,
+    line_num: usize,
+}
+
+impl LineIter {
+    fn take_line(&mut self, len: usize, consumed: usize) -> Result<String, HdfsErr> {
+        let mut line = &self.buf[self.start..self.start + len];
+        if line.last() == Some(&b'\r') {
+            line = &line[..line.len() - 1];
+        }
+        self.line_num += 1;
+        let line = String::from_utf8(line.to_vec()).map_err(|_| {
+            HdfsErr::InvalidData(format!(
+                "line {} of {} is not UTF-8",
+                self.line_num, self.path
+            ))
+        });
+        self.start += consumed;
+        line
+    }
+}
+
+impl Iterator for LineIter {
+    type Item = Result<String, HdfsErr>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let buffered = &self.buf[self.start..self.end];
+            if let Some(len) = buffered.iter().position(|b| *b == b'\n') {
+                return Some(self.take_line(len, len + 1));
+            }
+
+            let file = match self.file.as_mut() {
+                Some(file) => file,
+                // The last line without any ending
+                None if self.start < self.end => {
+                    let len = self.end - self.start;
+                    return Some(self.take_line(len, len));
+                }
+                None => return None,
+            };
+
+            // Move the partial line to the front, and grow the buffer for a long line
+            self.buf.copy_within(self.start..self.end, 0);
+            self.end -= self.start;
+            self.start = 0;
+            if self.end == self.buf.len() {
+                self.buf.resize(self.buf.len() * 2, 0);
+            }
+
+            match file.read(&mut self.buf[self.end..]) {
+                Ok(0) => {
+                    if let Err(e) = self.file.take().unwrap().close() {
+                        return Some(Err(e));
+                    }
+                }
+                Ok(len) => self.end += len as usize,
+                Err(e) => {
+                    self.file = None;
+                    self.start = self.end;
+                    return Some(Err(e));
+                }
+            }
+        }
+    }
+}
+
+/// Convert a C string into a string, where the bytes which are not UTF-8 are replaced
+/// with ``U+FFFD``, so that a single bad name doesn't break a whole listing
+fn c_str_to_string(ptr: *const c_char) -> String {
+    if ptr.is_null() {
+        return String::new();
+    }
+
+    unsafe { CStr::from_ptr(ptr) }
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// Copy a C string into the string as ``c_str_to_string``, reusing its allocation
+fn c_str_into_string(ptr: *const c_char, s: &mut String) {
+    s.clear();
+    if !ptr.is_null() {
+        s.push_str(&unsafe { CStr::from_ptr(ptr) }.to_string_lossy());
+    }
+}
+
+/// Convert a C string of a path into a string, which fails if it's not UTF-8
+fn c_str_to_string_strict(ptr: *const c_char) -> Result<String, HdfsErr> {
+    if ptr.is_null() {
+        return Ok(String::new());
+    }
+
+    let cstr = unsafe { CStr::from_ptr(ptr) };
+    cstr.to_str()
+        .map(|s| s.to_owned())
+        .map_err(|_| HdfsErr::InvalidPath(cstr.to_string_lossy().into_owned()))
+}
+
+/// Resolve the symbolic links of a qualified path component by component, by the target
+/// of each link got by ``link_target``, or ``None`` if it's not a link.
+///
+/// A relative target is resolved against the directory of the link, where ``.`` and
+/// ``..`` are resolved as well.
+fn resolve_links<F>(path: &str, mut link_target: F) -> Result<String, HdfsErr>
+where
+    F: FnMut(&str) -> Result<Option<String>, HdfsErr>,
+{
+    fn push_segments(pending: &mut Vec<String>, path: &str) {
+        pending.extend(
+            path.split('/')
+                .filter(|s| !s.is_empty())
+                .rev()
+                .map(|s| s.to_owned()),
+        );
+    }
+
+    let (prefix, rest) = path::split_authority(path);
+    let mut resolved = path::normalize(&format!("{}/", prefix));
+    let mut pending = Vec::new();
+    push_segments(&mut pending, rest);
+
+    let mut links = 0;
+    while let Some(segment) = pending.pop() {
+        match segment.as_str() {
+            "." => continue,
+            ".." => {
+                resolved = path::parent(&resolved).unwrap_or(resolved);
+                continue;
+            }
+            _ => {}
+        }
+
+        let candidate = path::join(&resolved, &segment);
+        match link_target(&candidate)? {
+            None => resolved = candidate,
+            Some(target) => {
+                links += 1;
+                if links > MAX_PATH_LINKS {
+                    return Err(HdfsErr::SymlinkLoop(path.to_owned()));
+                }
+
+                // The rest of the target is resolved before the rest of the path
+                let (target_prefix, target_rest) = path::split_authority(&target);
+                if !target_prefix.is_empty() {
+                    resolved = path::normalize(&format!("{}/", target_prefix));
+                } else if target_rest.starts_with('/') {
+                    resolved = path::join(&resolved, "/");
+                }
+                push_segments(&mut pending, target_rest);
+            }
+        }
+    }
+
+    Ok(resolved)
+}
+
+/// A unique temporary path next to the path, by the process id, the time and a sequence
+/// number, so that the concurrent writers of the same path don't clash
+fn tmp_path_of(path: &str) -> String {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |now| now.as_nanos());
+    format!(
+        "{}.{}-{}-{}{}",
+        path,
+        process::id(),
+        now,
+        TEMP_FILE_SEQ.fetch_add(1, Ordering::Relaxed),
+        TEMP_FILE_SUFFIX
+    )
+}
+
+fn to_file_kind(kind: tObjectKind) -> FileKind {
+    match kind {
+        tObjectKind::kObjectKindFile => FileKind::File,
+        tObjectKind::kObjectKindDirectory => FileKind::Directory,
+    }
+}
+
+/// libhdfs reports the time in seconds since the epoch
+fn to_system_time(secs: time_t) -> SystemTime {
+    if secs >= 0 {
+        UNIX_EPOCH + Duration::from_secs(secs as u64)
+    } else {
+        UNIX_EPOCH - Duration::from_secs(secs.unsigned_abs())
+    }
+}
 
 /// Safely deallocable hdfsFileInfo pointer
 struct HdfsFileInfoPtr {
@@ -743,212 +3684,3557 @@ struct HdfsFileInfoPtr {
     pub len: i32,
 }
 
-/// for safe deallocation
-impl<'a> Drop for HdfsFileInfoPtr {
-    fn drop(&mut self) {
-        unsafe { hdfsFreeFileInfo(self.ptr as *mut hdfsFileInfo, self.len) };
-    }
-}
+/// for safe deallocation
+impl Drop for HdfsFileInfoPtr {
+    fn drop(&mut self) {
+        unsafe {
+            native_call!(hdfsFreeFileInfo(self.ptr as *mut hdfsFileInfo, self.len))
+        };
+    }
+}
+
+impl HdfsFileInfoPtr {
+    fn new(ptr: *const hdfsFileInfo) -> HdfsFileInfoPtr {
+        HdfsFileInfoPtr { ptr, len: 1 }
+    }
+
+    pub fn new_array(ptr: *const hdfsFileInfo, len: i32) -> HdfsFileInfoPtr {
+        HdfsFileInfoPtr { ptr, len }
+    }
+}
+
+pub const LOCAL_FS_SCHEME: &str = "file";
+pub const HDFS_FS_SCHEME: &str = "hdfs";
+pub const VIEW_FS_SCHEME: &str = "viewfs";
+
+#[inline]
+fn get_namenode_uri(path: &str) -> Result<String, HdfsErr> {
+    match parse_namenode(path)? {
+        (name_node, Some(port)) => Ok(format!("{}:{}", name_node, port)),
+        (name_node, None) => Ok(name_node),
+    }
+}
+
+/// Parse the namenode of a URL into the scheme with the host, and the port if any.
+///
+/// A URL without a port, e.g. ``hdfs://mycluster``, may refer to a logical nameservice
+/// of an HA cluster, which is resolved by its client configuration rather than as a host.
+fn parse_namenode(path: &str) -> Result<(String, Option<u16>), HdfsErr> {
+    match Url::parse(path) {
+        Ok(url) => match url.scheme() {
+            LOCAL_FS_SCHEME => Ok(("file:///".to_string(), None)),
+            HDFS_FS_SCHEME | VIEW_FS_SCHEME => match url.host() {
+                Some(host) => Ok((format!("{}://{}", url.scheme(), host), url.port())),
+                None => Err(HdfsErr::InvalidUrl(path.to_string())),
+            },
+            _ => Err(HdfsErr::InvalidUrl(path.to_string())),
+        },
+        Err(_) => Err(HdfsErr::InvalidUrl(path.to_string())),
+    }
+}
+
+#[inline]
+pub fn get_uri(path: &str) -> Result<String, HdfsErr> {
+    let path = if path.starts_with('/') {
+        format!("{}://{}", LOCAL_FS_SCHEME, path)
+    } else {
+        path.to_string()
+    };
+    match Url::parse(&path) {
+        Ok(url) => match url.scheme() {
+            LOCAL_FS_SCHEME | HDFS_FS_SCHEME | VIEW_FS_SCHEME => Ok(url.to_string()),
+            _ => Err(HdfsErr::InvalidUrl(path.to_string())),
+        },
+        Err(_) => Err(HdfsErr::InvalidUrl(path.to_string())),
+    }
+}
+
+/// Convert a path into a C string, which fails if the path contains a NUL byte
+pub(crate) fn to_c_string(path: &str) -> Result<CString, HdfsErr> {
+    CString::new(path).map_err(|_| HdfsErr::InvalidPath(path.to_owned()))
+}
+
+/// Convert a non-path argument into a C string, which fails if it contains a NUL byte
+fn to_c_string_arg(arg: &str, name: &str) -> Result<CString, HdfsErr> {
+    CString::new(arg)
+        .map_err(|_| HdfsErr::InvalidArgument(format!("invalid {} {:?}", name, arg)))
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::hash_map::DefaultHasher;
+    use std::collections::HashMap;
+    use std::ffi::CString;
+    use std::hash::Hasher;
+    use std::io;
+    use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+    use std::mem::MaybeUninit;
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    use uuid::Uuid;
+
+    use super::{
+        c_str_to_string, c_str_to_string_strict, get_namenode_uri, parse_namenode,
+        resolve_links, FileKind, FileStatus, HdfsErr, HdfsFile, HdfsFs, HdfsFsBuilder,
+        HdfsOpenOptions, PermissionBits, Permissions, O_APPEND, O_EXCL, O_RDONLY,
+        O_WRONLY,
+    };
+    use crate::minidfs::get_dfs;
+    use crate::path;
+
+    #[cfg(feature = "use_existing_hdfs")]
+    #[test]
+    fn test_hdfs_default() {
+        let fs = super::get_hdfs().ok().unwrap();
+
+        let uuid = Uuid::new_v4().to_string();
+        let test_file = uuid.as_str();
+        let created_file = match fs.create(test_file) {
+            Ok(f) => f,
+            Err(_) => panic!("Couldn't create a file"),
+        };
+        assert!(created_file.close().is_ok());
+        assert!(fs.exist(test_file));
+
+        assert!(fs.delete(test_file, false).is_ok());
+        assert!(!fs.exist(test_file));
+    }
+
+    #[test]
+    fn test_hdfs() {
+        let dfs = get_dfs();
+        {
+            let minidfs_addr = dfs.namenode_addr();
+            let fs = dfs.get_hdfs().ok().unwrap();
+
+            // Test hadoop file
+            {
+                // create a file, check existence, and close
+                let uuid = Uuid::new_v4().to_string();
+                let test_file = uuid.as_str();
+                let created_file = match fs.create(test_file) {
+                    Ok(f) => f,
+                    Err(_) => panic!("Couldn't create a file"),
+                };
+                assert!(created_file.close().is_ok());
+                assert!(fs.exist(test_file));
+
+                // open a file and close
+                let opened_file = fs.open(test_file).ok().unwrap();
+                assert!(opened_file.close().is_ok());
+
+                // Clean up
+                assert!(fs.delete(test_file, false).is_ok());
+            }
+
+            // Test directory
+            {
+                let uuid = Uuid::new_v4().to_string();
+                let test_dir = format!("/{}", uuid);
+
+                match fs.mkdir(&test_dir) {
+                    Ok(_) => println!("{} created", test_dir),
+                    Err(_) => panic!("Couldn't create {} directory", test_dir),
+                };
+
+                let file_info = fs.get_file_status(&test_dir).ok().unwrap();
+
+                let expected_path = format!("{}{}", minidfs_addr, test_dir);
+                assert_eq!(&expected_path, file_info.name());
+                assert!(!file_info.is_file());
+                assert!(file_info.is_directory());
+
+                let sub_dir_num = 3;
+                let mut expected_list = Vec::new();
+                for x in 0..sub_dir_num {
+                    let filename = format!("{}/{}", test_dir, x);
+                    expected_list.push(format!("{}{}/{}", minidfs_addr, test_dir, x));
+
+                    match fs.mkdir(&filename) {
+                        Ok(_) => println!("{} created", filename),
+                        Err(_) => panic!("Couldn't create {} directory", filename),
+                    };
+                }
+
+                let mut list = fs.list_status(&test_dir).ok().unwrap();
+                assert_eq!(sub_dir_num, list.len());
+
+                list.sort_by(|a, b| Ord::cmp(a.name(), b.name()));
+                for (expected, name) in expected_list
+                    .iter()
+                    .zip(list.iter().map(|status| status.name()))
+                {
+                    assert_eq!(expected, name);
+                }
+
+                // Clean up
+                assert!(fs.delete(&test_dir, true).is_ok());
+            }
+        }
+    }
+
+    #[test]
+    fn test_list_status_with_empty_dir() {
+        let dfs = get_dfs();
+        {
+            let fs = dfs.get_hdfs().ok().unwrap();
+
+            // Test list status with empty directory
+            {
+                let uuid = Uuid::new_v4().to_string();
+                let test_dir = format!("/{}", uuid);
+                let empty_dir = format!("/{}", "_empty");
+
+                match fs.mkdir(&test_dir) {
+                    Ok(_) => println!("{} created", test_dir),
+                    Err(_) => panic!("Couldn't create {} directory", test_dir),
+                };
+
+                match fs.mkdir(&empty_dir) {
+                    Ok(_) => println!("{} created", test_dir),
+                    Err(_) => panic!("Couldn't create {} directory", test_dir),
+                };
+
+                let test_file = format!("{}/{}", &test_dir, "test.txt");
+                match fs.create(&test_file) {
+                    Ok(_) => println!("{} created", test_file),
+                    Err(_) => panic!("Couldn't create {} ", test_file),
+                }
+
+                let file_info = fs.list_status(&test_dir).ok().unwrap();
+                assert_eq!(file_info.len(), 1);
+
+                let file_info = fs.list_status(&empty_dir).ok().unwrap();
+                assert_eq!(file_info.len(), 0);
+
+                // Clean up
+                assert!(fs.delete(&test_dir, true).is_ok());
+            }
+        }
+    }
+
+    #[test]
+    fn test_read_with_buf_reader() {
+        let dfs = get_dfs();
+        {
+            let fs = dfs.get_hdfs().ok().unwrap();
+
+            let uuid = Uuid::new_v4().to_string();
+            let test_file = format!("/{}", uuid);
+
+            // Prepare a multi-megabyte file
+            let data: Vec<u8> = (0..4 * 1024 * 1024).map(|i| (i % 251) as u8).collect();
+            {
+                let mut file = fs.create(&test_file).ok().unwrap();
+                let mut written = 0;
+                while written < data.len() {
+                    written += file.write(&data[written..]).ok().unwrap() as usize;
+                }
+                assert!(file.close().is_ok());
+            }
+
+            let file = fs.open(&test_file).ok().unwrap();
+            let mut reader = BufReader::new(file);
+            let mut buf = Vec::new();
+            assert_eq!(data.len(), reader.read_to_end(&mut buf).unwrap());
+
+            let checksum = |bytes: &[u8]| {
+                let mut hasher = DefaultHasher::new();
+                hasher.write(bytes);
+                hasher.finish()
+            };
+            assert_eq!(checksum(&data), checksum(&buf));
+
+            // Reading at EOF returns 0 instead of an error
+            assert_eq!(0, reader.read(&mut [0u8; 16]).unwrap());
+            assert!(reader.into_inner().close().is_ok());
+
+            // Clean up
+            assert!(fs.delete(&test_file, false).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_write_with_buf_writer() {
+        let dfs = get_dfs();
+        {
+            let fs = dfs.get_hdfs().ok().unwrap();
+
+            let uuid = Uuid::new_v4().to_string();
+            let test_file = format!("/{}", uuid);
+
+            let data: Vec<u8> = (0..8 * 1024 * 1024).map(|i| (i % 253) as u8).collect();
+            {
+                let file = fs.create(&test_file).ok().unwrap();
+                let mut writer = BufWriter::new(file);
+                writer.write_all(&data).unwrap();
+                writeln!(writer, "done").unwrap();
+                writer.flush().unwrap();
+                let file = writer.into_inner().unwrap();
+                assert!(file.close().is_ok());
+            }
+
+            let file_info = fs.get_file_status(&test_file).ok().unwrap();
+            assert_eq!(data.len() + "done\n".len(), file_info.len());
+
+            // Clean up
+            assert!(fs.delete(&test_file, false).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_seek() {
+        let dfs = get_dfs();
+        {
+            let fs = dfs.get_hdfs().ok().unwrap();
+
+            let uuid = Uuid::new_v4().to_string();
+            let test_file = format!("/{}", uuid);
+
+            let data: Vec<u8> = (0..1000).map(|i| (i % 256) as u8).collect();
+            {
+                let mut file = fs.create(&test_file).ok().unwrap();
+                file.write_all(&data).unwrap();
+
+                // Seeking a write stream is not supported
+                assert!(Seek::seek(&mut file, SeekFrom::Start(0)).is_err());
+                assert!(file.close().is_ok());
+            }
+
+            let mut file = fs.open(&test_file).ok().unwrap();
+            let mut byte = [0u8; 1];
+
+            assert_eq!(100, Seek::seek(&mut file, SeekFrom::Start(100)).unwrap());
+            file.read_exact(&mut byte).unwrap();
+            assert_eq!(data[100], byte[0]);
+
+            assert_eq!(111, Seek::seek(&mut file, SeekFrom::Current(10)).unwrap());
+            file.read_exact(&mut byte).unwrap();
+            assert_eq!(data[111], byte[0]);
+
+            assert_eq!(990, Seek::seek(&mut file, SeekFrom::End(-10)).unwrap());
+            file.read_exact(&mut byte).unwrap();
+            assert_eq!(data[990], byte[0]);
+
+            assert!(Seek::seek(&mut file, SeekFrom::Current(-2000)).is_err());
+            assert!(file.close().is_ok());
+
+            // Clean up
+            assert!(fs.delete(&test_file, false).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_pread_from_threads() {
+        let dfs = get_dfs();
+        {
+            let fs = dfs.get_hdfs().ok().unwrap();
+
+            let uuid = Uuid::new_v4().to_string();
+            let test_file = format!("/{}", uuid);
+
+            let data: Vec<u8> = (0..64 * 1024).map(|i| (i % 241) as u8).collect();
+            {
+                let mut file = fs.create(&test_file).ok().unwrap();
+                file.write_all(&data).unwrap();
+                assert!(file.close().is_ok());
+            }
+
+            let file = Arc::new(fs.open(&test_file).ok().unwrap());
+            let data = Arc::new(data);
+            let region = 8 * 1024;
+            let handles: Vec<_> = (0..8)
+                .map(|idx| {
+                    let file = file.clone();
+                    let data = data.clone();
+                    thread::spawn(move || {
+                        let offset = idx * region;
+                        let mut buf = vec![0u8; region];
+                        let read = file.pread(offset as i64, &mut buf).ok().unwrap();
+                        assert_eq!(region, read);
+                        assert_eq!(&data[offset..offset + region], &buf[..]);
+                    })
+                })
+                .collect();
+            for handle in handles {
+                handle.join().unwrap();
+            }
+
+            // EOF
+            let mut buf = [0u8; 16];
+            assert_eq!(0, file.pread(data.len() as i64, &mut buf).ok().unwrap());
+            assert!(Arc::try_unwrap(file).ok().unwrap().close().is_ok());
+
+            // Clean up
+            assert!(fs.delete(&test_file, false).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_open_not_found() {
+        let dfs = get_dfs();
+        {
+            let fs = dfs.get_hdfs().ok().unwrap();
+
+            let uuid = Uuid::new_v4().to_string();
+            let test_file = format!("/{}", uuid);
+
+            match fs.open(&test_file) {
+                Err(HdfsErr::FileNotFound(path)) => assert_eq!(test_file, path),
+                other => panic!("Unexpected result {:?}", other),
+            }
+            match fs.get_file_status(&test_file) {
+                Err(HdfsErr::FileNotFound(path)) => assert_eq!(test_file, path),
+                other => panic!("Unexpected result {:?}", other.map(|s| s.len())),
+            }
+        }
+    }
+
+    #[test]
+    fn test_builder_with_conf() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let test_file = temp_dir.path().join(Uuid::new_v4().to_string());
+        let test_file = test_file.to_str().unwrap();
+
+        let fs = HdfsFsBuilder::new()
+            .set_name_node("file:///")
+            .set_conf("io.file.buffer.size", "65536")
+            .connect()
+            .ok()
+            .unwrap();
+        assert_eq!("file:///", fs.url());
+
+        let mut file = fs.create(test_file).ok().unwrap();
+        file.write_all(b"hello").unwrap();
+        assert!(file.close().is_ok());
+        assert!(fs.exist(test_file));
+        assert_eq!(5, fs.get_file_status(test_file).ok().unwrap().len());
+
+        // Clean up
+        assert!(fs.delete(test_file, false).is_ok());
+    }
+
+    #[test]
+    fn test_builder_with_confs() {
+        let dfs = get_dfs();
+        {
+            let block_size = 2 * 1024 * 1024;
+            let confs: HashMap<String, String> = [
+                ("dfs.replication", "1".to_owned()),
+                ("dfs.blocksize", block_size.to_string()),
+                ("io.file.buffer.size", "65536".to_owned()),
+                ("dfs.client.read.shortcircuit", "false".to_owned()),
+            ]
+            .into_iter()
+            .map(|(key, value)| (key.to_owned(), value))
+            .collect();
+
+            let mut builder = HdfsFsBuilder::new();
+            builder
+                .set_name_node(&dfs.namenode_addr())
+                .set_confs(&confs);
+            let mut keys: Vec<&String> = confs.keys().collect();
+            keys.sort();
+            assert_eq!(
+                keys,
+                builder.confs.iter().map(|(key, _)| key).collect::<Vec<_>>()
+            );
+
+            let fs = builder.connect().ok().unwrap();
+            let test_file = format!("/{}", Uuid::new_v4());
+            assert!(fs.write_all(&test_file, b"hello", false).is_ok());
+            let status = fs.get_file_status(&test_file).ok().unwrap();
+            assert_eq!(block_size, status.block_size());
+            assert_eq!(1, status.replica_count());
+
+            // Clean up
+            assert!(fs.delete(&test_file, false).ok().unwrap());
+        }
+    }
+
+    #[test]
+    fn test_connect_as_user() {
+        assert!(matches!(
+            HdfsFs::connect_as_user("localhost", 0, ""),
+            Err(HdfsErr::InvalidArgument(_))
+        ));
+
+        let dfs = get_dfs();
+        {
+            let fs = dfs.get_hdfs().ok().unwrap();
+            let port = dfs.namenode_port().unwrap() as u16;
+
+            // A directory which can be written by anyone
+            let test_dir = format!("/{}", Uuid::new_v4());
+            assert!(fs.mkdir(&test_dir).is_ok());
+            assert!(fs.chmod(&test_dir, 0o777).is_ok());
+
+            let user = "fs_hdfs_test_user";
+            let user_fs = HdfsFs::connect_as_user("localhost", port, user)
+                .ok()
+                .unwrap();
+            let test_file = format!("{}/{}", test_dir, Uuid::new_v4());
+            let file = user_fs.create(&test_file).ok().unwrap();
+            assert!(file.close().is_ok());
+
+            let file_info = fs.get_file_status(&test_file).ok().unwrap();
+            assert_eq!(user, file_info.owner());
+
+            // Clean up
+            assert!(fs.delete(&test_dir, true).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_list_status_recursive() {
+        let dfs = get_dfs();
+        {
+            let minidfs_addr = dfs.namenode_addr();
+            let fs = dfs.get_hdfs().ok().unwrap();
+
+            let test_dir = format!("/{}", Uuid::new_v4());
+            let dirs = ["a", "a/b", "a/b/c"];
+            let files = ["f0", "a/f1", "a/b/f2", "a/b/c/f3"];
+            for dir in dirs.iter() {
+                assert!(fs.mkdir(&format!("{}/{}", test_dir, dir)).is_ok());
+            }
+            for file in files.iter() {
+                let file = fs.create(&format!("{}/{}", test_dir, file)).ok().unwrap();
+                assert!(file.close().is_ok());
+            }
+
+            let mut expected: Vec<String> = dirs
+                .iter()
+                .chain(files.iter())
+                .map(|path| format!("{}{}/{}", minidfs_addr, test_dir, path))
+                .collect();
+            expected.sort();
+
+            let list = fs.list_status_recursive(&test_dir).ok().unwrap();
+            let mut names: Vec<String> =
+                list.iter().map(|status| status.name().to_owned()).collect();
+            names.sort();
+            assert_eq!(expected, names);
+
+            // Missing root
+            let missing_dir = format!("/{}", Uuid::new_v4());
+            assert!(matches!(
+                fs.list_status_recursive(&missing_dir),
+                Err(HdfsErr::FileNotFound(_))
+            ));
+
+            // Clean up
+            assert!(fs.delete(&test_dir, true).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_glob() {
+        let dfs = get_dfs();
+        {
+            let minidfs_addr = dfs.namenode_addr();
+            let fs = dfs.get_hdfs().ok().unwrap();
+
+            let test_dir = format!("/{}", Uuid::new_v4());
+            let dirs = ["2021", "2022", "logs"];
+            let files = [
+                "2021/a.parquet",
+                "2021/b.parquet",
+                "2021/b.csv",
+                "2022/part-1",
+                "2022/part-2",
+                "2022/part-10",
+            ];
+            for dir in dirs.iter() {
+                assert!(fs.mkdir(&format!("{}/{}", test_dir, dir)).is_ok());
+            }
+            for file in files.iter() {
+                let file = fs.create(&format!("{}/{}", test_dir, file)).ok().unwrap();
+                assert!(file.close().is_ok());
+            }
+
+            let glob_names = |pattern: &str| -> Vec<String> {
+                fs.glob(&format!("{}/{}", test_dir, pattern))
+                    .ok()
+                    .unwrap()
+                    .iter()
+                    .map(|status| status.name().to_owned())
+                    .collect()
+            };
+            let expected_names = |paths: &[&str]| -> Vec<String> {
+                paths
+                    .iter()
+                    .map(|path| format!("{}{}/{}", minidfs_addr, test_dir, path))
+                    .collect()
+            };
+
+            // Single star, not crossing the segment
+            assert_eq!(
+                expected_names(&["2021/a.parquet", "2021/b.parquet"]),
+                glob_names("*/*.parquet")
+            );
+            assert_eq!(expected_names(&["2021", "2022", "logs"]), glob_names("*"));
+
+            // Question mark
+            assert_eq!(
+                expected_names(&["2022/part-1", "2022/part-2"]),
+                glob_names("2022/part-?")
+            );
+
+            // Character class
+            assert_eq!(
+                expected_names(&["2021/b.csv", "2021/b.parquet"]),
+                glob_names("202[0-1]/[b]*")
+            );
+            assert_eq!(
+                expected_names(&["2022/part-1", "2022/part-10"]),
+                glob_names("20[!1]?/part-1*")
+            );
+
+            // Literal path
+            assert_eq!(expected_names(&["2021/b.csv"]), glob_names("2021/b.csv"));
+
+            // Nothing matched
+            assert!(glob_names("2023/*").is_empty());
+            assert!(glob_names("*/missing/*").is_empty());
+            assert!(glob_names("2021/a.parquet/*").is_empty());
+
+            // Clean up
+            assert!(fs.delete(&test_dir, true).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_set_replication() {
+        let dfs = get_dfs();
+        {
+            let fs = dfs.get_hdfs().ok().unwrap();
+
+            let test_file = format!("/{}", Uuid::new_v4());
+            let mut file = fs.create(&test_file).ok().unwrap();
+            assert!(file.write_all(b"replication").is_ok());
+            assert!(file.close().is_ok());
+
+            assert!(fs.set_replication(&test_file, 2).ok().unwrap());
+            let status = fs.get_file_status(&test_file).ok().unwrap();
+            assert_eq!(2, status.replica_count());
+
+            // Invalid replication
+            assert!(matches!(
+                fs.set_replication(&test_file, 0),
+                Err(HdfsErr::InvalidArgument(_))
+            ));
+            assert!(matches!(
+                fs.set_replication(&test_file, u16::MAX),
+                Err(HdfsErr::InvalidArgument(_))
+            ));
+
+            // Clean up
+            assert!(fs.delete(&test_file, false).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_chmod() {
+        let dfs = get_dfs();
+        {
+            let fs = dfs.get_hdfs().ok().unwrap();
+
+            let test_file = format!("/{}", Uuid::new_v4());
+            let file = fs.create(&test_file).ok().unwrap();
+            assert!(file.close().is_ok());
+
+            assert!(fs.chmod(&test_file, 0o600).ok().unwrap());
+            let status = fs.get_file_status(&test_file).ok().unwrap();
+            assert_eq!(0o600, status.permission());
+
+            // Missing path
+            let missing_file = format!("/{}", Uuid::new_v4());
+            assert!(matches!(
+                fs.chmod(&missing_file, 0o600),
+                Err(HdfsErr::FileNotFound(_))
+            ));
+
+            // Clean up
+            assert!(fs.delete(&test_file, false).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_chown() {
+        let dfs = get_dfs();
+        {
+            let fs = dfs.get_hdfs().ok().unwrap();
+
+            let test_file = format!("/{}", Uuid::new_v4());
+            let file = fs.create(&test_file).ok().unwrap();
+            assert!(file.close().is_ok());
+            let owner = fs
+                .get_file_status(&test_file)
+                .ok()
+                .unwrap()
+                .owner()
+                .to_owned();
+
+            let group = "fs_hdfs_test_group";
+            assert!(fs.chown(&test_file, None, Some(group)).ok().unwrap());
+            let status = fs.get_file_status(&test_file).ok().unwrap();
+            assert_eq!(group, status.group());
+            assert_eq!(owner, status.owner());
+
+            // No-op
+            assert!(fs.chown(&test_file, None, None).ok().unwrap());
+
+            // Empty group
+            assert!(matches!(
+                fs.chown(&test_file, None, Some("")),
+                Err(HdfsErr::InvalidArgument(_))
+            ));
+
+            // Clean up
+            assert!(fs.delete(&test_file, false).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_set_times() {
+        let dfs = get_dfs();
+        {
+            let fs = dfs.get_hdfs().ok().unwrap();
+
+            let test_file = format!("/{}", Uuid::new_v4());
+            let file = fs.create(&test_file).ok().unwrap();
+            assert!(file.close().is_ok());
+
+            // FileStatus reports times in the precision of seconds as well
+            let epoch_secs = |secs: i64| UNIX_EPOCH + Duration::from_secs(secs as u64);
+            let mtime = 1_600_000_000;
+            let atime = 1_600_000_100;
+            assert!(fs.set_times(&test_file, mtime, atime).ok().unwrap());
+            let status = fs.get_file_status(&test_file).ok().unwrap();
+            assert_eq!(epoch_secs(mtime), status.last_modified());
+            assert_eq!(epoch_secs(atime), status.last_access());
+
+            // Leave the access time unchanged
+            assert!(fs.set_times(&test_file, mtime + 1, -1).ok().unwrap());
+            let status = fs.get_file_status(&test_file).ok().unwrap();
+            assert_eq!(epoch_secs(mtime + 1), status.last_modified());
+            assert_eq!(epoch_secs(atime), status.last_access());
+
+            // Clean up
+            assert!(fs.delete(&test_file, false).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_truncate() {
+        let dfs = get_dfs();
+        {
+            let fs = dfs.get_hdfs().ok().unwrap();
+
+            let test_file = format!("/{}", Uuid::new_v4());
+            let mut file = fs.create(&test_file).ok().unwrap();
+            assert!(file.write_all(&[1u8; 1000]).is_ok());
+            assert!(file.close().is_ok());
+
+            let completed = fs.truncate(&test_file, 400).ok().unwrap();
+            if !completed {
+                // Wait for the recovery of the last block
+                let mut retries = 0;
+                while fs.get_file_status(&test_file).ok().unwrap().len() != 400 {
+                    retries += 1;
+                    assert!(retries < 100, "truncate is not completed in time");
+                    thread::sleep(Duration::from_millis(100));
+                }
+            }
+            let status = fs.get_file_status(&test_file).ok().unwrap();
+            assert_eq!(400, status.len());
+
+            // Negative length
+            assert!(matches!(
+                fs.truncate(&test_file, -1),
+                Err(HdfsErr::InvalidArgument(_))
+            ));
+
+            // Clean up
+            assert!(fs.delete(&test_file, false).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_concat() {
+        let dfs = get_dfs();
+        {
+            let fs = dfs.get_hdfs().ok().unwrap();
+
+            let test_dir = format!("/{}", Uuid::new_v4());
+            assert!(fs.mkdir(&test_dir).is_ok());
+
+            // Every file except the last source should consist of full blocks
+            let block_size = 1024 * 1024;
+            let contents = [(b'a', block_size), (b'b', block_size), (b'c', 100)];
+            let paths: Vec<String> = (0..contents.len())
+                .map(|idx| format!("{}/part-{}", test_dir, idx))
+                .collect();
+            for (path, (byte, len)) in paths.iter().zip(contents.iter()) {
+                let mut file = fs
+                    .create_with_params(path, false, 0, 1, block_size as i32)
+                    .ok()
+                    .unwrap();
+                assert!(file.write_all(&vec![*byte; *len]).is_ok());
+                assert!(file.close().is_ok());
+            }
+
+            let sources: Vec<&str> =
+                paths[1..].iter().map(|path| path.as_str()).collect();
+            assert!(fs.concat(&paths[0], &sources).ok().unwrap());
+
+            let status = fs.get_file_status(&paths[0]).ok().unwrap();
+            assert_eq!(2 * block_size + 100, status.len());
+            for source in sources.iter() {
+                assert!(!fs.exist(source));
+            }
+
+            let mut buf = vec![0u8; status.len()];
+            let file = fs.open(&paths[0]).ok().unwrap();
+            assert_eq!(buf.len(), file.pread(0, &mut buf).ok().unwrap());
+            assert!(buf[..block_size].iter().all(|byte| *byte == b'a'));
+            assert!(buf[block_size..2 * block_size]
+                .iter()
+                .all(|byte| *byte == b'b'));
+            assert!(buf[2 * block_size..].iter().all(|byte| *byte == b'c'));
+            assert!(file.close().is_ok());
+
+            // No sources
+            assert!(matches!(
+                fs.concat(&paths[0], &[]),
+                Err(HdfsErr::InvalidArgument(_))
+            ));
+
+            // Clean up
+            assert!(fs.delete(&test_dir, true).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_available() {
+        let dfs = get_dfs();
+        {
+            let fs = dfs.get_hdfs().ok().unwrap();
+
+            let test_file = format!("/{}", Uuid::new_v4());
+            let mut file = fs.create(&test_file).ok().unwrap();
+            assert!(file.write_all(&[1u8; 1000]).is_ok());
+            // Not open for read
+            assert!(matches!(file.available(), Err(HdfsErr::Io(_))));
+            assert!(file.close().is_ok());
+
+            let file = fs.open(&test_file).ok().unwrap();
+            assert!(file.available().ok().unwrap() > 0);
+            assert!(file.close().is_ok());
+
+            // Clean up
+            assert!(fs.delete(&test_file, false).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_hflush_and_hsync() {
+        let dfs = get_dfs();
+        {
+            let fs = dfs.get_hdfs().ok().unwrap();
+
+            let test_file = format!("/{}", Uuid::new_v4());
+            let mut file = fs.create(&test_file).ok().unwrap();
+            let data = b"visible to new readers";
+            assert!(file.write_all(data).is_ok());
+            assert!(file.hflush().ok().unwrap());
+
+            // A new reader sees the data before the writer is closed
+            let mut buf = vec![0u8; data.len()];
+            let reader = fs.open(&test_file).ok().unwrap();
+            assert_eq!(data.len(), reader.pread(0, &mut buf).ok().unwrap());
+            assert_eq!(&data[..], &buf[..]);
+
+            assert!(file.write_all(data).is_ok());
+            assert!(file.hsync().ok().unwrap());
+            assert!(file.close().is_ok());
+
+            // Not open for write
+            assert!(matches!(reader.hflush(), Err(HdfsErr::Io(_))));
+            assert!(matches!(reader.hsync(), Err(HdfsErr::Io(_))));
+            assert!(reader.close().is_ok());
+
+            // Clean up
+            assert!(fs.delete(&test_file, false).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_close_when_dropped() {
+        let dfs = get_dfs();
+        {
+            let fs = dfs.get_hdfs().ok().unwrap();
+
+            let test_file = format!("/{}", Uuid::new_v4());
+            let data = b"flushed when dropped";
+            {
+                let mut file = fs.create(&test_file).ok().unwrap();
+                assert!(file.write_all(data).is_ok());
+            }
+
+            let file = fs.open(&test_file).ok().unwrap();
+            let mut buf = vec![0u8; data.len()];
+            assert_eq!(data.len(), file.pread(0, &mut buf).ok().unwrap());
+            assert_eq!(&data[..], &buf[..]);
+
+            // Closed explicitly, which isn't closed again when dropped
+            assert!(file.close().is_ok());
+
+            // Clean up
+            assert!(fs.delete(&test_file, false).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_get_cached() {
+        let dfs = get_dfs();
+        {
+            let minidfs_addr = dfs.namenode_addr();
+            let fs1 = HdfsFs::get_cached(&minidfs_addr).ok().unwrap();
+            let fs2 = HdfsFs::get_cached(&format!("{}/some/path", minidfs_addr))
+                .ok()
+                .unwrap();
+            assert_eq!(fs1.raw(), fs2.raw());
+            assert_eq!(fs1.raw(), fs1.clone().raw());
+
+            // The connection is still alive for the other clones
+            drop(fs1);
+            assert!(fs2.get_file_status("/").is_ok());
+        }
+    }
+
+    #[test]
+    fn test_disconnect() {
+        let dfs = get_dfs();
+        {
+            let minidfs_addr = dfs.namenode_addr();
+            let fs = HdfsFsBuilder::new()
+                .set_name_node(&minidfs_addr)
+                .connect()
+                .ok()
+                .unwrap();
+            let other_fs = fs.clone();
+
+            // Still referred to by the other clone
+            assert!(matches!(
+                fs.disconnect(),
+                Err(HdfsErr::ConnectionInUse(_, 1))
+            ));
+
+            assert!(other_fs.exist("/"));
+            assert!(other_fs.disconnect().is_ok());
+
+            // A cached instance is referred to by the cache
+            let cached_fs = HdfsFs::get_cached(&minidfs_addr).ok().unwrap();
+            assert!(matches!(
+                cached_fs.disconnect(),
+                Err(HdfsErr::ConnectionInUse(_, _))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_get_hosts() {
+        let dfs = get_dfs();
+        {
+            let fs = dfs.get_hdfs().ok().unwrap();
+
+            // 3 blocks
+            let block_size = 1024 * 1024;
+            let len = 2 * block_size + block_size / 2;
+            let test_file = format!("/{}", Uuid::new_v4());
+            let mut file = fs
+                .create_with_params(&test_file, false, 0, 1, block_size as i32)
+                .ok()
+                .unwrap();
+            assert!(file.write_all(&vec![1u8; len]).is_ok());
+            assert!(file.close().is_ok());
+
+            let blocks = fs.get_hosts(&test_file, 0, len as i64).ok().unwrap();
+            assert_eq!(3, blocks.len());
+            for hosts in blocks.iter() {
+                assert!(!hosts.is_empty());
+            }
+
+            // The blocks overlapping with the range only
+            let blocks = fs
+                .get_hosts(&test_file, block_size as i64, 10)
+                .ok()
+                .unwrap();
+            assert_eq!(1, blocks.len());
+
+            // Empty range
+            assert!(fs.get_hosts(&test_file, 0, 0).ok().unwrap().is_empty());
+
+            // Clean up
+            assert!(fs.delete(&test_file, false).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_block_size() {
+        let dfs = get_dfs();
+        {
+            let fs = dfs.get_hdfs().ok().unwrap();
+
+            let default_block_size = fs.default_block_size().ok().unwrap();
+            assert!(default_block_size > 0);
+            assert_eq!(
+                default_block_size,
+                fs.default_block_size_at_path("/").ok().unwrap()
+            );
+
+            let block_size = 2 * 1024 * 1024;
+            assert_ne!(default_block_size, block_size);
+            let test_file = format!("/{}", Uuid::new_v4());
+            let file = fs
+                .create_with_params(&test_file, false, 0, 0, block_size as i32)
+                .ok()
+                .unwrap();
+            assert!(file.close().is_ok());
+            assert_eq!(block_size, fs.block_size(&test_file).ok().unwrap());
+
+            // Missing file
+            let missing_file = format!("/{}", Uuid::new_v4());
+            assert!(matches!(
+                fs.block_size(&missing_file),
+                Err(HdfsErr::FileNotFound(_))
+            ));
+
+            // Clean up
+            assert!(fs.delete(&test_file, false).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_capacity_and_used() {
+        let dfs = get_dfs();
+        {
+            let fs = dfs.get_hdfs().ok().unwrap();
+
+            let test_file = format!("/{}", Uuid::new_v4());
+            let mut file = fs.create(&test_file).ok().unwrap();
+            assert!(file.write_all(&[1u8; 1000]).is_ok());
+            assert!(file.close().is_ok());
+
+            // The usage is reported by the datanodes periodically
+            let capacity = fs.capacity().ok().unwrap();
+            let used = fs.used().ok().unwrap();
+            assert!(used >= 0);
+            assert!(capacity > used);
+
+            // Clean up
+            assert!(fs.delete(&test_file, false).is_ok());
+        }
+
+        let local_fs = HdfsFsBuilder::new().connect().ok().unwrap();
+        assert!(matches!(local_fs.capacity(), Err(HdfsErr::Unsupported(_))));
+        assert!(matches!(local_fs.used(), Err(HdfsErr::Unsupported(_))));
+    }
+
+    #[test]
+    fn test_working_directory() {
+        let dfs = get_dfs();
+        {
+            let minidfs_addr = dfs.namenode_addr();
+            // Not to affect the others sharing the cached connection
+            let fs = HdfsFsBuilder::new()
+                .set_name_node(&minidfs_addr)
+                .connect()
+                .ok()
+                .unwrap();
+
+            let test_dir = format!("/{}", Uuid::new_v4());
+            assert!(fs.mkdir(&test_dir).is_ok());
+            assert!(fs.set_working_directory(&test_dir).ok().unwrap());
+            assert_eq!(
+                format!("{}{}", minidfs_addr, test_dir),
+                fs.working_directory().ok().unwrap()
+            );
+
+            // Relative path
+            let file = fs.create("relative").ok().unwrap();
+            assert!(file.close().is_ok());
+            assert!(fs.exist(&format!("{}/relative", test_dir)));
+
+            // A long working directory which doesn't fit into the initial buffer
+            let long_dir = format!("{}/{}", test_dir, "d".repeat(300));
+            assert!(fs.set_working_directory(&long_dir).ok().unwrap());
+            assert_eq!(
+                format!("{}{}", minidfs_addr, long_dir),
+                fs.working_directory().ok().unwrap()
+            );
+
+            // Clean up
+            assert!(fs.delete(&test_dir, true).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_rename() {
+        let dfs = get_dfs();
+        {
+            let fs = dfs.get_hdfs().ok().unwrap();
+
+            let test_dir = format!("/{}", Uuid::new_v4());
+            assert!(fs.mkdir(&test_dir).is_ok());
+            let old_file = format!("{}/old", test_dir);
+            let new_file = format!("{}/new", test_dir);
+            assert!(fs.create(&old_file).ok().unwrap().close().is_ok());
+
+            assert!(fs.rename(&old_file, &new_file).ok().unwrap());
+            assert!(!fs.exist(&old_file));
+            assert!(fs.exist(&new_file));
+
+            // Onto an existing file
+            assert!(fs.create(&old_file).ok().unwrap().close().is_ok());
+            assert!(matches!(
+                fs.rename(&old_file, &new_file),
+                Err(HdfsErr::FileAlreadyExists(_))
+            ));
+
+            // Into an existing directory
+            let sub_dir = format!("{}/sub", test_dir);
+            assert!(fs.mkdir(&sub_dir).is_ok());
+            assert!(fs.rename(&old_file, &sub_dir).ok().unwrap());
+            assert!(!fs.exist(&old_file));
+            assert!(fs.exist(&format!("{}/old", sub_dir)));
+
+            // Clean up
+            assert!(fs.delete(&test_dir, true).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_mkdir_with_mode() {
+        let dfs = get_dfs();
+        {
+            let fs = dfs.get_hdfs().ok().unwrap();
+
+            let test_dir = format!("/{}", Uuid::new_v4());
+            let leaf_dir = format!("{}/a/b", test_dir);
+            assert!(fs.mkdir_with_mode(&leaf_dir, 0o700).ok().unwrap());
+            let status = fs.get_file_status(&leaf_dir).ok().unwrap();
+            assert!(status.is_directory());
+            assert_eq!(0o700, status.permission());
+
+            // Existing directory
+            assert!(fs.mkdir_with_mode(&leaf_dir, 0o750).ok().unwrap());
+            let status = fs.get_file_status(&leaf_dir).ok().unwrap();
+            assert_eq!(0o750, status.permission());
+
+            // Clean up
+            assert!(fs.delete(&test_dir, true).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_open_with_params() {
+        let dfs = get_dfs();
+        {
+            let fs = dfs.get_hdfs().ok().unwrap();
+
+            let block_size = 32 * 1024 * 1024;
+            let test_file = format!("/{}", Uuid::new_v4());
+            let mut file = fs
+                .open_with_params(&test_file, O_WRONLY, 4096, 1, block_size)
+                .ok()
+                .unwrap();
+            assert!(file.write_all(b"custom block size").is_ok());
+            assert!(file.close().is_ok());
+            assert_eq!(block_size, fs.block_size(&test_file).ok().unwrap());
+            let status = fs.get_file_status(&test_file).ok().unwrap();
+            assert_eq!(1, status.replica_count());
+
+            // Invalid block size
+            assert!(matches!(
+                fs.open_with_params(&test_file, O_WRONLY, 0, 0, block_size + 1),
+                Err(HdfsErr::InvalidArgument(_))
+            ));
+            assert!(matches!(
+                fs.open_with_params(&test_file, O_WRONLY, 0, 0, 4 * 1024 * 1024 * 1024),
+                Err(HdfsErr::InvalidArgument(_))
+            ));
+
+            // Clean up
+            assert!(fs.delete(&test_file, false).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_append() {
+        let dfs = get_dfs();
+        {
+            let fs = dfs.get_hdfs().ok().unwrap();
+
+            let test_file = format!("/{}", Uuid::new_v4());
+            let mut file = fs.create(&test_file).ok().unwrap();
+            assert!(file.write_all(b"hello ").is_ok());
+            assert!(file.close().is_ok());
+
+            let mut file = fs.append(&test_file).ok().unwrap();
+            assert!(file.is_open_for_write());
+            assert!(file.write_all(b"world").is_ok());
+            assert!(file.close().is_ok());
+
+            let status = fs.get_file_status(&test_file).ok().unwrap();
+            assert_eq!(11, status.len());
+            let mut buf = vec![0u8; 11];
+            let file = fs.open(&test_file).ok().unwrap();
+            assert_eq!(11, file.pread(0, &mut buf).ok().unwrap());
+            assert_eq!(b"hello world", &buf[..]);
+            assert!(file.close().is_ok());
+
+            // Missing file
+            let missing_file = format!("/{}", Uuid::new_v4());
+            assert!(matches!(
+                fs.append(&missing_file),
+                Err(HdfsErr::FileNotFound(_))
+            ));
+
+            // Clean up
+            assert!(fs.delete(&test_file, false).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_handle_mode() {
+        let dfs = get_dfs();
+        {
+            let fs = dfs.get_hdfs().ok().unwrap();
+
+            let test_file = format!("/{}", Uuid::new_v4());
+            let mut file = fs.create(&test_file).ok().unwrap();
+            assert!(file.is_open_for_write());
+            assert!(!file.is_open_for_read());
+            let mut buf = [0u8; 10];
+            assert!(matches!(
+                file.read(&mut buf),
+                Err(HdfsErr::InvalidHandleMode(_))
+            ));
+            assert!(matches!(
+                file.pread(0, &mut buf),
+                Err(HdfsErr::InvalidHandleMode(_))
+            ));
+            assert!(matches!(file.seek(0), Err(HdfsErr::InvalidHandleMode(_))));
+            assert!(Read::read(&mut file, &mut buf).is_err());
+            assert!(file.write(b"handle mode").is_ok());
+            assert!(file.close().is_ok());
+
+            let mut file = fs.open(&test_file).ok().unwrap();
+            assert!(file.is_open_for_read());
+            assert!(!file.is_open_for_write());
+            assert!(matches!(
+                file.write(b"handle mode"),
+                Err(HdfsErr::InvalidHandleMode(_))
+            ));
+            assert!(Write::write(&mut file, b"handle mode").is_err());
+            assert!(file.seek(3).ok().unwrap());
+            assert_eq!(8, file.read(&mut buf).ok().unwrap());
+            assert!(file.close().is_ok());
+
+            let file = fs.append(&test_file).ok().unwrap();
+            assert!(file.is_open_for_write());
+            assert!(!file.is_open_for_read());
+            assert!(file.close().is_ok());
+
+            // Clean up
+            assert!(fs.delete(&test_file, false).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_direct_read() {
+        let dfs = get_dfs();
+        {
+            let fs = dfs.get_hdfs().ok().unwrap();
+
+            let test_file = format!("/{}", Uuid::new_v4());
+            let data = b"direct read";
+            let mut file = fs.create(&test_file).ok().unwrap();
+            assert!(file.write_all(data).is_ok());
+            assert!(!file.uses_direct_read());
+            assert!(file.close().is_ok());
+
+            // It depends on the environment
+            let file = fs.open(&test_file).ok().unwrap();
+            let _ = file.uses_direct_read();
+            file.disable_direct_read();
+            assert!(!file.uses_direct_read());
+
+            let mut buf = vec![0u8; data.len()];
+            assert_eq!(data.len(), file.pread(0, &mut buf).ok().unwrap());
+            assert_eq!(&data[..], &buf[..]);
+            assert!(file.close().is_ok());
+
+            // Clean up
+            assert!(fs.delete(&test_file, false).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_file_status() {
+        let dfs = get_dfs();
+        {
+            let minidfs_addr = dfs.namenode_addr();
+            let fs = dfs.get_hdfs().ok().unwrap();
+
+            let test_dir = format!("/{}", Uuid::new_v4());
+            assert!(fs.mkdir(&test_dir).is_ok());
+            let test_file = format!("{}/file", test_dir);
+            let before = SystemTime::now() - Duration::from_secs(60);
+            let mut file = fs
+                .create_with_params(&test_file, false, 0, 1, 1024 * 1024)
+                .ok()
+                .unwrap();
+            assert!(file.write_all(&[1u8; 1000]).is_ok());
+            assert!(file.close().is_ok());
+            assert!(fs.chmod(&test_file, 0o640).is_ok());
+
+            let status = fs.get_file_status(&test_file).ok().unwrap();
+            assert_eq!(format!("{}{}", minidfs_addr, test_file), status.name());
+            assert_eq!(FileKind::File, status.kind());
+            assert!(status.is_file());
+            assert!(!status.is_directory());
+            assert_eq!(1000, status.len());
+            assert_eq!(1024 * 1024, status.block_size());
+            assert_eq!(1, status.replica_count());
+            assert!(!status.owner().is_empty());
+            assert!(!status.group().is_empty());
+            assert_eq!(0o640, status.permission());
+            assert!(status.last_modified() > before);
+            assert!(status.last_access() > before);
+
+            let status = fs.get_file_status(&test_dir).ok().unwrap();
+            assert_eq!(FileKind::Directory, status.kind());
+            assert!(status.is_directory());
+            assert_eq!(0, status.len());
+
+            // Listed entries are owned as well
+            let list = fs.list_status(&test_dir).ok().unwrap();
+            assert_eq!(1, list.len());
+            let listed = list[0].clone();
+            drop(list);
+            assert_eq!(format!("{}{}", minidfs_addr, test_file), listed.name());
+
+            // Clean up
+            assert!(fs.delete(&test_dir, true).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_list_iter() {
+        let dfs = get_dfs();
+        {
+            let minidfs_addr = dfs.namenode_addr();
+            let fs = dfs.get_hdfs().ok().unwrap();
+
+            let test_dir = format!("/{}", Uuid::new_v4());
+            assert!(fs.mkdir(&test_dir).is_ok());
+            let mut expected: Vec<String> =
+                (0..5).map(|idx| format!("{}/f{}", test_dir, idx)).collect();
+            for path in expected.iter() {
+                assert!(fs.create(path).ok().unwrap().close().is_ok());
+            }
+            expected = expected
+                .iter()
+                .map(|path| format!("{}{}", minidfs_addr, path))
+                .collect();
+
+            let mut names: Vec<String> = fs
+                .list_iter(&test_dir)
+                .ok()
+                .unwrap()
+                .map(|status| status.ok().unwrap().name().to_owned())
+                .collect();
+            names.sort();
+            assert_eq!(expected, names);
+
+            // Stop early
+            let mut iter = fs.list_iter(&test_dir).ok().unwrap();
+            assert!(iter.next().unwrap().is_ok());
+            drop(iter);
+
+            // Empty directory
+            let empty_dir = format!("{}/empty", test_dir);
+            assert!(fs.mkdir(&empty_dir).is_ok());
+            assert_eq!(0, fs.list_iter(&empty_dir).ok().unwrap().count());
+
+            // Missing directory
+            let missing_dir = format!("/{}", Uuid::new_v4());
+            assert!(matches!(
+                fs.list_iter(&missing_dir),
+                Err(HdfsErr::FileNotFound(_))
+            ));
+
+            // Clean up
+            assert!(fs.delete(&test_dir, true).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_share_fs_across_threads() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<HdfsFs>();
+        assert_send_sync::<HdfsFile>();
+
+        let dfs = get_dfs();
+        {
+            let fs = Arc::new(dfs.get_hdfs().ok().unwrap());
+
+            let test_dir = format!("/{}", Uuid::new_v4());
+            assert!(fs.mkdir(&test_dir).is_ok());
+
+            let handles: Vec<_> = (0..8)
+                .map(|idx| {
+                    let fs = fs.clone();
+                    let path = format!("{}/f{}", test_dir, idx);
+                    thread::spawn(move || {
+                        let mut file = fs.create(&path).ok().unwrap();
+                        file.write_all(path.as_bytes()).unwrap();
+                        assert!(file.close().is_ok());
+                        path
+                    })
+                })
+                .collect();
+            let paths: Vec<String> =
+                handles.into_iter().map(|h| h.join().unwrap()).collect();
+
+            assert_eq!(8, fs.list_status(&test_dir).ok().unwrap().len());
+            for path in paths.iter() {
+                let mut file = fs.open(path).ok().unwrap();
+                let mut content = String::new();
+                file.read_to_string(&mut content).unwrap();
+                assert_eq!(path, &content);
+                assert!(file.close().is_ok());
+            }
+
+            // Clean up
+            assert!(fs.delete(&test_dir, true).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_path_kind() {
+        let dfs = get_dfs();
+        {
+            let fs = dfs.get_hdfs().ok().unwrap();
+
+            let test_dir = format!("/{}", Uuid::new_v4());
+            let test_file = format!("{}/file", test_dir);
+            assert!(fs.mkdir(&test_dir).is_ok());
+            assert!(fs.create(&test_file).ok().unwrap().close().is_ok());
+
+            assert_eq!(Some(FileKind::File), fs.path_kind(&test_file).ok().unwrap());
+            assert_eq!(
+                Some(FileKind::Directory),
+                fs.path_kind(&test_dir).ok().unwrap()
+            );
+            let missing = format!("{}/missing", test_dir);
+            assert_eq!(None, fs.path_kind(&missing).ok().unwrap());
+
+            // Clean up
+            assert!(fs.delete(&test_dir, true).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_delete() {
+        let dfs = get_dfs();
+        {
+            let fs = dfs.get_hdfs().ok().unwrap();
+
+            let test_dir = format!("/{}", Uuid::new_v4());
+            let test_file = format!("{}/file", test_dir);
+            assert!(fs.mkdir(&test_dir).is_ok());
+            assert!(fs.create(&test_file).ok().unwrap().close().is_ok());
+
+            // Non-empty directory without recursion
+            assert!(matches!(
+                fs.delete(&test_dir, false),
+                Err(HdfsErr::DirectoryNotEmpty(_))
+            ));
+            assert!(fs.exist(&test_file));
+
+            // File
+            assert!(fs.delete(&test_file, false).ok().unwrap());
+            assert!(!fs.exist(&test_file));
+
+            // Missing path
+            assert!(!fs.delete(&test_file, false).ok().unwrap());
+            assert!(!fs.delete(&test_file, true).ok().unwrap());
+
+            // Empty directory without recursion
+            assert!(fs.delete(&test_dir, false).ok().unwrap());
+            assert!(!fs.exist(&test_dir));
+        }
+    }
+
+    #[test]
+    fn test_read_to_vec() {
+        let dfs = get_dfs();
+        {
+            let fs = dfs.get_hdfs().ok().unwrap();
+
+            let test_dir = format!("/{}", Uuid::new_v4());
+            let test_file = format!("{}/file", test_dir);
+            assert!(fs.mkdir(&test_dir).is_ok());
+
+            let data: Vec<u8> = (0..300 * 1024).map(|i| (i % 253) as u8).collect();
+            let mut file = fs.create(&test_file).ok().unwrap();
+            file.write_all(&data).unwrap();
+            assert!(file.close().is_ok());
+            assert_eq!(data, fs.read_to_vec(&test_file).ok().unwrap());
+
+            // Empty file
+            let empty_file = format!("{}/empty", test_dir);
+            assert!(fs.create(&empty_file).ok().unwrap().close().is_ok());
+            assert!(fs.read_to_vec(&empty_file).ok().unwrap().is_empty());
+
+            // Directory
+            assert!(matches!(
+                fs.read_to_vec(&test_dir),
+                Err(HdfsErr::IsDirectory(_))
+            ));
+
+            // Clean up
+            assert!(fs.delete(&test_dir, true).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_write_all() {
+        let dfs = get_dfs();
+        {
+            let fs = dfs.get_hdfs().ok().unwrap();
+
+            let test_dir = format!("/{}", Uuid::new_v4());
+            let test_file = format!("{}/file", test_dir);
+            assert!(fs.mkdir(&test_dir).is_ok());
+            // No temporary file is left next to the file
+            let only_file = || fs.list_status(&test_dir).ok().unwrap().len() == 1;
+
+            let data: Vec<u8> = (0..200 * 1024).map(|i| (i % 239) as u8).collect();
+            assert!(fs.write_all(&test_file, &data, false).is_ok());
+            assert_eq!(data, fs.read_to_vec(&test_file).ok().unwrap());
+            assert!(only_file());
+
+            // No overwrite
+            assert!(matches!(
+                fs.write_all(&test_file, b"new", false),
+                Err(HdfsErr::FileAlreadyExists(_))
+            ));
+            assert_eq!(data, fs.read_to_vec(&test_file).ok().unwrap());
+
+            // Overwrite
+            assert!(fs.write_all(&test_file, b"new", true).is_ok());
+            assert_eq!(b"new".to_vec(), fs.read_to_vec(&test_file).ok().unwrap());
+            assert!(only_file());
+
+            // Fail to write under a file, leaving no temporary file
+            let nested_file = format!("{}/nested", test_file);
+            assert!(fs.write_all(&nested_file, b"data", false).is_err());
+            assert!(only_file());
+            assert_eq!(b"new".to_vec(), fs.read_to_vec(&test_file).ok().unwrap());
+
+            // Concurrent writers of the same path don't clash on the temporary file
+            let writers: Vec<_> = (0..4u8)
+                .map(|idx| {
+                    let fs = fs.clone();
+                    let test_file = test_file.clone();
+                    thread::spawn(move || fs.write_all(&test_file, &[idx; 1024], true))
+                })
+                .collect();
+            for writer in writers {
+                assert!(writer.join().unwrap().is_ok());
+            }
+            let written = fs.read_to_vec(&test_file).ok().unwrap();
+            assert_eq!(1024, written.len());
+            assert!(written.iter().all(|b| *b == written[0]));
+            assert!(only_file());
+            assert!(fs.write_all(&test_file, b"new", true).is_ok());
+
+            // Directory
+            assert!(matches!(
+                fs.write_all(&test_dir, b"data", true),
+                Err(HdfsErr::IsDirectory(_))
+            ));
+
+            // Clean up
+            assert!(fs.delete(&test_dir, true).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_write_all_rename_failure() {
+        let dfs = get_dfs();
+        {
+            let fs = dfs.get_hdfs().ok().unwrap();
+            let test_file = format!("/{}", Uuid::new_v4());
+            let moved_file = format!("/{}", Uuid::new_v4());
+            assert!(fs.write_all(&test_file, b"old", false).is_ok());
+
+            // The temporary file is moved away before the rename, which fails
+            let written = fs.write_via_tmp_file(&test_file, true, |file| {
+                file.write_chunked(b"new", usize::MAX)?;
+                fs.rename(file.path(), &moved_file)?;
+                Ok(3)
+            });
+            assert!(written.is_err());
+            assert_eq!(b"old".to_vec(), fs.read_to_vec(&test_file).ok().unwrap());
+
+            // Clean up
+            assert!(fs.delete(&test_file, false).ok().unwrap());
+            assert!(fs.delete(&moved_file, false).ok().unwrap());
+        }
+    }
+    #[test]
+    fn test_exception() {
+        let dfs = get_dfs();
+        {
+            let fs = dfs.get_hdfs().ok().unwrap();
+
+            let test_file = format!("/{}", Uuid::new_v4());
+            assert!(fs.write_all(&test_file, b"data", false).is_ok());
+
+            // HDFS rejects to truncate to a larger size by an exception without errno
+            match fs.truncate(&test_file, 1024) {
+                Err(HdfsErr::Exception { message }) => assert!(!message.is_empty()),
+                other => panic!("Unexpected result {:?}", other),
+            }
+
+            // Clean up
+            assert!(fs.delete(&test_file, false).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_invalid_path() {
+        let dfs = get_dfs();
+        {
+            let fs = dfs.get_hdfs().ok().unwrap();
+
+            let invalid = "/invalid\0path";
+            assert!(matches!(fs.open(invalid), Err(HdfsErr::InvalidPath(_))));
+            assert!(matches!(fs.create(invalid), Err(HdfsErr::InvalidPath(_))));
+            assert!(matches!(
+                fs.get_file_status(invalid),
+                Err(HdfsErr::InvalidPath(_))
+            ));
+            assert!(matches!(fs.mkdir(invalid), Err(HdfsErr::InvalidPath(_))));
+            assert!(matches!(
+                fs.rename("/", invalid),
+                Err(HdfsErr::InvalidPath(_))
+            ));
+            assert!(matches!(
+                fs.delete(invalid, false),
+                Err(HdfsErr::InvalidPath(_))
+            ));
+            assert!(!fs.exist(invalid));
+        }
+    }
+
+    #[test]
+    fn test_stats() {
+        let dfs = get_dfs();
+        {
+            let fs = dfs.get_hdfs().ok().unwrap();
+
+            let stats = fs.stats().ok().unwrap();
+            assert!(stats.capacity > 0);
+            assert!(stats.remaining >= 0);
+            assert!(stats.used + stats.remaining <= stats.capacity);
+            assert_eq!(
+                fs.default_block_size().ok().unwrap(),
+                stats.default_block_size
+            );
+        }
+
+        let local_fs = HdfsFsBuilder::new().connect().ok().unwrap();
+        assert!(matches!(local_fs.stats(), Err(HdfsErr::Unsupported(_))));
+    }
+
+    #[test]
+    fn test_content_summary() {
+        let dfs = get_dfs();
+        {
+            let fs = dfs.get_hdfs().ok().unwrap();
+
+            // root/{f0, a/{f1, b/{f2}}, c/}
+            let root = format!("/{}", Uuid::new_v4());
+            for dir in ["a/b", "c"].iter() {
+                assert!(fs.mkdir(&format!("{}/{}", root, dir)).is_ok());
+            }
+            for (file, len) in [("f0", 100), ("a/f1", 200), ("a/b/f2", 300)].iter() {
+                let mut file = fs.create(&format!("{}/{}", root, file)).ok().unwrap();
+                assert!(file.write_all(&vec![1u8; *len]).is_ok());
+                assert!(file.close().is_ok());
+            }
+
+            let summary = fs.content_summary(&root).ok().unwrap();
+            assert_eq!(600, summary.length);
+            assert_eq!(3, summary.file_count);
+            assert_eq!(4, summary.directory_count);
+
+            let summary = fs.content_summary(&format!("{}/a/f1", root)).ok().unwrap();
+            assert_eq!(200, summary.length);
+            assert_eq!(1, summary.file_count);
+            assert_eq!(0, summary.directory_count);
+
+            let summary = fs.content_summary(&format!("{}/c", root)).ok().unwrap();
+            assert_eq!(0, summary.length);
+            assert_eq!(0, summary.file_count);
+            assert_eq!(1, summary.directory_count);
+
+            // Clean up
+            assert!(fs.delete(&root, true).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_create_new() {
+        let dfs = get_dfs();
+        {
+            let fs = dfs.get_hdfs().ok().unwrap();
+
+            let test_file = format!("/{}", Uuid::new_v4());
+            let mut file = fs.create_new(&test_file).ok().unwrap();
+            assert!(file.write_all(b"first").is_ok());
+            assert!(file.close().is_ok());
+
+            assert!(matches!(
+                fs.create_new(&test_file),
+                Err(HdfsErr::FileAlreadyExists(_))
+            ));
+            assert_eq!(b"first".to_vec(), fs.read_to_vec(&test_file).ok().unwrap());
+
+            // Only one of the concurrent creations succeeds
+            let racing_file = format!("/{}", Uuid::new_v4());
+            let handles: Vec<_> = (0..4)
+                .map(|_| {
+                    let fs = fs.clone();
+                    let racing_file = racing_file.clone();
+                    thread::spawn(move || match fs.create_new(&racing_file) {
+                        Ok(file) => file.close().is_ok(),
+                        Err(_) => false,
+                    })
+                })
+                .collect();
+            let created = handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap())
+                .filter(|created| *created)
+                .count();
+            assert_eq!(1, created);
+
+            // Clean up
+            assert!(fs.delete(&test_file, false).is_ok());
+            assert!(fs.delete(&racing_file, false).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_delete_collect_errors() {
+        let dfs = get_dfs();
+        {
+            let fs = dfs.get_hdfs().ok().unwrap();
+
+            // parent/root/{f0, hidden/{f3}, locked/{f1}, unlocked/{f2}}, where f1 can't
+            // be deleted by others since its directory isn't writable, and hidden can't
+            // be listed by others
+            let parent = format!("/{}", Uuid::new_v4());
+            let root = format!("{}/root", parent);
+            for dir in ["hidden", "locked", "unlocked"].iter() {
+                assert!(fs.mkdir(&format!("{}/{}", root, dir)).is_ok());
+            }
+            for file in ["f0", "hidden/f3", "locked/f1", "unlocked/f2"].iter() {
+                let path = format!("{}/{}", root, file);
+                assert!(fs.create(&path).ok().unwrap().close().is_ok());
+            }
+            for dir in [&parent, &root, &format!("{}/unlocked", root)].iter() {
+                assert!(fs.chmod(dir, 0o777).is_ok());
+            }
+            assert!(fs.chmod(&format!("{}/locked", root), 0o555).is_ok());
+            assert!(fs.chmod(&format!("{}/hidden", root), 0o700).is_ok());
+
+            let other_fs = HdfsFsBuilder::new()
+                .set_name_node(&dfs.namenode_addr())
+                .set_user("other")
+                .connect()
+                .ok()
+                .unwrap();
+            let mut failures = other_fs.delete_collect_errors(&root).err().unwrap();
+            failures.sort_by(|a, b| a.0.cmp(&b.0));
+            assert_eq!(2, failures.len());
+            assert!(failures[0].0.ends_with("/hidden"));
+            assert!(matches!(failures[0].1, HdfsErr::PermissionDenied(_)));
+            assert!(failures[1].0.ends_with("/locked/f1"));
+            assert!(matches!(failures[1].1, HdfsErr::PermissionDenied(_)));
+
+            assert!(fs.exist(&format!("{}/hidden/f3", root)));
+            assert!(!fs.exist(&format!("{}/f0", root)));
+            assert!(!fs.exist(&format!("{}/unlocked", root)));
+            assert!(fs.exist(&format!("{}/locked/f1", root)));
+
+            // Nothing fails
+            assert!(fs.delete_collect_errors(&root).is_ok());
+            assert!(!fs.exist(&root));
+            assert!(fs.delete_collect_errors(&root).is_err());
+
+            // Clean up
+            assert!(fs.delete(&parent, true).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_parse_namenode() {
+        // A nameservice of an HA cluster has no port
+        assert_eq!(
+            ("hdfs://mycluster".to_owned(), None),
+            parse_namenode("hdfs://mycluster/user/data").ok().unwrap()
+        );
+        assert_eq!(
+            "hdfs://mycluster",
+            get_namenode_uri("hdfs://mycluster").ok().unwrap()
+        );
+
+        assert_eq!(
+            ("hdfs://host".to_owned(), Some(8020)),
+            parse_namenode("hdfs://host:8020/user/data").ok().unwrap()
+        );
+        assert_eq!(
+            "hdfs://host:8020",
+            get_namenode_uri("hdfs://host:8020").ok().unwrap()
+        );
+
+        assert_eq!(
+            ("file:///".to_owned(), None),
+            parse_namenode("file:///tmp").ok().unwrap()
+        );
+        assert!(matches!(
+            parse_namenode("s3://bucket/data"),
+            Err(HdfsErr::InvalidUrl(_))
+        ));
+        assert!(matches!(
+            parse_namenode("hdfs:///data"),
+            Err(HdfsErr::InvalidUrl(_))
+        ));
+    }
+
+    #[test]
+    fn test_rewind() {
+        let dfs = get_dfs();
+        {
+            let fs = dfs.get_hdfs().ok().unwrap();
+            let test_file = format!("/{}", Uuid::new_v4());
+
+            let data: Vec<u8> = (0..(100 * 1024 + 3)).map(|i| (i % 253) as u8).collect();
+            let mut file = fs.create(&test_file).ok().unwrap();
+            assert!(matches!(file.rewind(), Err(HdfsErr::InvalidHandleMode(_))));
+            file.write_all(&data).unwrap();
+            assert!(file.close().is_ok());
+
+            let mut file = fs.open(&test_file).ok().unwrap();
+            let mut read = Vec::new();
+            file.read_to_end(&mut read).unwrap();
+            assert_eq!(data, read);
+
+            assert!(file.rewind().is_ok());
+            assert_eq!(0, file.pos().ok().unwrap());
+            let mut reread = Vec::new();
+            file.read_to_end(&mut reread).unwrap();
+            assert_eq!(read, reread);
+            assert!(file.close().is_ok());
+
+            // Clean up
+            assert!(fs.delete(&test_file, false).ok().unwrap());
+        }
+    }
+
+    #[test]
+    fn test_tail() {
+        let dfs = get_dfs();
+        {
+            let fs = dfs.get_hdfs().ok().unwrap();
+            let test_file = format!("/{}", Uuid::new_v4());
+
+            let data: Vec<u8> = (0..10 * 1024).map(|i| (i % 247) as u8).collect();
+            assert!(fs.write_all(&test_file, &data, false).is_ok());
+
+            assert_eq!(
+                &data[data.len() - 100..],
+                &fs.tail(&test_file, 100).ok().unwrap()[..]
+            );
+            assert_eq!(data, fs.tail(&test_file, data.len()).ok().unwrap());
+            assert_eq!(data, fs.tail(&test_file, 20 * 1024).ok().unwrap());
+            assert!(fs.tail(&test_file, 0).ok().unwrap().is_empty());
+
+            assert!(matches!(fs.tail("/", 100), Err(HdfsErr::IsDirectory(_))));
+            let missing = format!("/{}", Uuid::new_v4());
+            assert!(fs.tail(&missing, 100).is_err());
+
+            // Clean up
+            assert!(fs.delete(&test_file, false).ok().unwrap());
+        }
+    }
+
+    #[test]
+    fn test_head() {
+        let dfs = get_dfs();
+        {
+            let fs = dfs.get_hdfs().ok().unwrap();
+            let test_file = format!("/{}", Uuid::new_v4());
+
+            // A file starting and ending with the magic number of parquet
+            let mut data = b"PAR1".to_vec();
+            data.extend((0..10 * 1024).map(|i| (i % 247) as u8));
+            data.extend_from_slice(b"PAR1");
+            assert!(fs.write_all(&test_file, &data, false).is_ok());
+
+            assert_eq!(b"PAR1".to_vec(), fs.head(&test_file, 4).ok().unwrap());
+            assert_eq!(
+                fs.tail(&test_file, 4).ok().unwrap(),
+                fs.head(&test_file, 4).ok().unwrap()
+            );
+            assert_eq!(data, fs.head(&test_file, 20 * 1024).ok().unwrap());
+            assert!(fs.head(&test_file, 0).ok().unwrap().is_empty());
+
+            assert!(matches!(fs.head("/", 4), Err(HdfsErr::IsDirectory(_))));
+            let missing = format!("/{}", Uuid::new_v4());
+            assert!(fs.head(&missing, 4).is_err());
+
+            // Clean up
+            assert!(fs.delete(&test_file, false).ok().unwrap());
+        }
+    }
+
+    #[test]
+    fn test_read_lines() {
+        let dfs = get_dfs();
+        {
+            let fs = dfs.get_hdfs().ok().unwrap();
+            let test_file = format!("/{}", Uuid::new_v4());
+
+            let long_line = "x".repeat(200 * 1024);
+            let data = format!("first\n\nthird\r\n{}\nlast", long_line);
+            assert!(fs.write_all(&test_file, data.as_bytes(), false).is_ok());
+            let lines: Vec<String> = fs
+                .read_lines(&test_file)
+                .ok()
+                .unwrap()
+                .map(|line| line.ok().unwrap())
+                .collect();
+            assert_eq!(
+                vec!["first", "", "third", long_line.as_str(), "last"],
+                lines
+            );
+
+            // Ending with a newline
+            assert!(fs.write_all(&test_file, b"a\nb\n", true).is_ok());
+            let lines: Vec<String> = fs
+                .read_lines(&test_file)
+                .ok()
+                .unwrap()
+                .map(|line| line.ok().unwrap())
+                .collect();
+            assert_eq!(vec!["a", "b"], lines);
+
+            // Invalid UTF-8
+            assert!(fs.write_all(&test_file, b"a\n\xff\xfe\nc", true).is_ok());
+            let mut lines = fs.read_lines(&test_file).ok().unwrap();
+            assert_eq!("a", lines.next().unwrap().ok().unwrap());
+            assert!(matches!(
+                lines.next().unwrap(),
+                Err(HdfsErr::InvalidData(_))
+            ));
+            assert_eq!("c", lines.next().unwrap().ok().unwrap());
+            assert!(lines.next().is_none());
+
+            // Dropped before the end
+            let mut lines = fs.read_lines(&test_file).ok().unwrap();
+            assert!(lines.next().is_some());
+            drop(lines);
+
+            let missing = format!("/{}", Uuid::new_v4());
+            assert!(fs.read_lines(&missing).is_err());
+
+            // Clean up
+            assert!(fs.delete(&test_file, false).ok().unwrap());
+        }
+    }
+
+    #[cfg(feature = "ffi_log")]
+    #[test]
+    fn test_ffi_log() {
+        use std::sync::Mutex;
+
+        use log::{Level, LevelFilter, Log, Metadata, Record};
+
+        struct CapturingLogger(Mutex<Vec<(Level, String)>>);
+
+        impl Log for CapturingLogger {
+            fn enabled(&self, _: &Metadata) -> bool {
+                true
+            }
+
+            fn log(&self, record: &Record) {
+                self.0
+                    .lock()
+                    .unwrap()
+                    .push((record.level(), record.args().to_string()));
+            }
+
+            fn flush(&self) {}
+        }
+
+        static LOGGER: CapturingLogger = CapturingLogger(Mutex::new(Vec::new()));
+        log::set_logger(&LOGGER).unwrap();
+        log::set_max_level(LevelFilter::Debug);
+
+        let dfs = get_dfs();
+        {
+            let fs = dfs.get_hdfs().ok().unwrap();
+            let missing = format!("/{}", Uuid::new_v4());
+            assert!(fs.open(&missing).is_err());
+            // A missing path is not an error of the probes
+            let probed = format!("/{}", Uuid::new_v4());
+            assert_eq!(None, fs.path_kind(&probed).ok().unwrap());
+            assert!(!fs.delete(&probed, false).ok().unwrap());
+
+            // The records of the other tests running concurrently are captured as well
+            let records = LOGGER.0.lock().unwrap();
+            assert!(records.iter().any(|(level, message)| {
+                *level == Level::Debug
+                    && message.starts_with("hdfsOpenFile(")
+                    && message.contains(&missing)
+            }));
+            assert!(records.iter().any(|(level, message)| {
+                *level == Level::Error
+                    && message.contains(&missing)
+                    && message.contains("FileNotFound")
+            }));
+            assert!(records.iter().any(|(level, message)| {
+                *level == Level::Debug && message.contains(&probed)
+            }));
+            assert!(!records.iter().any(|(level, message)| {
+                *level == Level::Error && message.contains(&probed)
+            }));
+        }
+    }
+
+    #[test]
+    fn test_copy_to_writer() {
+        let dfs = get_dfs();
+        {
+            let fs = dfs.get_hdfs().ok().unwrap();
+
+            // Multiple blocks with a partial last block
+            let block_size: usize = 1024 * 1024;
+            let data: Vec<u8> = (0..(2 * block_size + 333))
+                .map(|i| (i % 241) as u8)
+                .collect();
+            let test_file = format!("/{}", Uuid::new_v4());
+            let mut file = fs
+                .create_with_params(&test_file, false, 0, 1, block_size as i32)
+                .ok()
+                .unwrap();
+            assert!(file.write_all(&data).is_ok());
+            assert!(file.close().is_ok());
+
+            let mut sink = Vec::new();
+            assert_eq!(
+                data.len() as u64,
+                fs.copy_to_writer(&test_file, &mut sink).ok().unwrap()
+            );
+            assert_eq!(data, sink);
+
+            // An error of the writer
+            let mut full = [0u8; 100];
+            assert!(matches!(
+                fs.copy_to_writer(&test_file, &mut &mut full[..]),
+                Err(HdfsErr::Sink(_))
+            ));
+
+            let missing = format!("/{}", Uuid::new_v4());
+            assert!(fs.copy_to_writer(&missing, &mut sink).is_err());
+
+            // Clean up
+            assert!(fs.delete(&test_file, false).ok().unwrap());
+        }
+    }
+
+    #[test]
+    fn test_write_from_reader() {
+        let dfs = get_dfs();
+        {
+            let fs = dfs.get_hdfs().ok().unwrap();
+            let test_file = format!("/{}", Uuid::new_v4());
+
+            // Multiple chunks with a partial last one
+            let data: Vec<u8> = (0..(2 * 1024 * 1024 + 77))
+                .map(|i| (i % 251) as u8)
+                .collect();
+            let mut reader = io::Cursor::new(data.clone());
+            assert_eq!(
+                data.len() as u64,
+                fs.write_from_reader(&test_file, &mut reader, false)
+                    .ok()
+                    .unwrap()
+            );
+            assert_eq!(
+                data.len(),
+                fs.get_file_status(&test_file).ok().unwrap().len()
+            );
+            assert_eq!(data, fs.read_to_vec(&test_file).ok().unwrap());
+
+            let mut reader = io::Cursor::new(b"overwritten".to_vec());
+            assert!(matches!(
+                fs.write_from_reader(&test_file, &mut reader, false),
+                Err(HdfsErr::FileAlreadyExists(_))
+            ));
+            assert_eq!(
+                11,
+                fs.write_from_reader(&test_file, &mut reader, true)
+                    .ok()
+                    .unwrap()
+            );
+            assert_eq!(
+                b"overwritten".to_vec(),
+                fs.read_to_vec(&test_file).ok().unwrap()
+            );
+
+            // An error of the reader keeps the existing file
+            struct FailingReader;
+            impl Read for FailingReader {
+                fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+                    Err(io::Error::other("broken source"))
+                }
+            }
+            assert!(matches!(
+                fs.write_from_reader(&test_file, &mut FailingReader, true),
+                Err(HdfsErr::Source(_))
+            ));
+            assert_eq!(
+                b"overwritten".to_vec(),
+                fs.read_to_vec(&test_file).ok().unwrap()
+            );
+
+            // Clean up
+            assert!(fs.delete(&test_file, false).ok().unwrap());
+        }
+    }
+
+    #[test]
+    fn test_raw_file() {
+        let dfs = get_dfs();
+        {
+            let fs = dfs.get_hdfs().ok().unwrap();
+            let test_file = format!("/{}", Uuid::new_v4());
+            assert!(fs.write_all(&test_file, b"raw", false).is_ok());
+
+            let file = fs.open(&test_file).ok().unwrap();
+            assert!(!file.raw().is_null());
+            assert_eq!(1, unsafe {
+                crate::native::hdfsFileIsOpenForRead(file.raw())
+            });
+            // Still owned by the file
+            assert!(file.close().is_ok());
+
+            // Clean up
+            assert!(fs.delete(&test_file, false).ok().unwrap());
+        }
+    }
+
+    #[test]
+    fn test_set_replication_recursive() {
+        let dfs = get_dfs();
+        {
+            let fs = dfs.get_hdfs().ok().unwrap();
+
+            // root/{f0, a/{f1, b/{f2}}, empty/}
+            let root = format!("/{}", Uuid::new_v4());
+            assert!(fs.mkdir(&format!("{}/a/b", root)).is_ok());
+            assert!(fs.mkdir(&format!("{}/empty", root)).is_ok());
+            let files: Vec<String> = ["f0", "a/f1", "a/b/f2"]
+                .iter()
+                .map(|file| format!("{}/{}", root, file))
+                .collect();
+            for file in files.iter() {
+                assert!(fs.write_all(file, b"replication", false).is_ok());
+            }
+
+            assert_eq!(3, fs.set_replication_recursive(&root, 2).ok().unwrap());
+            for file in files.iter() {
+                assert_eq!(2, fs.get_file_status(file).ok().unwrap().replica_count());
+            }
+
+            // A single file
+            assert_eq!(1, fs.set_replication_recursive(&files[0], 1).ok().unwrap());
+            assert_eq!(
+                1,
+                fs.get_file_status(&files[0]).ok().unwrap().replica_count()
+            );
+
+            assert!(matches!(
+                fs.set_replication_recursive(&root, 0),
+                Err(HdfsErr::InvalidArgument(_))
+            ));
+            let missing = format!("/{}", Uuid::new_v4());
+            assert!(fs.set_replication_recursive(&missing, 2).is_err());
+
+            // Clean up
+            assert!(fs.delete(&root, true).ok().unwrap());
+        }
+    }
+
+    #[test]
+    fn test_move_glob() {
+        let dfs = get_dfs();
+        {
+            let fs = dfs.get_hdfs().ok().unwrap();
+
+            // src/{a.log, b.log, c.txt, d.log/}
+            let root = format!("/{}", Uuid::new_v4());
+            let src = format!("{}/src", root);
+            assert!(fs.mkdir(&format!("{}/d.log", src)).is_ok());
+            for file in ["a.log", "b.log", "c.txt"].iter() {
+                let path = format!("{}/{}", src, file);
+                assert!(fs.write_all(&path, file.as_bytes(), false).is_ok());
+            }
+
+            let archive = format!("{}/archive", root);
+            let pattern = format!("{}/*.log", src);
+            assert_eq!(2, fs.move_glob(&pattern, &archive).ok().unwrap());
+            for file in ["a.log", "b.log"].iter() {
+                assert!(!fs.exist(&format!("{}/{}", src, file)));
+                let moved = format!("{}/{}", archive, file);
+                assert_eq!(
+                    file.as_bytes().to_vec(),
+                    fs.read_to_vec(&moved).ok().unwrap()
+                );
+            }
+            assert!(fs.exist(&format!("{}/c.txt", src)));
+            assert!(fs.exist(&format!("{}/d.log", src)));
+
+            // Nothing to move
+            assert_eq!(0, fs.move_glob(&pattern, &archive).ok().unwrap());
+            // Already in the destination
+            let archived = format!("{}/*.log", archive);
+            assert_eq!(0, fs.move_glob(&archived, &archive).ok().unwrap());
+
+            // A collision moves nothing
+            assert!(fs
+                .write_all(&format!("{}/a.log", src), b"new", false)
+                .is_ok());
+            assert!(fs.write_all(&format!("{}/e.log", src), b"e", false).is_ok());
+            assert!(matches!(
+                fs.move_glob(&pattern, &archive),
+                Err(HdfsErr::FileAlreadyExists(_))
+            ));
+            assert!(fs.exist(&format!("{}/e.log", src)));
+            assert_eq!(
+                b"a.log".to_vec(),
+                fs.read_to_vec(&format!("{}/a.log", archive)).ok().unwrap()
+            );
+
+            // Clean up
+            assert!(fs.delete(&root, true).ok().unwrap());
+        }
+    }
+
+    #[test]
+    fn test_open_options_flags() {
+        let flags = |opts: &HdfsOpenOptions| opts.flags().ok().unwrap();
+        assert_eq!(O_RDONLY, flags(HdfsOpenOptions::new().read(true)));
+        assert_eq!(
+            O_WRONLY,
+            flags(HdfsOpenOptions::new().write(true).create(true))
+        );
+        assert_eq!(
+            O_WRONLY | O_EXCL,
+            flags(HdfsOpenOptions::new().write(true).create_new(true))
+        );
+        assert_eq!(
+            O_WRONLY | O_APPEND,
+            flags(HdfsOpenOptions::new().append(true))
+        );
+        assert_eq!(
+            O_WRONLY | O_APPEND,
+            flags(HdfsOpenOptions::new().write(true).append(true))
+        );
+
+        let invalid = |opts: &HdfsOpenOptions| {
+            matches!(opts.flags(), Err(HdfsErr::InvalidArgument(_)))
+        };
+        assert!(invalid(&HdfsOpenOptions::new()));
+        assert!(invalid(HdfsOpenOptions::new().read(true).append(true)));
+        assert!(invalid(
+            HdfsOpenOptions::new().read(true).write(true).create(true)
+        ));
+        assert!(invalid(HdfsOpenOptions::new().write(true)));
+        assert!(invalid(HdfsOpenOptions::new().create(true)));
+        assert!(invalid(HdfsOpenOptions::new().append(true).create(true)));
+        assert!(invalid(
+            HdfsOpenOptions::new()
+                .write(true)
+                .create(true)
+                .create_new(true)
+        ));
+    }
+
+    #[test]
+    fn test_open_with() {
+        let dfs = get_dfs();
+        {
+            let fs = dfs.get_hdfs().ok().unwrap();
+            let test_file = format!("/{}", Uuid::new_v4());
+
+            let mut file = fs
+                .open_with(
+                    &test_file,
+                    HdfsOpenOptions::new().write(true).create_new(true),
+                )
+                .ok()
+                .unwrap();
+            assert!(file.write_all(b"open").is_ok());
+            assert!(file.close().is_ok());
+            assert!(matches!(
+                fs.open_with(
+                    &test_file,
+                    HdfsOpenOptions::new().write(true).create_new(true)
+                ),
+                Err(HdfsErr::FileAlreadyExists(_))
+            ));
+
+            let mut file = fs
+                .open_with(&test_file, HdfsOpenOptions::new().append(true))
+                .ok()
+                .unwrap();
+            assert!(file.write_all(b" with").is_ok());
+            assert!(file.close().is_ok());
+
+            let mut file = fs
+                .open_with(&test_file, HdfsOpenOptions::new().read(true))
+                .ok()
+                .unwrap();
+            let mut read = String::new();
+            file.read_to_string(&mut read).unwrap();
+            assert_eq!("open with", read);
+            assert!(file.close().is_ok());
+
+            // Rejected before opening
+            assert!(matches!(
+                fs.open_with(&test_file, HdfsOpenOptions::new().read(true).append(true)),
+                Err(HdfsErr::InvalidArgument(_))
+            ));
+
+            // Clean up
+            assert!(fs.delete(&test_file, false).ok().unwrap());
+        }
+    }
+
+    #[test]
+    fn test_block_locations() {
+        let dfs = get_dfs();
+        {
+            let fs = dfs.get_hdfs().ok().unwrap();
+
+            // 3 blocks with a short last one
+            let block_size: usize = 1024 * 1024;
+            let len = 2 * block_size + 1000;
+            let test_file = format!("/{}", Uuid::new_v4());
+            let mut file = fs
+                .create_with_params(&test_file, false, 0, 1, block_size as i32)
+                .ok()
+                .unwrap();
+            assert!(file.write_all(&vec![1u8; len]).is_ok());
+            assert!(file.close().is_ok());
+
+            let locations = fs.block_locations(&test_file).ok().unwrap();
+            assert_eq!(3, locations.len());
+            let mut offset = 0;
+            for location in locations.iter() {
+                assert_eq!(offset, location.offset);
+                assert!(!location.hosts.is_empty());
+                offset += location.length;
+            }
+            assert_eq!(len as i64, offset);
+            assert_eq!(1000, locations[2].length);
+
+            // Empty file
+            let empty_file = format!("/{}", Uuid::new_v4());
+            assert!(fs.create(&empty_file).ok().unwrap().close().is_ok());
+            assert!(fs.block_locations(&empty_file).ok().unwrap().is_empty());
+
+            assert!(matches!(
+                fs.block_locations("/"),
+                Err(HdfsErr::IsDirectory(_))
+            ));
+
+            // Clean up
+            assert!(fs.delete(&test_file, false).is_ok());
+            assert!(fs.delete(&empty_file, false).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_c_str_to_string() {
+        let valid = CString::new("/data/part-0").unwrap();
+        assert_eq!("/data/part-0", c_str_to_string(valid.as_ptr()));
+        assert_eq!(
+            "/data/part-0",
+            c_str_to_string_strict(valid.as_ptr()).ok().unwrap()
+        );
+
+        let invalid = CString::new(b"/data/part-\xff".to_vec()).unwrap();
+        assert_eq!("/data/part-\u{FFFD}", c_str_to_string(invalid.as_ptr()));
+        assert!(matches!(
+            c_str_to_string_strict(invalid.as_ptr()),
+            Err(HdfsErr::InvalidPath(_))
+        ));
+
+        assert_eq!("", c_str_to_string(std::ptr::null()));
+        assert_eq!("", c_str_to_string_strict(std::ptr::null()).ok().unwrap());
+    }
+
+    #[test]
+    fn test_list_status_strict() {
+        let dfs = get_dfs();
+        {
+            let fs = dfs.get_hdfs().ok().unwrap();
+            let test_dir = format!("/{}", Uuid::new_v4());
+            for name in ["a", "b"].iter() {
+                assert!(fs.mkdir(&format!("{}/{}", test_dir, name)).is_ok());
+            }
+
+            let names = |list: Vec<super::FileStatus>| -> Vec<String> {
+                let mut names: Vec<String> = list
+                    .iter()
+                    .map(|status| status.name().rsplit('/').next().unwrap().to_owned())
+                    .collect();
+                names.sort();
+                names
+            };
+            assert_eq!(
+                vec!["a", "b"],
+                names(fs.list_status_strict(&test_dir).ok().unwrap())
+            );
+            assert_eq!(
+                names(fs.list_status(&test_dir).ok().unwrap()),
+                names(fs.list_status_strict(&test_dir).ok().unwrap())
+            );
+
+            // Clean up
+            assert!(fs.delete(&test_dir, true).ok().unwrap());
+        }
+    }
+
+    #[test]
+    fn test_wait_for_length() {
+        let dfs = get_dfs();
+        {
+            let fs = dfs.get_hdfs().ok().unwrap();
+            let test_file = format!("/{}", Uuid::new_v4());
+            assert!(fs.write_all(&test_file, &[1u8; 10], false).is_ok());
+
+            assert_eq!(
+                10,
+                fs.wait_for_length(
+                    &test_file,
+                    10,
+                    Duration::from_secs(1),
+                    Duration::from_millis(10)
+                )
+                .ok()
+                .unwrap()
+            );
+            // No deadline
+            assert_eq!(
+                10,
+                fs.wait_for_length(
+                    &test_file,
+                    10,
+                    Duration::MAX,
+                    Duration::from_millis(10)
+                )
+                .ok()
+                .unwrap()
+            );
+            assert!(matches!(
+                fs.wait_for_length(
+                    &test_file,
+                    20,
+                    Duration::from_millis(100),
+                    Duration::from_millis(10)
+                ),
+                Err(HdfsErr::Timeout(_))
+            ));
+
+            let writer = {
+                let fs = fs.clone();
+                let test_file = test_file.clone();
+                thread::spawn(move || {
+                    thread::sleep(Duration::from_millis(200));
+                    let mut file = fs.append(&test_file).ok().unwrap();
+                    assert!(file.write_all(&[2u8; 15]).is_ok());
+                    assert!(file.close().is_ok());
+                })
+            };
+            assert_eq!(
+                25,
+                fs.wait_for_length(
+                    &test_file,
+                    20,
+                    Duration::from_secs(30),
+                    Duration::from_millis(20)
+                )
+                .ok()
+                .unwrap()
+            );
+            writer.join().unwrap();
+
+            let missing = format!("/{}", Uuid::new_v4());
+            assert!(matches!(
+                fs.wait_for_length(
+                    &missing,
+                    1,
+                    Duration::from_millis(100),
+                    Duration::from_millis(10)
+                ),
+                Err(HdfsErr::FileNotFound(_))
+            ));
+
+            // Clean up
+            assert!(fs.delete(&test_file, false).ok().unwrap());
+        }
+    }
+
+    #[test]
+    fn test_close() {
+        let dfs = get_dfs();
+        {
+            let fs = dfs.get_hdfs().ok().unwrap();
+            let test_file = format!("/{}", Uuid::new_v4());
+
+            let mut file = fs.create(&test_file).ok().unwrap();
+            assert!(file.write_all(b"closed").is_ok());
+            assert!(matches!(file.close(), Ok(())));
+            assert_eq!(b"closed".to_vec(), fs.read_to_vec(&test_file).ok().unwrap());
+
+            let file = fs.open(&test_file).ok().unwrap();
+            assert!(matches!(file.close(), Ok(())));
+
+            // Clean up
+            assert!(fs.delete(&test_file, false).ok().unwrap());
+        }
+    }
+
+    #[test]
+    fn test_supports_local_read() {
+        let dfs = get_dfs();
+        {
+            let fs = dfs.get_hdfs().ok().unwrap();
+            let test_file = format!("/{}", Uuid::new_v4());
+            assert!(fs.write_all(&test_file, b"local read", false).is_ok());
+
+            // It depends on the environment
+            assert!(fs.supports_local_read(&test_file).is_ok());
+            let missing = format!("/{}", Uuid::new_v4());
+            assert!(matches!(
+                fs.supports_local_read(&missing),
+                Err(HdfsErr::FileNotFound(_))
+            ));
+
+            // Clean up
+            assert!(fs.delete(&test_file, false).ok().unwrap());
+        }
+    }
+
+    #[test]
+    fn test_exist_many() {
+        let dfs = get_dfs();
+        {
+            let fs = dfs.get_hdfs().ok().unwrap();
+            let test_dir = format!("/{}", Uuid::new_v4());
+            assert!(fs.mkdir(&test_dir).is_ok());
+
+            // Every third path exists, for both a small and a parallel batch
+            let paths: Vec<String> = (0..100)
+                .map(|idx| format!("{}/f{}", test_dir, idx))
+                .collect();
+            for path in paths.iter().step_by(3) {
+                assert!(fs.write_all(path, b"exist", false).is_ok());
+            }
+            let expected: Vec<bool> = (0..paths.len()).map(|idx| idx % 3 == 0).collect();
+
+            let paths: Vec<&str> = paths.iter().map(|path| path.as_str()).collect();
+            assert_eq!(&expected[..10], &fs.exist_many(&paths[..10])[..]);
+            assert_eq!(expected, fs.exist_many(&paths));
+            assert!(fs.exist_many(&[]).is_empty());
+
+            // Clean up
+            assert!(fs.delete(&test_dir, true).ok().unwrap());
+        }
+    }
+
+    #[test]
+    fn test_move_to_trash() {
+        let dfs = get_dfs();
+        {
+            let fs = dfs.get_hdfs().ok().unwrap();
+            let trash_root = fs.trash_root().ok().unwrap();
+            let test_dir = format!("/{}", Uuid::new_v4());
+            let test_file = format!("{}/a.txt", test_dir);
+            assert!(fs.write_all(&test_file, b"trash", false).is_ok());
+
+            assert!(fs.move_to_trash(&test_file).ok().unwrap());
+            assert!(!fs.exist(&test_file));
+            let trash_file = format!("{}/Current{}", trash_root, test_file);
+            assert_eq!(b"trash".to_vec(), fs.read_to_vec(&trash_file).ok().unwrap());
+
+            // A collision in the trash is renamed with the time
+            assert!(fs.write_all(&test_file, b"again", false).is_ok());
+            assert!(fs.move_to_trash(&test_file).ok().unwrap());
+            assert!(!fs.exist(&test_file));
+            let trash_dir = format!("{}/Current{}", trash_root, test_dir);
+            let trashed = fs.list_status(&trash_dir).ok().unwrap();
+            assert_eq!(2, trashed.len());
+            assert!(trashed.iter().all(|status| status.name().contains("a.txt")));
+
+            // Missing path, and a path in the trash
+            assert!(!fs.move_to_trash(&test_file).ok().unwrap());
+            assert!(matches!(
+                fs.move_to_trash(&trash_file),
+                Err(HdfsErr::InvalidArgument(_))
+            ));
+            assert!(matches!(
+                fs.move_to_trash("/"),
+                Err(HdfsErr::InvalidArgument(_))
+            ));
+
+            // Clean up
+            assert!(fs.delete(&test_dir, true).ok().unwrap());
+            assert!(fs.delete(&trash_dir, true).ok().unwrap());
+        }
+    }
+
+    #[test]
+    fn test_permissions() {
+        let permissions = Permissions::from_mode(0o1755).ok().unwrap();
+        let rx = PermissionBits {
+            read: true,
+            write: false,
+            execute: true,
+        };
+        assert_eq!(
+            Permissions {
+                owner: PermissionBits {
+                    read: true,
+                    write: true,
+                    execute: true,
+                },
+                group: rx,
+                other: rx,
+                sticky: true,
+            },
+            permissions
+        );
+        assert_eq!(0o1755, permissions.mode());
+        assert_eq!("rwxr-xr-t", permissions.to_string());
+
+        // Exact round-trip of every valid mode
+        for mode in 0..=Permissions::MODE_MASK {
+            assert_eq!(mode, Permissions::from_mode(mode).ok().unwrap().mode());
+        }
+        assert_eq!(
+            "rw-r----T",
+            Permissions::from_mode(0o1640).ok().unwrap().to_string()
+        );
+        assert!(matches!(
+            Permissions::from_mode(0o4755),
+            Err(HdfsErr::InvalidArgument(_))
+        ));
+        assert!(matches!(
+            Permissions::from_mode(-1),
+            Err(HdfsErr::InvalidArgument(_))
+        ));
+    }
+
+    #[test]
+    fn test_set_permissions() {
+        let dfs = get_dfs();
+        {
+            let fs = dfs.get_hdfs().ok().unwrap();
+            let test_dir = format!("/{}", Uuid::new_v4());
+            assert!(fs.mkdir(&test_dir).is_ok());
+
+            let permissions = Permissions::from_mode(0o1755).ok().unwrap();
+            assert!(fs.set_permissions(&test_dir, &permissions).ok().unwrap());
+            let status = fs.get_file_status(&test_dir).ok().unwrap();
+            assert_eq!(permissions, status.permissions());
+            assert_eq!(0o1755, status.permission());
 
-impl HdfsFileInfoPtr {
-    fn new(ptr: *const hdfsFileInfo) -> HdfsFileInfoPtr {
-        HdfsFileInfoPtr { ptr, len: 1 }
+            // Clean up
+            assert!(fs.delete(&test_dir, false).ok().unwrap());
+        }
     }
 
-    pub fn new_array(ptr: *const hdfsFileInfo, len: i32) -> HdfsFileInfoPtr {
-        HdfsFileInfoPtr { ptr, len }
+    #[test]
+    fn test_read_uninit() {
+        let dfs = get_dfs();
+        {
+            let fs = dfs.get_hdfs().ok().unwrap();
+            let test_file = format!("/{}", Uuid::new_v4());
+            let data: Vec<u8> = (0..100 * 1024).map(|i| (i % 241) as u8).collect();
+            assert!(fs.write_all(&test_file, &data, false).is_ok());
+
+            let mut file = fs.open(&test_file).ok().unwrap();
+            let mut buf = vec![MaybeUninit::<u8>::uninit(); 64 * 1024];
+            let mut read = Vec::new();
+            loop {
+                let len = file.read_uninit(&mut buf).ok().unwrap();
+                if len == 0 {
+                    break;
+                }
+                read.extend(buf[..len].iter().map(|b| unsafe { b.assume_init() }));
+            }
+            assert_eq!(data, read);
+            assert!(file.close().is_ok());
+
+            // A file opened for write
+            let mut file = fs.append(&test_file).ok().unwrap();
+            assert!(matches!(
+                file.read_uninit(&mut buf),
+                Err(HdfsErr::InvalidHandleMode(_))
+            ));
+            assert!(file.close().is_ok());
+
+            // Clean up
+            assert!(fs.delete(&test_file, false).ok().unwrap());
+        }
     }
-}
 
-/// Includes hostnames where a particular block of a file is stored.
-pub struct BlockHosts {
-    ptr: *mut *mut *mut c_char,
-}
+    #[test]
+    fn test_list_dirs_and_files() {
+        let dfs = get_dfs();
+        {
+            let fs = dfs.get_hdfs().ok().unwrap();
+            let test_dir = format!("/{}", Uuid::new_v4());
+            for dir in ["d1", "d2"].iter() {
+                assert!(fs.mkdir(&format!("{}/{}", test_dir, dir)).is_ok());
+            }
+            for file in ["f1", "f2", "f3"].iter() {
+                let file = format!("{}/{}", test_dir, file);
+                assert!(fs.write_all(&file, b"list", false).is_ok());
+            }
 
-impl Drop for BlockHosts {
-    fn drop(&mut self) {
-        unsafe { hdfsFreeHosts(self.ptr) };
+            let names = |statuses: Vec<FileStatus>| -> Vec<String> {
+                let mut names: Vec<String> = statuses
+                    .iter()
+                    .map(|status| path::file_name(status.name()).unwrap())
+                    .collect();
+                names.sort();
+                names
+            };
+            let dirs = fs.list_dirs(&test_dir).ok().unwrap();
+            assert!(dirs.iter().all(|status| status.is_directory()));
+            assert_eq!(vec!["d1", "d2"], names(dirs));
+            let files = fs.list_files(&test_dir).ok().unwrap();
+            assert!(files.iter().all(|status| status.is_file()));
+            assert_eq!(vec!["f1", "f2", "f3"], names(files));
+
+            // Empty and missing directories
+            let empty_dir = format!("{}/d1", test_dir);
+            assert!(fs.list_dirs(&empty_dir).ok().unwrap().is_empty());
+            assert!(fs.list_files(&empty_dir).ok().unwrap().is_empty());
+            let missing = format!("/{}", Uuid::new_v4());
+            assert!(matches!(
+                fs.list_files(&missing),
+                Err(HdfsErr::FileNotFound(_))
+            ));
+
+            // Clean up
+            assert!(fs.delete(&test_dir, true).ok().unwrap());
+        }
     }
-}
 
-pub const LOCAL_FS_SCHEME: &str = "file";
-pub const HDFS_FS_SCHEME: &str = "hdfs";
-pub const VIEW_FS_SCHEME: &str = "viewfs";
+    #[test]
+    fn test_read_range() {
+        let dfs = get_dfs();
+        {
+            let fs = dfs.get_hdfs().ok().unwrap();
+            let test_file = format!("/{}", Uuid::new_v4());
+            let data: Vec<u8> = (0..10 * 1024).map(|i| (i % 239) as u8).collect();
+            assert!(fs.write_all(&test_file, &data, false).is_ok());
+
+            assert_eq!(
+                &data[1000..3000],
+                &fs.read_range(&test_file, 1000, 2000).ok().unwrap()[..]
+            );
+            assert!(fs.read_range(&test_file, 1000, 0).ok().unwrap().is_empty());
+
+            // Past the end of the file
+            assert_eq!(
+                &data[10 * 1024 - 24..],
+                &fs.read_range(&test_file, 10 * 1024 - 24, 100).ok().unwrap()[..]
+            );
+            assert_eq!(data, fs.read_range(&test_file, 0, usize::MAX).ok().unwrap());
+            assert!(fs
+                .read_range(&test_file, 20 * 1024, 100)
+                .ok()
+                .unwrap()
+                .is_empty());
+
+            assert!(matches!(
+                fs.read_range(&test_file, -1, 100),
+                Err(HdfsErr::InvalidArgument(_))
+            ));
+            assert!(matches!(
+                fs.read_range("/", 0, 100),
+                Err(HdfsErr::IsDirectory(_))
+            ));
+
+            // Clean up
+            assert!(fs.delete(&test_file, false).ok().unwrap());
+        }
+    }
 
-#[inline]
-fn get_namenode_uri(path: &str) -> Result<String, HdfsErr> {
-    match Url::parse(path) {
-        Ok(url) => match url.scheme() {
-            LOCAL_FS_SCHEME => Ok("file:///".to_string()),
-            HDFS_FS_SCHEME | VIEW_FS_SCHEME => {
-                if let Some(host) = url.host() {
-                    let mut uri_builder = String::new();
-                    uri_builder.push_str(&(format!("{}://{}", url.scheme(), host)));
-
-                    if let Some(port) = url.port() {
-                        uri_builder.push_str(&(format!(":{}", port)));
-                    }
-                    Ok(uri_builder)
-                } else {
-                    Err(HdfsErr::InvalidUrl(path.to_string()))
-                }
+    #[test]
+    fn test_rename_each() {
+        let dfs = get_dfs();
+        {
+            let fs = dfs.get_hdfs().ok().unwrap();
+            let test_dir = format!("/{}", Uuid::new_v4());
+            assert!(fs.mkdir(&format!("{}/SubDir", test_dir)).is_ok());
+            for file in ["A.txt", "B.TXT", "c.txt"].iter() {
+                let file = format!("{}/{}", test_dir, file);
+                assert!(fs.write_all(&file, file.as_bytes(), false).is_ok());
             }
-            _ => Err(HdfsErr::InvalidUrl(path.to_string())),
-        },
-        Err(_) => Err(HdfsErr::InvalidUrl(path.to_string())),
+
+            let lowercase = |name: &str| Some(name.to_lowercase());
+            assert_eq!(2, fs.rename_each(&test_dir, lowercase).ok().unwrap());
+            let mut names: Vec<String> = fs
+                .list_status(&test_dir)
+                .ok()
+                .unwrap()
+                .iter()
+                .map(|status| path::file_name(status.name()).unwrap())
+                .collect();
+            names.sort();
+            assert_eq!(vec!["SubDir", "a.txt", "b.txt", "c.txt"], names);
+            assert_eq!(
+                format!("{}/A.txt", test_dir).into_bytes(),
+                fs.read_to_vec(&format!("{}/a.txt", test_dir)).ok().unwrap()
+            );
+            assert_eq!(0, fs.rename_each(&test_dir, lowercase).ok().unwrap());
+
+            // Collisions are rejected before renaming anything
+            assert!(matches!(
+                fs.rename_each(&test_dir, |_| Some("same.txt".to_owned())),
+                Err(HdfsErr::FileAlreadyExists(_))
+            ));
+            assert!(matches!(
+                fs.rename_each(&test_dir, |name| if name == "a.txt" {
+                    Some("SubDir".to_owned())
+                } else {
+                    None
+                }),
+                Err(HdfsErr::FileAlreadyExists(_))
+            ));
+            assert!(matches!(
+                fs.rename_each(&test_dir, |name| Some(format!("x/{}", name))),
+                Err(HdfsErr::InvalidArgument(_))
+            ));
+            assert!(fs.exist(&format!("{}/a.txt", test_dir)));
+            assert!(fs.exist(&format!("{}/b.txt", test_dir)));
+
+            // Clean up
+            assert!(fs.delete(&test_dir, true).ok().unwrap());
+        }
     }
-}
 
-#[inline]
-pub fn get_uri(path: &str) -> Result<String, HdfsErr> {
-    let path = if path.starts_with('/') {
-        format!("{}://{}", LOCAL_FS_SCHEME, path)
-    } else {
-        path.to_string()
-    };
-    match Url::parse(&path) {
-        Ok(url) => match url.scheme() {
-            LOCAL_FS_SCHEME | HDFS_FS_SCHEME | VIEW_FS_SCHEME => Ok(url.to_string()),
-            _ => Err(HdfsErr::InvalidUrl(path.to_string())),
-        },
-        Err(_) => Err(HdfsErr::InvalidUrl(path.to_string())),
+    #[test]
+    fn test_ping() {
+        let dfs = get_dfs();
+        {
+            let fs = dfs.get_hdfs().ok().unwrap();
+            assert!(fs.ping().is_ok());
+        }
     }
-}
 
-#[cfg(test)]
-mod test {
-    use uuid::Uuid;
+    #[test]
+    fn test_sync_and_verify() {
+        let dfs = get_dfs();
+        {
+            let fs = dfs.get_hdfs().ok().unwrap();
+            let test_file = format!("/{}", Uuid::new_v4());
+            let mut file = fs.create(&test_file).ok().unwrap();
+
+            let data = vec![7u8; 10 * 1024];
+            assert!(file.write_all(&data).is_ok());
+            assert!(file.sync_and_verify(data.len() as i64).is_ok());
+            assert!(file.write_all(&data).is_ok());
+            assert!(file.sync_and_verify(2 * data.len() as i64).is_ok());
+            assert!(matches!(
+                file.sync_and_verify(data.len() as i64),
+                Err(HdfsErr::LengthMismatch(_, _, _))
+            ));
+            // Longer than the data written
+            match file.sync_and_verify(3 * data.len() as i64) {
+                Err(HdfsErr::LengthMismatch(_, expected, actual)) => {
+                    assert_eq!(3 * data.len() as i64, expected);
+                    assert_eq!(2 * data.len() as i64, actual);
+                }
+                other => panic!("unexpected result {:?}", other),
+            }
+            assert!(file.close().is_ok());
+
+            // Not open for write
+            let reader = fs.open(&test_file).ok().unwrap();
+            assert!(matches!(
+                reader.sync_and_verify(2 * data.len() as i64),
+                Err(HdfsErr::InvalidHandleMode(_))
+            ));
+            assert!(reader.close().is_ok());
+
+            // Clean up
+            assert!(fs.delete(&test_file, false).ok().unwrap());
+        }
+    }
 
-    use crate::minidfs::get_dfs;
+    #[test]
+    fn test_default_io_buffer_size() {
+        let dfs = get_dfs();
+        {
+            // A connection of its own, since the setting is shared by the clones
+            let fs = HdfsFsBuilder::new()
+                .set_name_node(&dfs.namenode_addr())
+                .connect()
+                .ok()
+                .unwrap();
+            let test_file = format!("/{}", Uuid::new_v4());
+            let data: Vec<u8> = (0..(3 * 1024 * 1024 + 7))
+                .map(|i| (i % 229) as u8)
+                .collect();
+
+            // Shared by the clones
+            let clone = fs.clone();
+            assert_eq!(None, fs.default_io_buffer_size());
+            clone.set_default_io_buffer_size(4096);
+            assert_eq!(Some(4096), fs.default_io_buffer_size());
+
+            assert!(fs.write_all(&test_file, &data, false).is_ok());
+            assert_eq!(data, fs.read_to_vec(&test_file).ok().unwrap());
+            let mut copied = Vec::new();
+            fs.copy_to_writer(&test_file, &mut copied).ok().unwrap();
+            assert_eq!(data, copied);
+            let mut reader = io::Cursor::new(data.clone());
+            assert_eq!(
+                data.len() as u64,
+                fs.write_from_reader(&test_file, &mut reader, true)
+                    .ok()
+                    .unwrap()
+            );
+            assert_eq!(data, fs.read_to_vec(&test_file).ok().unwrap());
+
+            fs.set_default_io_buffer_size(0);
+            assert_eq!(None, clone.default_io_buffer_size());
+
+            // In chunks of the block size
+            assert!(fs.write_all(&test_file, &data, true).is_ok());
+            assert_eq!(data, fs.read_to_vec(&test_file).ok().unwrap());
+
+            // Clean up
+            assert!(fs.delete(&test_file, false).ok().unwrap());
+        }
+    }
 
-    #[cfg(feature = "use_existing_hdfs")]
     #[test]
-    fn test_hdfs_default() {
-        let fs = super::get_hdfs().ok().unwrap();
+    fn test_list_status_filtered() {
+        let dfs = get_dfs();
+        {
+            let fs = dfs.get_hdfs().ok().unwrap();
+            let test_dir = format!("/{}", Uuid::new_v4());
+            assert!(fs.mkdir(&format!("{}/dir.parquet", test_dir)).is_ok());
+            for file in ["a.parquet", "b.csv", "c.parquet", "_SUCCESS"].iter() {
+                let file = format!("{}/{}", test_dir, file);
+                assert!(fs.write_all(&file, b"filter", false).is_ok());
+            }
 
-        let uuid = Uuid::new_v4().to_string();
-        let test_file = uuid.as_str();
-        let created_file = match fs.create(test_file) {
-            Ok(f) => f,
-            Err(_) => panic!("Couldn't create a file"),
-        };
-        assert!(created_file.close().is_ok());
-        assert!(fs.exist(test_file));
+            let parquet_files = fs
+                .list_status_filtered(&test_dir, |status| {
+                    status.is_file() && status.name().ends_with(".parquet")
+                })
+                .ok()
+                .unwrap();
+            let mut names: Vec<String> = parquet_files
+                .iter()
+                .map(|status| path::file_name(status.name()).unwrap())
+                .collect();
+            names.sort();
+            assert_eq!(vec!["a.parquet", "c.parquet"], names);
+            // Cloned out of the scratch status, rather than overwritten by the next entries
+            let owner = fs
+                .get_file_status(&test_dir)
+                .ok()
+                .unwrap()
+                .owner()
+                .to_owned();
+            assert!(parquet_files
+                .iter()
+                .all(|status| status.len() == 6 && status.owner() == owner));
+
+            assert!(fs
+                .list_status_filtered(&test_dir, |_| false)
+                .ok()
+                .unwrap()
+                .is_empty());
+            assert_eq!(
+                5,
+                fs.list_status_filtered(&test_dir, |_| true)
+                    .ok()
+                    .unwrap()
+                    .len()
+            );
+
+            // Clean up
+            assert!(fs.delete(&test_dir, true).ok().unwrap());
+        }
+    }
 
-        assert!(fs.delete(test_file, false).is_ok());
-        assert!(!fs.exist(test_file));
+    #[test]
+    fn test_qualify() {
+        let dfs = get_dfs();
+        {
+            // Not to affect the others sharing the cached connection
+            let fs = HdfsFsBuilder::new()
+                .set_name_node(&dfs.namenode_addr())
+                .connect()
+                .ok()
+                .unwrap();
+            let working_dir = fs.working_directory().ok().unwrap();
+            let authority = path::split_authority(&working_dir).0.to_owned();
+            assert!(authority.starts_with("hdfs://"));
+
+            assert_eq!(
+                format!("{}/a/b", authority),
+                fs.qualify("/a//b/").ok().unwrap()
+            );
+            assert_eq!(
+                "hdfs://other:9000/a",
+                fs.qualify("hdfs://other:9000/a").ok().unwrap()
+            );
+            assert!(matches!(fs.qualify(""), Err(HdfsErr::InvalidPath(_))));
+
+            // Resolved against the working directory
+            let test_dir = format!("/{}", Uuid::new_v4());
+            assert!(fs.mkdir(&test_dir).is_ok());
+            assert!(fs.set_working_directory(&test_dir).ok().unwrap());
+            assert_eq!(
+                format!("{}{}/b", authority, test_dir),
+                fs.qualify("b").ok().unwrap()
+            );
+
+            // Clean up
+            assert!(fs.delete(&test_dir, false).ok().unwrap());
+        }
     }
 
+    #[cfg(feature = "compression")]
     #[test]
-    fn test_hdfs() {
+    fn test_open_decompressed() {
         let dfs = get_dfs();
         {
-            let minidfs_addr = dfs.namenode_addr();
             let fs = dfs.get_hdfs().ok().unwrap();
+            let test_dir = format!("/{}", Uuid::new_v4());
+            let data: Vec<u8> = (0..100 * 1024).map(|i| (i % 17) as u8).collect();
+            let read_decompressed = |path: &str| -> Vec<u8> {
+                let mut read = Vec::new();
+                let mut reader = fs.open_decompressed(path).ok().unwrap();
+                reader.read_to_end(&mut read).unwrap();
+                read
+            };
 
-            // Test hadoop file
-            {
-                // create a file, check existence, and close
-                let uuid = Uuid::new_v4().to_string();
-                let test_file = uuid.as_str();
-                let created_file = match fs.create(test_file) {
-                    Ok(f) => f,
-                    Err(_) => panic!("Couldn't create a file"),
-                };
-                assert!(created_file.close().is_ok());
-                assert!(fs.exist(test_file));
-
-                // open a file and close
-                let opened_file = fs.open(test_file).ok().unwrap();
-                assert!(opened_file.close().is_ok());
+            let mut gz =
+                flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+            gz.write_all(&data).unwrap();
+            let gz = gz.finish().unwrap();
+            assert_ne!(data, gz);
+            let gz_file = format!("{}/data.gz", test_dir);
+            assert!(fs.write_all(&gz_file, &gz, false).is_ok());
+            assert_eq!(data, read_decompressed(&gz_file));
+
+            // Concatenated members
+            let concat_file = format!("{}/concat.gz", test_dir);
+            assert!(fs
+                .write_all(&concat_file, &[gz.clone(), gz].concat(), false)
+                .is_ok());
+            assert_eq!(
+                [data.clone(), data.clone()].concat(),
+                read_decompressed(&concat_file)
+            );
+
+            let zst_file = format!("{}/data.zst", test_dir);
+            let zst = zstd::encode_all(&data[..], 0).unwrap();
+            assert!(fs.write_all(&zst_file, &zst, false).is_ok());
+            assert_eq!(data, read_decompressed(&zst_file));
+
+            let mut bz2 =
+                bzip2::write::BzEncoder::new(Vec::new(), bzip2::Compression::default());
+            bz2.write_all(&data).unwrap();
+            let bz2_file = format!("{}/data.bz2", test_dir);
+            assert!(fs
+                .write_all(&bz2_file, &bz2.finish().unwrap(), false)
+                .is_ok());
+            assert_eq!(data, read_decompressed(&bz2_file));
+
+            // Unrecognized extensions
+            let plain_file = format!("{}/data.txt", test_dir);
+            assert!(fs.write_all(&plain_file, &data, false).is_ok());
+            assert_eq!(data, read_decompressed(&plain_file));
+
+            // Corrupted file
+            let corrupted_file = format!("{}/corrupted.gz", test_dir);
+            assert!(fs.write_all(&corrupted_file, &data, false).is_ok());
+            let mut reader = fs.open_decompressed(&corrupted_file).ok().unwrap();
+            assert!(reader.read_to_end(&mut Vec::new()).is_err());
+
+            // Clean up
+            assert!(fs.delete(&test_dir, true).ok().unwrap());
+        }
+    }
 
-                // Clean up
-                assert!(fs.delete(test_file, false).is_ok());
-            }
+    #[test]
+    fn test_rename_overwrite() {
+        let dfs = get_dfs();
+        {
+            let fs = dfs.get_hdfs().ok().unwrap();
+            let src = format!("/{}", Uuid::new_v4());
+            let dst = format!("/{}", Uuid::new_v4());
+            assert!(fs.write_all(&src, b"new", false).is_ok());
+            assert!(fs.write_all(&dst, b"old", false).is_ok());
 
-            // Test directory
-            {
-                let uuid = Uuid::new_v4().to_string();
-                let test_dir = format!("/{}", uuid);
+            assert!(fs.rename_overwrite(&src, &dst).ok().unwrap());
+            assert!(!fs.exist(&src));
+            assert_eq!(b"new".to_vec(), fs.read_to_vec(&dst).ok().unwrap());
 
-                match fs.mkdir(&test_dir) {
-                    Ok(_) => println!("{} created", test_dir),
-                    Err(_) => panic!("Couldn't create {} directory", test_dir),
-                };
+            // Missing source
+            assert!(fs.rename_overwrite(&src, &dst).is_err());
 
-                let file_info = fs.get_file_status(&test_dir).ok().unwrap();
+            // Clean up
+            assert!(fs.delete(&dst, false).ok().unwrap());
+        }
+    }
 
-                let expected_path = format!("{}{}", minidfs_addr, test_dir);
-                assert_eq!(&expected_path, file_info.name());
-                assert!(!file_info.is_file());
-                assert!(file_info.is_directory());
+    #[test]
+    fn test_overwrite_atomic() {
+        let dfs = get_dfs();
+        {
+            let fs = dfs.get_hdfs().ok().unwrap().as_ref().clone();
+            let test_file = format!("/{}", Uuid::new_v4());
+            // The version i is i + 1 bytes of i
+            let version = |i: u8| vec![i; i as usize + 1];
+            assert!(fs.overwrite_atomic(&test_file, &version(0)).is_ok());
+
+            let writer = {
+                let fs = fs.clone();
+                let test_file = test_file.clone();
+                thread::spawn(move || {
+                    for i in 1..50 {
+                        assert!(fs.overwrite_atomic(&test_file, &version(i)).is_ok());
+                    }
+                })
+            };
+            let mut observed = 0;
+            while !writer.is_finished() {
+                // A reader of a replaced file may fail, but never see a partial one
+                if let Ok(data) = fs.read_to_vec(&test_file) {
+                    let i = data[0];
+                    assert_eq!(version(i), data);
+                    assert!(i >= observed);
+                    observed = i;
+                }
+            }
+            writer.join().unwrap();
+            assert_eq!(version(49), fs.read_to_vec(&test_file).ok().unwrap());
+
+            // No temporary file is left
+            let parent = path::parent(&test_file).unwrap();
+            let name = path::file_name(&test_file).unwrap();
+            assert!(fs
+                .list_status(&parent)
+                .ok()
+                .unwrap()
+                .iter()
+                .all(|status| !status.name().contains(&format!("{}.", name))));
+            assert!(matches!(
+                fs.overwrite_atomic("/", b"dir"),
+                Err(HdfsErr::IsDirectory(_))
+            ));
+
+            // Clean up
+            assert!(fs.delete(&test_file, false).ok().unwrap());
+        }
+    }
 
-                let sub_dir_num = 3;
-                let mut expected_list = Vec::new();
-                for x in 0..sub_dir_num {
-                    let filename = format!("{}/{}", test_dir, x);
-                    expected_list.push(format!("{}{}/{}", minidfs_addr, test_dir, x));
+    #[test]
+    fn test_block_readers() {
+        let dfs = get_dfs();
+        {
+            let fs = dfs.get_hdfs().ok().unwrap().as_ref().clone();
+            let block_size: i32 = 1024 * 1024;
+            let data: Vec<u8> = (0..(3 * block_size + 100))
+                .map(|i| (i % 227) as u8)
+                .collect();
+            let test_file = format!("/{}", Uuid::new_v4());
+            let mut file = fs
+                .create_with_params(&test_file, false, 0, 1, block_size)
+                .ok()
+                .unwrap();
+            assert!(file.write_all(&data).is_ok());
+            assert!(file.close().is_ok());
+
+            let readers = fs.block_readers(&test_file).ok().unwrap();
+            assert_eq!(4, readers.len());
+            let handles: Vec<_> = readers
+                .into_iter()
+                .map(|(location, mut reader)| {
+                    thread::spawn(move || {
+                        let mut read = Vec::new();
+                        reader.read_to_end(&mut read).unwrap();
+                        assert_eq!(location.length as usize, read.len());
+                        (location.offset, read)
+                    })
+                })
+                .collect();
+            let mut blocks: Vec<(i64, Vec<u8>)> =
+                handles.into_iter().map(|h| h.join().unwrap()).collect();
+            blocks.sort_by_key(|(offset, _)| *offset);
+            let read: Vec<u8> = blocks.into_iter().flat_map(|(_, read)| read).collect();
+            assert_eq!(data, read);
+
+            // Clean up
+            assert!(fs.delete(&test_file, false).ok().unwrap());
+        }
+    }
 
-                    match fs.mkdir(&filename) {
-                        Ok(_) => println!("{} created", filename),
-                        Err(_) => panic!("Couldn't create {} directory", filename),
-                    };
-                }
+    #[test]
+    fn test_list_modified_since() {
+        let dfs = get_dfs();
+        {
+            let fs = dfs.get_hdfs().ok().unwrap();
+            let test_dir = format!("/{}", Uuid::new_v4());
+            let sub_dir = format!("{}/sub", test_dir);
+            assert!(fs.mkdir(&sub_dir).is_ok());
+
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_secs() as i64;
+            let files: Vec<String> = ["a", "b", "sub/c", "sub/d"]
+                .iter()
+                .map(|name| format!("{}/{}", test_dir, name))
+                .collect();
+            for file in files.iter() {
+                assert!(fs.write_all(file, b"data", false).is_ok());
+                assert!(fs.set_times(file, now - 100, -1).ok().unwrap());
+            }
+            let since =
+                UNIX_EPOCH + Duration::from_millis(((now - 50) * 1000 + 500) as u64);
 
-                let mut list = fs.list_status(&test_dir).ok().unwrap();
-                assert_eq!(sub_dir_num, list.len());
+            // Modify some of them after the timestamp
+            assert!(fs.set_times(&files[1], now, -1).ok().unwrap());
+            assert!(fs.set_times(&files[3], now, -1).ok().unwrap());
+            // Modified within the same second as the timestamp
+            assert!(fs.set_times(&files[2], now - 50, -1).ok().unwrap());
 
-                list.sort_by(|a, b| Ord::cmp(a.name(), b.name()));
-                for (expected, name) in expected_list
+            let names = |list: Vec<FileStatus>| -> Vec<String> {
+                let mut names: Vec<String> = list
                     .iter()
-                    .zip(list.iter().map(|status| status.name()))
-                {
-                    assert_eq!(expected, name);
-                }
-
-                // Clean up
-                assert!(fs.delete(&test_dir, true).is_ok());
-            }
+                    .map(|status| path::file_name(status.name()).unwrap())
+                    .collect();
+                names.sort();
+                names
+            };
+            let modified = fs
+                .list_modified_since(&test_dir, since, false)
+                .ok()
+                .unwrap();
+            assert_eq!(vec!["b".to_owned()], names(modified));
+            let modified = fs.list_modified_since(&test_dir, since, true).ok().unwrap();
+            assert_eq!(vec!["b", "c", "d"], names(modified));
+            let modified = fs
+                .list_modified_since(
+                    &test_dir,
+                    SystemTime::now() + Duration::from_secs(100),
+                    true,
+                )
+                .ok()
+                .unwrap();
+            assert!(modified.is_empty());
+
+            assert!(matches!(
+                fs.list_modified_since(&format!("/{}", Uuid::new_v4()), since, true),
+                Err(HdfsErr::FileNotFound(_))
+            ));
+
+            // Clean up
+            assert!(fs.delete(&test_dir, true).ok().unwrap());
         }
     }
 
     #[test]
-    fn test_list_status_with_empty_dir() {
+    fn test_open_slice() {
         let dfs = get_dfs();
         {
             let fs = dfs.get_hdfs().ok().unwrap();
+            let data: Vec<u8> = (0..100 * 1024).map(|i| (i % 241) as u8).collect();
+            let test_file = format!("/{}", Uuid::new_v4());
+            assert!(fs.write_all(&test_file, &data, false).is_ok());
+
+            let mut reader = fs.open_slice(&test_file, 4096, 8192).ok().unwrap();
+            assert_eq!(8192, reader.len());
+            let mut read = Vec::new();
+            assert_eq!(8192, reader.read_to_end(&mut read).unwrap());
+            assert_eq!(&data[4096..4096 + 8192], &read[..]);
+            assert_eq!(0, reader.read(&mut [0u8; 16]).unwrap());
+
+            let mut buf = [0u8; 4];
+            assert_eq!(8188, reader.seek(SeekFrom::End(-4)).unwrap());
+            reader.read_exact(&mut buf).unwrap();
+            assert_eq!(&data[4096 + 8188..4096 + 8192], &buf[..]);
+            assert_eq!(0, reader.seek(SeekFrom::Start(0)).unwrap());
+            reader.read_exact(&mut buf).unwrap();
+            assert_eq!(&data[4096..4100], &buf[..]);
+
+            // Past the end of the file
+            let reader = fs.open_slice(&test_file, 99 * 1024, 4096).ok().unwrap();
+            assert_eq!(1024, reader.len());
+            let reader = fs.open_slice(&test_file, 200 * 1024, 4096).ok().unwrap();
+            assert_eq!(0, reader.len());
+
+            assert!(matches!(
+                fs.open_slice(&test_file, -1, 10),
+                Err(HdfsErr::InvalidArgument(_))
+            ));
+            assert!(matches!(
+                fs.open_slice(&test_file, 0, -1),
+                Err(HdfsErr::InvalidArgument(_))
+            ));
+            assert!(matches!(
+                fs.open_slice("/", 0, 10),
+                Err(HdfsErr::IsDirectory(_))
+            ));
+
+            // Clean up
+            assert!(fs.delete(&test_file, false).ok().unwrap());
+        }
+    }
 
-            // Test list status with empty directory
+    #[test]
+    fn test_create_configured() {
+        let dfs = get_dfs();
+        {
+            let fs = dfs.get_hdfs().ok().unwrap();
+            let test_file = format!("/{}", Uuid::new_v4());
+            let block_size = 2 * 1024 * 1024;
+
+            let mut file = fs
+                .create_configured(&test_file, 1, block_size)
+                .ok()
+                .unwrap();
+            assert!(file.write_all(b"configured").is_ok());
+            assert!(file.close().is_ok());
+            let status = fs.get_file_status(&test_file).ok().unwrap();
+            assert_eq!(block_size as usize, status.block_size());
+            assert_eq!(1, status.replica_count());
+            assert_eq!(10, status.len());
+
+            // Existing file
+            assert!(matches!(
+                fs.create_configured(&test_file, 1, block_size),
+                Err(HdfsErr::FileAlreadyExists(_))
+            ));
+
+            let new_file = format!("/{}", Uuid::new_v4());
+            for (replication, block_size) in
+                [(0, block_size), (1, 0), (1, -512), (1, 1000)]
             {
-                let uuid = Uuid::new_v4().to_string();
-                let test_dir = format!("/{}", uuid);
-                let empty_dir = format!("/{}", "_empty");
+                assert!(matches!(
+                    fs.create_configured(&new_file, replication, block_size),
+                    Err(HdfsErr::InvalidArgument(_))
+                ));
+            }
+            assert!(!fs.exist(&new_file));
 
-                match fs.mkdir(&test_dir) {
-                    Ok(_) => println!("{} created", test_dir),
-                    Err(_) => panic!("Couldn't create {} directory", test_dir),
-                };
+            // Clean up
+            assert!(fs.delete(&test_file, false).ok().unwrap());
+        }
+    }
 
-                match fs.mkdir(&empty_dir) {
-                    Ok(_) => println!("{} created", test_dir),
-                    Err(_) => panic!("Couldn't create {} directory", test_dir),
-                };
+    #[test]
+    fn test_open_with_len() {
+        let dfs = get_dfs();
+        {
+            let fs = dfs.get_hdfs().ok().unwrap();
+            let data: Vec<u8> = (0..70 * 1024 + 3).map(|i| (i % 239) as u8).collect();
+            let test_file = format!("/{}", Uuid::new_v4());
+            assert!(fs.write_all(&test_file, &data, false).is_ok());
+
+            let (mut file, len) = fs.open_with_len(&test_file).ok().unwrap();
+            assert_eq!(data.len() as i64, len);
+            let mut read = Vec::with_capacity(len as usize);
+            assert_eq!(data.len(), file.read_to_end(&mut read).unwrap());
+            assert_eq!(data, read);
+            assert!(file.close().is_ok());
+
+            assert!(matches!(
+                fs.open_with_len("/"),
+                Err(HdfsErr::IsDirectory(_))
+            ));
+            assert!(matches!(
+                fs.open_with_len(&format!("/{}", Uuid::new_v4())),
+                Err(HdfsErr::FileNotFound(_))
+            ));
+
+            // Clean up
+            assert!(fs.delete(&test_file, false).ok().unwrap());
+        }
+    }
 
-                let test_file = format!("{}/{}", &test_dir, "test.txt");
-                match fs.create(&test_file) {
-                    Ok(_) => println!("{} created", test_file),
-                    Err(_) => panic!("Couldn't create {} ", test_file),
-                }
+    #[test]
+    fn test_resolve_links() {
+        let links: HashMap<&str, &str> = [
+            ("hdfs://nn/a", "hdfs://nn/b/c"),
+            ("hdfs://nn/b/c", "d"),
+            ("hdfs://nn/b/up", "../x"),
+            ("hdfs://nn/abs", "/b"),
+            ("hdfs://nn/other", "hdfs://other/data"),
+            ("hdfs://nn/loop1", "loop2"),
+            ("hdfs://nn/loop2", "/loop1"),
+        ]
+        .into_iter()
+        .collect();
+        let resolve = |path: &str| {
+            resolve_links(path, |link| {
+                Ok(links.get(link).map(|target| target.to_string()))
+            })
+        };
 
-                let file_info = fs.list_status(&test_dir).ok().unwrap();
-                assert_eq!(file_info.len(), 1);
+        assert_eq!("hdfs://nn/x/y", resolve("hdfs://nn/x/y").ok().unwrap());
+        assert_eq!("hdfs://nn/", resolve("hdfs://nn/").ok().unwrap());
+        // A chain of the links, and the components after them
+        assert_eq!("hdfs://nn/b/d", resolve("hdfs://nn/a").ok().unwrap());
+        assert_eq!("hdfs://nn/b/d/e", resolve("hdfs://nn/a/e").ok().unwrap());
+        assert_eq!(
+            "hdfs://nn/b/d/e",
+            resolve("hdfs://nn/abs/c/e").ok().unwrap()
+        );
+        assert_eq!("hdfs://nn/x", resolve("hdfs://nn/b/up").ok().unwrap());
+        assert_eq!(
+            "hdfs://other/data/f",
+            resolve("hdfs://nn/other/f").ok().unwrap()
+        );
+        assert!(matches!(
+            resolve("hdfs://nn/loop1/f"),
+            Err(HdfsErr::SymlinkLoop(_))
+        ));
+
+        // The errors of the lookup
+        let missing = resolve_links("hdfs://nn/a/b", |link| {
+            Err(HdfsErr::FileNotFound(link.to_owned()))
+        });
+        assert!(
+            matches!(missing, Err(HdfsErr::FileNotFound(path)) if path == "hdfs://nn/a")
+        );
+    }
 
-                let file_info = fs.list_status(&empty_dir).ok().unwrap();
-                assert_eq!(file_info.len(), 0);
+    #[test]
+    fn test_canonicalize() {
+        let dfs = get_dfs();
+        {
+            let fs = dfs.get_hdfs().ok().unwrap();
+            let test_dir = format!("/{}", Uuid::new_v4());
+            let test_file = format!("{}/f", test_dir);
+            assert!(fs.write_all(&test_file, b"real", false).is_ok());
+
+            // Without any link
+            assert_eq!(
+                fs.qualify(&test_file).ok().unwrap(),
+                fs.canonicalize(&format!("{}//f/", test_dir)).ok().unwrap()
+            );
+            assert_eq!(
+                fs.qualify("/").ok().unwrap(),
+                fs.canonicalize("/").ok().unwrap()
+            );
+            assert!(matches!(
+                fs.canonicalize(&format!("{}/missing/f", test_dir)),
+                Err(HdfsErr::FileNotFound(_))
+            ));
+
+            // Clean up
+            assert!(fs.delete(&test_dir, true).ok().unwrap());
+        }
+    }
 
-                // Clean up
-                assert!(fs.delete(&test_dir, true).is_ok());
-            }
+    #[cfg(feature = "handle_tracking")]
+    #[test]
+    fn test_open_handle_count() {
+        let dfs = get_dfs();
+        {
+            // A connection of its own, not to count the files of the other tests
+            let fs = HdfsFsBuilder::new()
+                .set_name_node(&dfs.namenode_addr())
+                .connect()
+                .ok()
+                .unwrap();
+            let test_file = format!("/{}", Uuid::new_v4());
+            assert!(fs.write_all(&test_file, b"tracked", false).is_ok());
+            assert_eq!(0, fs.connection_open_handle_count());
+
+            let closed = fs.open(&test_file).ok().unwrap();
+            let dropped = fs.clone().open(&test_file).ok().unwrap();
+            let written = fs.append(&test_file).ok().unwrap();
+            assert_eq!(3, fs.connection_open_handle_count());
+            assert_eq!(vec![test_file.clone(); 3], fs.connection_open_handles());
+            // The files of the other tests are counted by the process-wide ones as well
+            assert!(HdfsFs::open_handle_count() >= 3);
+            let count_global = || {
+                HdfsFs::open_handles()
+                    .iter()
+                    .filter(|path| **path == test_file)
+                    .count()
+            };
+            assert_eq!(3, count_global());
+
+            // A failed open is not counted
+            assert!(fs.open(&format!("/{}", Uuid::new_v4())).is_err());
+            assert_eq!(3, fs.connection_open_handle_count());
+
+            assert!(closed.close().is_ok());
+            assert_eq!(2, fs.connection_open_handle_count());
+            drop(dropped);
+            assert_eq!(1, fs.connection_open_handle_count());
+            assert!(written.close().is_ok());
+            assert_eq!(0, fs.connection_open_handle_count());
+            assert!(fs.connection_open_handles().is_empty());
+            assert_eq!(0, count_global());
+
+            // Clean up
+            assert!(fs.delete(&test_file, false).ok().unwrap());
         }
     }
 }
@@ -61,6 +61,11 @@
 //! export CLASSPATH=$CLASSPATH:`hadoop classpath --glob`
 //! ```
 //!
+//! To connect to a high-availability cluster by its logical nameservice, e.g.
+//! ``hdfs://mycluster``, the ``core-site.xml`` and ``hdfs-site.xml`` defining the
+//! nameservice must be on the ``CLASSPATH`` as well, e.g. by adding ``$HADOOP_CONF_DIR``,
+//! since the nameservice is resolved to its namenodes by the Hadoop client.
+//!
 //! ## Testing
 //! The test also requires the ``CLASSPATH``. In case that the java class of ``org.junit.Assert``
 //! can't be found. Refine the ``$CLASSPATH`` as follows:
@@ -99,14 +104,49 @@
 #![allow(non_camel_case_types)]
 #![allow(non_snake_case)]
 
+/// Call a native function of libhdfs, which is logged with the given arguments at debug
+/// if the ``ffi_log`` feature is enabled, e.g. ``native_call!(hdfsDelete(fs, p, 1), path)``.
+///
+/// The arguments to log are the Rust values, e.g. the path rather than its C pointer.
+/// Nothing is evaluated for the log if the feature is disabled.
+macro_rules! native_call {
+    ($func:ident($($arg:expr),* $(,)?) $(, $log_arg:expr)* $(,)?) => {{
+        #[cfg(feature = "ffi_log")]
+        {
+            let log_args: &[String] = &[$(format!("{:?}", $log_arg)),*];
+            log::debug!("{}({})", stringify!($func), log_args.join(", "));
+        }
+        $func($($arg),*)
+    }};
+}
+
+/// Raw FFI bindings of libhdfs, for the native functions which are not wrapped yet.
+///
+/// They take the raw handles got by ``HdfsFs::raw`` and ``HdfsFile::raw``.
 #[allow(deref_nullptr)]
-mod native;
+pub mod native;
 
+#[cfg(feature = "async")]
+pub mod async_hdfs;
 pub mod err;
+/// An abstraction of the filesystem operations, implemented by ``HdfsFs`` and ``MockFs``
+pub mod filesystem;
+mod glob;
 /// Rust APIs wrapping libhdfs API, providing better semantic and abstraction
 pub mod hdfs;
 #[cfg(feature = "test_util")]
 /// Mainly for unit test
 pub mod minidfs;
+/// An in-memory filesystem for the unit tests without a cluster
+#[cfg(feature = "test_util")]
+pub mod mock;
+pub mod path;
+pub mod reader;
+pub mod retry;
 #[cfg(feature = "test_util")]
 pub mod util;
+/// Dispatching to multiple filesystems by the prefixes of the paths, which copies
+/// across them by ``HdfsUtil``
+#[cfg(feature = "test_util")]
+pub mod vfs;
+pub mod writer;
@@ -0,0 +1,436 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! An in-memory filesystem for the unit tests without a cluster
+
+use std::collections::BTreeMap;
+use std::io;
+use std::io::{Cursor, Read, Write};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::SystemTime;
+
+use crate::err::HdfsErr;
+use crate::filesystem::FileSystem;
+use crate::hdfs::{FileKind, FileStatus};
+use crate::path;
+
+/// The entries of MockFs by their normalized absolute paths
+type Entries = BTreeMap<String, MockEntry>;
+
+#[derive(Debug)]
+struct MockEntry {
+    kind: FileKind,
+    data: Vec<u8>,
+    last_modified: SystemTime,
+}
+
+impl MockEntry {
+    fn new(kind: FileKind) -> MockEntry {
+        MockEntry {
+            kind,
+            data: Vec::new(),
+            last_modified: SystemTime::now(),
+        }
+    }
+
+    fn status(&self, name: &str) -> FileStatus {
+        FileStatus::new(
+            name.to_owned(),
+            self.kind,
+            self.data.len(),
+            self.last_modified,
+        )
+    }
+}
+
+/// An in-memory filesystem following the semantics of HDFS, which is cheap to clone
+/// as the clones share the files.
+///
+/// A path is taken as an absolute one without the scheme and the authority, e.g. both
+/// ``hdfs://host/a`` and ``a`` are ``/a``, which is the name of its file status. The errors
+/// are the ones of HdfsFs, except that the failures reported by HDFS without any reason
+/// have the dedicated ones, e.g. a rename of a missing path fails with ``FileNotFound``
+/// rather than ``Io``.
+#[derive(Debug, Clone)]
+pub struct MockFs {
+    entries: Arc<Mutex<Entries>>,
+}
+
+impl Default for MockFs {
+    fn default() -> Self {
+        let mut entries = Entries::new();
+        entries.insert("/".to_owned(), MockEntry::new(FileKind::Directory));
+        MockFs {
+            entries: Arc::new(Mutex::new(entries)),
+        }
+    }
+}
+
+impl MockFs {
+    /// Create a filesystem with the root directory only
+    pub fn new() -> MockFs {
+        MockFs::default()
+    }
+
+    fn entries(&self) -> MutexGuard<'_, Entries> {
+        self.entries.lock().unwrap()
+    }
+}
+
+impl FileSystem for MockFs {
+    /// The data is read from a snapshot taken when the file is opened
+    fn open(&self, path: &str) -> Result<Box<dyn Read + Send>, HdfsErr> {
+        match self.entries().get(&key(path)) {
+            Some(entry) if entry.kind == FileKind::File => {
+                Ok(Box::new(Cursor::new(entry.data.clone())))
+            }
+            // HDFS fails to open a directory with FileNotFoundException as well
+            _ => Err(HdfsErr::FileNotFound(path.to_owned())),
+        }
+    }
+
+    /// Each write is appended to the file right away
+    fn create(&self, path: &str) -> Result<Box<dyn Write + Send>, HdfsErr> {
+        let key = key(path);
+        let mut entries = self.entries();
+        if entries.contains_key(&key) {
+            return Err(HdfsErr::FileAlreadyExists(path.to_owned()));
+        }
+        if let Some(parent) = path::parent(&key) {
+            make_dirs(&mut entries, &parent)?;
+        }
+
+        entries.insert(key.clone(), MockEntry::new(FileKind::File));
+        Ok(Box::new(MockWriter {
+            entries: self.entries.clone(),
+            key,
+        }))
+    }
+
+    fn exist(&self, path: &str) -> bool {
+        self.entries().contains_key(&key(path))
+    }
+
+    fn delete(&self, path: &str, recursive: bool) -> Result<bool, HdfsErr> {
+        let key = key(path);
+        if key == "/" {
+            return Err(HdfsErr::InvalidArgument(format!(
+                "delete the root of {}",
+                path
+            )));
+        }
+
+        let mut entries = self.entries();
+        if !entries.contains_key(&key) {
+            return Ok(false);
+        }
+        let descendants = descendants(&entries, &key);
+        if !recursive && !descendants.is_empty() {
+            return Err(HdfsErr::DirectoryNotEmpty(path.to_owned()));
+        }
+
+        for descendant in descendants {
+            entries.remove(&descendant);
+        }
+        entries.remove(&key);
+        Ok(true)
+    }
+
+    fn list_status(&self, path: &str) -> Result<Vec<FileStatus>, HdfsErr> {
+        let key = key(path);
+        let entries = self.entries();
+        match entries.get(&key) {
+            None => Err(HdfsErr::FileNotFound(path.to_owned())),
+            Some(entry) if entry.kind == FileKind::File => Ok(vec![entry.status(&key)]),
+            Some(_) => Ok(descendants(&entries, &key)
+                .into_iter()
+                .filter(|descendant| path::parent(descendant).as_ref() == Some(&key))
+                .map(|child| entries[&child].status(&child))
+                .collect()),
+        }
+    }
+
+    fn get_file_status(&self, path: &str) -> Result<FileStatus, HdfsErr> {
+        let key = key(path);
+        match self.entries().get(&key) {
+            Some(entry) => Ok(entry.status(&key)),
+            None => Err(HdfsErr::FileNotFound(path.to_owned())),
+        }
+    }
+
+    fn rename(&self, old_path: &str, new_path: &str) -> Result<bool, HdfsErr> {
+        let (old_key, new_key) = (key(old_path), key(new_path));
+        if old_key == "/" {
+            return Err(HdfsErr::InvalidArgument(format!(
+                "rename the root of {}",
+                old_path
+            )));
+        }
+
+        let mut entries = self.entries();
+        if !entries.contains_key(&old_key) {
+            return Err(HdfsErr::FileNotFound(old_path.to_owned()));
+        }
+        let dst = match entries.get(&new_key) {
+            Some(entry) if entry.kind == FileKind::Directory => {
+                path::join(&new_key, &path::file_name(&old_key).unwrap())
+            }
+            _ => new_key,
+        };
+        if dst == old_key {
+            return Ok(true);
+        }
+        if entries.contains_key(&dst) {
+            return Err(HdfsErr::FileAlreadyExists(dst));
+        }
+        if dst.starts_with(&format!("{}/", old_key)) {
+            return Err(HdfsErr::InvalidArgument(format!(
+                "rename {} into itself as {}",
+                old_path, dst
+            )));
+        }
+        match path::parent(&dst).and_then(|parent| entries.get(&parent)) {
+            Some(parent) if parent.kind == FileKind::Directory => {}
+            _ => return Err(HdfsErr::FileNotFound(path::parent(&dst).unwrap())),
+        }
+
+        let mut moved = descendants(&entries, &old_key);
+        moved.push(old_key.clone());
+        for key in moved {
+            let entry = entries.remove(&key).unwrap();
+            entries.insert(format!("{}{}", dst, &key[old_key.len()..]), entry);
+        }
+        Ok(true)
+    }
+
+    fn mkdir(&self, path: &str) -> Result<bool, HdfsErr> {
+        make_dirs(&mut self.entries(), &key(path))?;
+        Ok(true)
+    }
+}
+
+/// A writer appending to a file of MockFs
+struct MockWriter {
+    entries: Arc<Mutex<Entries>>,
+    key: String,
+}
+
+impl Write for MockWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self.entries.lock().unwrap().get_mut(&self.key) {
+            Some(entry) if entry.kind == FileKind::File => {
+                entry.data.extend_from_slice(buf);
+                entry.last_modified = SystemTime::now();
+                Ok(buf.len())
+            }
+            _ => Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("{} is deleted", self.key),
+            )),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// The key of a path, i.e. the normalized absolute path without the scheme and the authority
+fn key(path: &str) -> String {
+    let (_, path) = path::split_authority(path);
+    path::join("/", path)
+}
+
+/// Get the keys of all the entries under the directory
+fn descendants(entries: &Entries, dir: &str) -> Vec<String> {
+    let prefix = if dir == "/" {
+        dir.to_owned()
+    } else {
+        format!("{}/", dir)
+    };
+    entries
+        .range(prefix.clone()..)
+        .map(|(key, _)| key)
+        .take_while(|key| key.starts_with(&prefix))
+        .filter(|key| key.len() > prefix.len())
+        .cloned()
+        .collect()
+}
+
+/// Create a directory and its missing parents, which fails with ``FileAlreadyExists``
+/// if any of them is a file
+fn make_dirs(entries: &mut Entries, dir: &str) -> Result<(), HdfsErr> {
+    let mut missing = Vec::new();
+    let mut next = Some(dir.to_owned());
+    while let Some(dir) = next {
+        match entries.get(&dir) {
+            Some(entry) if entry.kind == FileKind::File => {
+                return Err(HdfsErr::FileAlreadyExists(dir));
+            }
+            Some(_) => break,
+            None => {
+                next = path::parent(&dir);
+                missing.push(dir);
+            }
+        }
+    }
+
+    for dir in missing {
+        entries.insert(dir, MockEntry::new(FileKind::Directory));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::{Read, Write};
+
+    use uuid::Uuid;
+
+    use super::MockFs;
+    use crate::err::HdfsErr;
+    use crate::filesystem::FileSystem;
+    use crate::minidfs::get_dfs;
+    use crate::path;
+
+    /// Move the ``.done`` files of a directory into the archive, and return their total
+    /// length, as an example of the logic written against FileSystem
+    fn archive_done(
+        fs: &dyn FileSystem,
+        dir: &str,
+        archive: &str,
+    ) -> Result<usize, HdfsErr> {
+        fs.mkdir(archive)?;
+        let mut archived = 0;
+        for status in fs.list_status(dir)? {
+            if status.is_file() && status.name().ends_with(".done") {
+                archived += status.len();
+                fs.rename(status.name(), archive)?;
+            }
+        }
+        Ok(archived)
+    }
+
+    fn check_archive_done(fs: &dyn FileSystem, root: &str) {
+        let dir = format!("{}/in", root);
+        let archive = format!("{}/archive", root);
+        for (name, data) in [("a.done", "aaa"), ("b.tmp", "bb"), ("c.done", "c")] {
+            let mut writer = fs.create(&format!("{}/{}", dir, name)).ok().unwrap();
+            writer.write_all(data.as_bytes()).unwrap();
+            writer.flush().unwrap();
+        }
+        assert!(fs.mkdir(&format!("{}/sub.done", dir)).ok().unwrap());
+
+        assert_eq!(4, archive_done(fs, &dir, &archive).ok().unwrap());
+        let names = |dir: &str| -> Vec<String> {
+            let mut names: Vec<String> = fs
+                .list_status(dir)
+                .ok()
+                .unwrap()
+                .iter()
+                .map(|status| path::file_name(status.name()).unwrap())
+                .collect();
+            names.sort();
+            names
+        };
+        assert_eq!(vec!["a.done", "c.done"], names(&archive));
+        assert_eq!(vec!["b.tmp", "sub.done"], names(&dir));
+
+        let mut data = String::new();
+        let mut reader = fs.open(&format!("{}/a.done", archive)).ok().unwrap();
+        reader.read_to_string(&mut data).unwrap();
+        assert_eq!("aaa", data);
+
+        assert!(fs.delete(root, true).ok().unwrap());
+        assert!(!fs.exist(root));
+    }
+
+    #[test]
+    fn test_mock_fs() {
+        let fs = MockFs::new();
+        let test_dir = format!("/{}", Uuid::new_v4());
+        let test_file = format!("{}/a/f", test_dir);
+
+        let mut writer = fs.create(&test_file).ok().unwrap();
+        writer.write_all(b"mock").unwrap();
+        assert!(fs
+            .get_file_status(&format!("{}/a", test_dir))
+            .ok()
+            .unwrap()
+            .is_directory());
+        let status = fs
+            .get_file_status(&format!("hdfs://host{}", test_file))
+            .ok()
+            .unwrap();
+        assert_eq!(test_file, status.name());
+        assert_eq!(4, status.len());
+        assert!(matches!(
+            fs.create(&test_file),
+            Err(HdfsErr::FileAlreadyExists(_))
+        ));
+        assert!(matches!(
+            fs.create(&format!("{}/g", test_file)),
+            Err(HdfsErr::FileAlreadyExists(_))
+        ));
+        assert!(matches!(fs.open(&test_dir), Err(HdfsErr::FileNotFound(_))));
+        assert_eq!(1, fs.list_status(&test_file).ok().unwrap().len());
+        assert_eq!(1, fs.list_status("/").ok().unwrap().len());
+
+        // Rename
+        let renamed = format!("{}/b", test_dir);
+        assert!(fs
+            .rename(&format!("{}/a", test_dir), &renamed)
+            .ok()
+            .unwrap());
+        assert!(fs.exist(&format!("{}/f", renamed)));
+        assert!(!fs.exist(&test_file));
+        assert!(matches!(
+            fs.rename(&test_file, &renamed),
+            Err(HdfsErr::FileNotFound(_))
+        ));
+        assert!(matches!(
+            fs.rename(&renamed, &format!("{}/c", renamed)),
+            Err(HdfsErr::InvalidArgument(_))
+        ));
+        assert!(matches!(
+            fs.rename(&renamed, &format!("{}/missing/c", test_dir)),
+            Err(HdfsErr::FileNotFound(_))
+        ));
+
+        // Delete
+        assert!(matches!(
+            fs.delete(&test_dir, false),
+            Err(HdfsErr::DirectoryNotEmpty(_))
+        ));
+        assert!(fs.delete(&test_dir, true).ok().unwrap());
+        assert!(!fs.delete(&test_dir, true).ok().unwrap());
+        assert!(writer.write_all(b"deleted").is_err());
+        assert!(fs.exist("/"));
+        assert!(fs.list_status("/").ok().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_file_system() {
+        check_archive_done(&MockFs::new(), &format!("/{}", Uuid::new_v4()));
+
+        let dfs = get_dfs();
+        {
+            let fs = dfs.get_hdfs().ok().unwrap();
+            check_archive_done(fs.as_ref(), &format!("/{}", Uuid::new_v4()));
+        }
+    }
+}
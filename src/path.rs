@@ -0,0 +1,227 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Manipulation of HDFS paths as strings, without accessing any filesystem
+//!
+//! A path is always ``/``-separated, and may be qualified by a scheme and an authority,
+//! e.g. ``hdfs://localhost:9000/data``, which is kept untouched. Repeated and trailing
+//! slashes are dropped, while ``.`` and ``..`` segments are kept as they are.
+
+/// Split a path into the scheme with the authority, e.g. ``hdfs://localhost:9000``,
+/// and the rest of the path. The former is empty for a path without a scheme.
+pub(crate) fn split_authority(path: &str) -> (&str, &str) {
+    match path.find("://") {
+        Some(idx) => match path[idx + 3..].find('/') {
+            Some(slash) => path.split_at(idx + 3 + slash),
+            None => (path, ""),
+        },
+        None => ("", path),
+    }
+}
+
+/// Normalize a path by dropping repeated and trailing slashes.
+///
+/// A scheme-qualified path is always absolute, e.g. ``hdfs://host`` is normalized
+/// to ``hdfs://host/``.
+pub fn normalize(path: &str) -> String {
+    let (prefix, path) = split_authority(path);
+    let mut normalized = prefix.to_owned();
+    if !prefix.is_empty() || path.starts_with('/') {
+        normalized.push('/');
+    }
+    let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+    normalized.push_str(&segments.join("/"));
+    normalized
+}
+
+/// Join a child path to the base path.
+///
+/// As the ``Path`` of Hadoop, a scheme-qualified child replaces the base, and an
+/// absolute child replaces the path of the base but keeps its scheme and authority.
+pub fn join(base: &str, child: &str) -> String {
+    if child.contains("://") || base.is_empty() {
+        return normalize(child);
+    }
+    if child.starts_with('/') {
+        let (prefix, _) = split_authority(base);
+        return normalize(&format!("{}{}", prefix, child));
+    }
+    normalize(&format!("{}/{}", base, child))
+}
+
+/// Get the parent of a path, or ``None`` for the root or a single relative segment.
+pub fn parent(path: &str) -> Option<String> {
+    let normalized = normalize(path);
+    let (prefix, path) = split_authority(&normalized);
+    let idx = path.rfind('/')?;
+    if idx + 1 == path.len() {
+        // root
+        return None;
+    }
+
+    let parent = if idx == 0 { "/" } else { &path[..idx] };
+    Some(format!("{}{}", prefix, parent))
+}
+
+/// Get the last segment of a path, or ``None`` for the root or an empty path.
+pub fn file_name(path: &str) -> Option<String> {
+    let normalized = normalize(path);
+    let (_, path) = split_authority(&normalized);
+    match path.rsplit('/').next() {
+        Some(name) if !name.is_empty() => Some(name.to_owned()),
+        _ => None,
+    }
+}
+
+/// Qualify a path by the fully qualified working directory, e.g. ``hdfs://host:9000/user/a``,
+/// as ``Path.makeQualified`` of Hadoop.
+///
+/// An absolute path takes the scheme and the authority of the working directory, and a
+/// relative one is resolved against it. A qualified path is only normalized, except for
+/// a path without the authority of the same scheme, e.g. ``hdfs:///a``, which takes the
+/// authority of the working directory too. A working directory in the form of
+/// ``file:/tmp``, which is the one of the local filesystem, is taken as ``file:///tmp``.
+pub(crate) fn qualify(working_dir: &str, path: &str) -> String {
+    let working_dir = with_empty_authority(working_dir);
+    let (prefix, rest) = split_authority(path);
+    let (dir_prefix, _) = split_authority(&working_dir);
+    if !prefix.is_empty() {
+        return if prefix.ends_with("://") && dir_prefix.starts_with(prefix) {
+            join(dir_prefix, rest)
+        } else {
+            normalize(path)
+        };
+    }
+
+    if rest.starts_with('/') {
+        join(dir_prefix, rest)
+    } else {
+        join(&working_dir, rest)
+    }
+}
+
+/// Add the empty authority to a path in the form of ``scheme:/path``
+fn with_empty_authority(path: &str) -> String {
+    match path.find(":/") {
+        Some(idx)
+            if !path[idx..].starts_with("://")
+                && !path[..idx].is_empty()
+                && path[..idx]
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "+.-".contains(c)) =>
+        {
+            format!("{}://{}", &path[..idx], &path[idx + 1..])
+        }
+        _ => path.to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{file_name, join, normalize, parent, qualify, split_authority};
+
+    #[test]
+    fn test_split_authority() {
+        assert_eq!(
+            ("hdfs://localhost:9000", "/data"),
+            split_authority("hdfs://localhost:9000/data")
+        );
+        assert_eq!(
+            ("hdfs://localhost", ""),
+            split_authority("hdfs://localhost")
+        );
+        assert_eq!(("file://", "/tmp"), split_authority("file:///tmp"));
+        assert_eq!(("", "/data"), split_authority("/data"));
+        assert_eq!(("", "data"), split_authority("data"));
+    }
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!("/a/b", normalize("/a//b/"));
+        assert_eq!("a/b", normalize("a/b/"));
+        assert_eq!("/", normalize("/"));
+        assert_eq!("/", normalize("//"));
+        assert_eq!("", normalize(""));
+        assert_eq!("hdfs://host:9000/", normalize("hdfs://host:9000"));
+        assert_eq!("hdfs://host:9000/a/b", normalize("hdfs://host:9000//a/b/"));
+        assert_eq!("file:///tmp", normalize("file:///tmp/"));
+    }
+
+    #[test]
+    fn test_join() {
+        assert_eq!("/a/b", join("/a", "b"));
+        assert_eq!("/b", join("/a/", "/b"));
+        assert_eq!("/a/b/c", join("/a/", "b//c/"));
+        assert_eq!("/b", join("/", "b"));
+        assert_eq!("a/b", join("a", "b"));
+        assert_eq!("b", join("", "b"));
+        assert_eq!("/a", join("/a", ""));
+        assert_eq!("hdfs://host/a/b", join("hdfs://host/a", "b"));
+        assert_eq!("hdfs://host/b", join("hdfs://host", "b"));
+        assert_eq!("hdfs://host/b", join("hdfs://host/a", "/b"));
+        assert_eq!("hdfs://other/b", join("hdfs://host/a", "hdfs://other/b"));
+    }
+
+    #[test]
+    fn test_parent() {
+        assert_eq!(Some("/a".to_owned()), parent("/a/b"));
+        assert_eq!(Some("/a".to_owned()), parent("/a/b/"));
+        assert_eq!(Some("/".to_owned()), parent("/a"));
+        assert_eq!(None, parent("/"));
+        assert_eq!(Some("a".to_owned()), parent("a/b"));
+        assert_eq!(None, parent("a"));
+        assert_eq!(None, parent(""));
+        assert_eq!(Some("hdfs://host/a".to_owned()), parent("hdfs://host/a/b"));
+        assert_eq!(Some("hdfs://host/".to_owned()), parent("hdfs://host/a"));
+        assert_eq!(None, parent("hdfs://host/"));
+        assert_eq!(None, parent("hdfs://host"));
+    }
+
+    #[test]
+    fn test_file_name() {
+        assert_eq!(Some("b".to_owned()), file_name("/a/b"));
+        assert_eq!(Some("b".to_owned()), file_name("/a/b/"));
+        assert_eq!(Some("a".to_owned()), file_name("a"));
+        assert_eq!(None, file_name("/"));
+        assert_eq!(None, file_name(""));
+        assert_eq!(Some("b".to_owned()), file_name("hdfs://host:9000/a/b"));
+        assert_eq!(None, file_name("hdfs://host:9000"));
+    }
+
+    #[test]
+    fn test_qualify() {
+        let working_dir = "hdfs://host:9000/user/a";
+        // Relative paths
+        assert_eq!("hdfs://host:9000/user/a/b", qualify(working_dir, "b"));
+        assert_eq!("hdfs://host:9000/user/a/b/c", qualify(working_dir, "b//c/"));
+        assert_eq!("hdfs://host:9000/user/a", qualify(working_dir, ""));
+        // Absolute paths
+        assert_eq!("hdfs://host:9000/b", qualify(working_dir, "/b"));
+        assert_eq!("hdfs://host:9000/", qualify(working_dir, "/"));
+        // Qualified paths
+        assert_eq!("hdfs://other/b", qualify(working_dir, "hdfs://other/b/"));
+        assert_eq!("hdfs://host:9000/b", qualify(working_dir, "hdfs:///b"));
+        assert_eq!("file:///tmp", qualify(working_dir, "file:///tmp"));
+
+        let working_dir = "file:/tmp/work";
+        assert_eq!("file:///tmp/work/b", qualify(working_dir, "b"));
+        assert_eq!("file:///b", qualify(working_dir, "/b"));
+        let working_dir = "file:///tmp/work";
+        assert_eq!("file:///tmp/work/b", qualify(working_dir, "b"));
+        assert_eq!("file:///b", qualify(working_dir, "/b"));
+    }
+}
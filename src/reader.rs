@@ -0,0 +1,644 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Buffered readers of HdfsFile
+
+use std::io;
+use std::io::{Read, Seek, SeekFrom};
+use std::mem;
+use std::panic;
+use std::sync::Arc;
+use std::thread;
+use std::thread::JoinHandle;
+
+use crate::err::HdfsErr;
+use crate::hdfs::HdfsFile;
+
+/// Default buffer size of HdfsBufReader
+pub const DEFAULT_BUF_SIZE: usize = 128 * 1024;
+
+/// A buffered reader of a file opened for read, serving small reads from its buffer.
+///
+/// Different from ``std::io::BufReader``, the buffer is never filled across a block
+/// boundary, so that each fill is served by a single datanode.
+///
+/// With the prefetch enabled, the next fill is read ahead into a second buffer by a
+/// thread while the current buffer is consumed, so that a sequential scan overlaps the
+/// reads with the processing. An error of the prefetch is returned by the read reaching
+/// it, and a prefetch wasted by a seek is discarded.
+pub struct HdfsBufReader {
+    /// The file shared with the prefetching thread
+    file: Arc<HdfsFile>,
+    buf: Vec<u8>,
+    /// The file offset of the buffered data
+    buf_start: u64,
+    /// The length of the buffered data
+    buf_len: usize,
+    /// The file offset of the next read
+    pos: u64,
+    block_size: u64,
+    prefetch: bool,
+    /// The prefetch of the next fill, which is joined when it's dropped
+    pending: Option<Prefetch>,
+}
+
+impl HdfsBufReader {
+    /// Create a reader with the default buffer size
+    pub fn new(file: HdfsFile) -> Result<HdfsBufReader, HdfsErr> {
+        HdfsBufReader::with_capacity(DEFAULT_BUF_SIZE, file)
+    }
+
+    /// Create a reader with the specified buffer size
+    pub fn with_capacity(
+        capacity: usize,
+        file: HdfsFile,
+    ) -> Result<HdfsBufReader, HdfsErr> {
+        HdfsBufReader::with_prefetch(capacity, file, false)
+    }
+
+    /// Create a reader with the specified buffer size, which prefetches the next fill of
+    /// the buffer by a thread if ``prefetch`` is true
+    pub fn with_prefetch(
+        capacity: usize,
+        file: HdfsFile,
+        prefetch: bool,
+    ) -> Result<HdfsBufReader, HdfsErr> {
+        if capacity == 0 {
+            return Err(HdfsErr::InvalidArgument(format!(
+                "zero buffer size to read {}",
+                file.path()
+            )));
+        }
+
+        let block_size = file.get_file_status()?.block_size() as u64;
+        let pos = file.pos()?;
+
+        Ok(HdfsBufReader {
+            file: Arc::new(file),
+            buf: vec![0; capacity],
+            buf_start: pos,
+            buf_len: 0,
+            pos,
+            block_size,
+            prefetch,
+            pending: None,
+        })
+    }
+
+    /// Get the underlying file
+    #[inline]
+    pub fn get_ref(&self) -> &HdfsFile {
+        &self.file
+    }
+
+    /// Consume the reader and return the underlying file,
+    /// whose cursor is not moved by the reader
+    pub fn into_inner(mut self) -> HdfsFile {
+        // The prefetching thread releases the file once it's joined
+        self.pending = None;
+        Arc::try_unwrap(self.file).unwrap()
+    }
+
+    /// Whether the next fill of the buffer is prefetched
+    #[inline]
+    pub fn is_prefetching(&self) -> bool {
+        self.prefetch
+    }
+
+    /// Get the buffer size
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// Get the buffered data from the current position
+    #[inline]
+    fn buffer(&self) -> &[u8] {
+        if self.pos >= self.buf_start && self.pos < self.buf_start + self.buf_len as u64 {
+            &self.buf[(self.pos - self.buf_start) as usize..self.buf_len]
+        } else {
+            &[]
+        }
+    }
+
+    /// The length to read from the position, not exceeding the next block boundary
+    fn read_len_within_block(&self, pos: u64, max_len: usize) -> usize {
+        if self.block_size == 0 {
+            return max_len;
+        }
+
+        let block_remaining = self.block_size - pos % self.block_size;
+        max_len.min(block_remaining.min(usize::MAX as u64) as usize)
+    }
+
+    fn fill_buf(&mut self) -> io::Result<()> {
+        let spare = match self.pending.take() {
+            Some(prefetch) if prefetch.start == self.pos => {
+                let (buf, read) = prefetch.join();
+                let read = read?;
+                self.buf_start = self.pos;
+                self.buf_len = read;
+                Some(mem::replace(&mut self.buf, buf))
+            }
+            // The prefetch is wasted by a seek, but its buffer is reused
+            stale => {
+                let spare = stale.map(|prefetch| prefetch.join().0);
+                let fill_len = self.read_len_within_block(self.pos, self.buf.len());
+                let read = pread(&self.file, self.pos, &mut self.buf[..fill_len])?;
+                self.buf_start = self.pos;
+                self.buf_len = read;
+                spare
+            }
+        };
+
+        // Nothing to prefetch at the end of the file
+        if self.prefetch && self.buf_len > 0 {
+            let start = self.buf_start + self.buf_len as u64;
+            let len = self.read_len_within_block(start, self.buf.len());
+            let buf = spare.unwrap_or_else(|| vec![0; self.buf.len()]);
+            self.pending = Some(Prefetch::spawn(self.file.clone(), start, len, buf));
+        }
+
+        Ok(())
+    }
+
+    /// Whether the next read from the position is served by the pending prefetch
+    fn is_prefetched(&self, pos: u64) -> bool {
+        self.pending
+            .as_ref()
+            .is_some_and(|prefetch| prefetch.start == pos)
+    }
+}
+
+/// A fill of the buffer read ahead by a thread
+struct Prefetch {
+    /// The file offset of the fill
+    start: u64,
+    handle: Option<JoinHandle<(Vec<u8>, io::Result<usize>)>>,
+}
+
+impl Prefetch {
+    fn spawn(file: Arc<HdfsFile>, start: u64, len: usize, mut buf: Vec<u8>) -> Prefetch {
+        let handle = thread::spawn(move || {
+            let read = pread(&file, start, &mut buf[..len]);
+            (buf, read)
+        });
+
+        Prefetch {
+            start,
+            handle: Some(handle),
+        }
+    }
+
+    /// Wait for the fill, and return the buffer with the length read into it
+    fn join(mut self) -> (Vec<u8>, io::Result<usize>) {
+        match self.handle.take().unwrap().join() {
+            Ok(filled) => filled,
+            Err(e) => panic::resume_unwind(e),
+        }
+    }
+}
+
+impl Drop for Prefetch {
+    fn drop(&mut self) {
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+impl Read for HdfsBufReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        // Bypass the buffer for a large read if nothing is buffered or prefetched
+        if self.buffer().is_empty()
+            && buf.len() >= self.buf.len()
+            && !self.is_prefetched(self.pos)
+        {
+            let read_len = self.read_len_within_block(self.pos, buf.len());
+            let read = pread(&self.file, self.pos, &mut buf[..read_len])?;
+            self.pos += read as u64;
+            return Ok(read);
+        }
+
+        if self.buffer().is_empty() {
+            self.fill_buf()?;
+        }
+
+        let buffered = self.buffer();
+        let read = buffered.len().min(buf.len());
+        buf[..read].copy_from_slice(&buffered[..read]);
+        self.pos += read as u64;
+
+        Ok(read)
+    }
+}
+
+impl Seek for HdfsBufReader {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let (base, offset) = match pos {
+            SeekFrom::Start(offset) => (0, offset as i128),
+            SeekFrom::End(offset) => {
+                let len = self
+                    .file
+                    .get_file_status()
+                    .map_err(|e| io::Error::other(format!("{:?}", e)))?
+                    .len();
+                (len as i128, offset as i128)
+            }
+            SeekFrom::Current(offset) => (self.pos as i128, offset as i128),
+        };
+
+        let target = base + offset;
+        if target < 0 || target > u64::MAX as i128 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("invalid seek to {} in {}", target, self.file.path()),
+            ));
+        }
+        self.pos = target as u64;
+
+        // Discard the buffer if the target is out of it
+        if self.buffer().is_empty() {
+            self.buf_start = self.pos;
+            self.buf_len = 0;
+        }
+
+        Ok(self.pos)
+    }
+}
+
+/// A reader of a range of a file opened for read, exposing the positions of ``0..len``
+/// mapped onto ``offset..offset + len`` of the file, which is read by ``pread``.
+///
+/// It reaches the end at ``len``, or at the end of the file if it's shorter. As ``pread``
+/// doesn't move the cursor of the file, the readers of the ranges of a file can share
+/// it, e.g. to read the blocks in parallel. Seeking is relative to the range, i.e.
+/// ``SeekFrom::End`` is relative to ``len``, and a seek past it makes the reads return
+/// the end.
+pub struct HdfsSliceReader {
+    file: Arc<HdfsFile>,
+    offset: u64,
+    len: u64,
+    /// The position of the next read in the range
+    pos: u64,
+}
+
+impl HdfsSliceReader {
+    pub fn new(file: Arc<HdfsFile>, offset: u64, len: u64) -> HdfsSliceReader {
+        HdfsSliceReader {
+            file,
+            offset,
+            len,
+            pos: 0,
+        }
+    }
+
+    /// Get the underlying file
+    #[inline]
+    pub fn get_ref(&self) -> &HdfsFile {
+        &self.file
+    }
+
+    /// Get the offset of the range in the file
+    #[inline]
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Get the length of the range
+    #[allow(clippy::len_without_is_empty)]
+    #[inline]
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+}
+
+impl Read for HdfsSliceReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let remaining = self.len.saturating_sub(self.pos);
+        let read_len = buf.len().min(remaining.min(usize::MAX as u64) as usize);
+        if read_len == 0 {
+            return Ok(0);
+        }
+
+        let read = pread(&self.file, self.offset + self.pos, &mut buf[..read_len])?;
+        self.pos += read as u64;
+        Ok(read)
+    }
+}
+
+impl Seek for HdfsSliceReader {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let (base, offset) = match pos {
+            SeekFrom::Start(offset) => (0, offset as i128),
+            SeekFrom::End(offset) => (self.len as i128, offset as i128),
+            SeekFrom::Current(offset) => (self.pos as i128, offset as i128),
+        };
+
+        let target = base + offset;
+        if target < 0 || target > u64::MAX as i128 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("invalid seek to {} in {}", target, self.file.path()),
+            ));
+        }
+        self.pos = target as u64;
+
+        Ok(self.pos)
+    }
+}
+
+fn pread(file: &HdfsFile, pos: u64, buf: &mut [u8]) -> io::Result<usize> {
+    if pos > i64::MAX as u64 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("invalid position {} in {}", pos, file.path()),
+        ));
+    }
+
+    file.pread(pos as i64, buf)
+        .map_err(|e| io::Error::other(format!("{:?}", e)))
+}
+
+#[cfg(test)]
+mod test {
+    use std::io;
+    use std::io::{Read, Seek, SeekFrom, Write};
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Instant;
+
+    use uuid::Uuid;
+
+    use super::{HdfsBufReader, HdfsSliceReader, Prefetch};
+    use crate::minidfs::get_dfs;
+
+    #[test]
+    fn test_buf_reader() {
+        let dfs = get_dfs();
+        {
+            let fs = dfs.get_hdfs().ok().unwrap();
+
+            // Multiple blocks with a size which is not a multiple of the buffer size
+            let block_size = 1024 * 1024;
+            let data: Vec<u8> = (0..(2 * block_size + 1000)).map(|i| i as u8).collect();
+            let test_file = format!("/{}", Uuid::new_v4());
+            let mut file = fs
+                .create_with_params(&test_file, false, 0, 1, block_size as i32)
+                .ok()
+                .unwrap();
+            assert!(file.write_all(&data).is_ok());
+            assert!(file.close().is_ok());
+
+            let file = fs.open(&test_file).ok().unwrap();
+            let mut reader = HdfsBufReader::with_capacity(300 * 1024, file).ok().unwrap();
+            let mut buf = Vec::new();
+            assert_eq!(data.len(), reader.read_to_end(&mut buf).unwrap());
+            assert_eq!(data, buf);
+
+            // Seek within and out of the buffer
+            for pos in [10, 20, block_size - 5, 5, 2 * block_size + 999].iter() {
+                assert_eq!(
+                    *pos as u64,
+                    reader.seek(SeekFrom::Start(*pos as u64)).unwrap()
+                );
+                let mut buf = [0u8; 10];
+                let expected = &data[*pos..(*pos + 10).min(data.len())];
+                assert_eq!(expected.len(), reader.read(&mut buf).unwrap());
+                assert_eq!(expected, &buf[..expected.len()]);
+            }
+            assert_eq!(
+                data.len() as u64 - 10,
+                reader.seek(SeekFrom::End(-10)).unwrap()
+            );
+            let mut buf = [0u8; 10];
+            reader.read_exact(&mut buf).unwrap();
+            assert_eq!(&data[data.len() - 10..], &buf[..]);
+            assert_eq!(0, reader.read(&mut buf).unwrap());
+            assert!(reader
+                .seek(SeekFrom::Current(-(data.len() as i64) - 1))
+                .is_err());
+
+            // Clean up
+            assert!(reader.into_inner().close().is_ok());
+            assert!(fs.delete(&test_file, false).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_buf_reader_byte_by_byte() {
+        let dfs = get_dfs();
+        {
+            let fs = dfs.get_hdfs().ok().unwrap();
+
+            let data: Vec<u8> = (0..64 * 1024).map(|i| (i % 251) as u8).collect();
+            let test_file = format!("/{}", Uuid::new_v4());
+            let mut file = fs.create(&test_file).ok().unwrap();
+            assert!(file.write_all(&data).is_ok());
+            assert!(file.close().is_ok());
+
+            let read_byte_by_byte = |reader: &mut dyn Read| -> Vec<u8> {
+                let mut read = Vec::new();
+                let mut byte = [0u8; 1];
+                while reader.read(&mut byte).unwrap() == 1 {
+                    read.push(byte[0]);
+                }
+                read
+            };
+
+            let mut file = fs.open(&test_file).ok().unwrap();
+            let start = Instant::now();
+            assert_eq!(data, read_byte_by_byte(&mut file));
+            let raw_elapsed = start.elapsed();
+            assert!(file.close().is_ok());
+
+            let file = fs.open(&test_file).ok().unwrap();
+            let mut reader = HdfsBufReader::new(file).ok().unwrap();
+            let start = Instant::now();
+            assert_eq!(data, read_byte_by_byte(&mut reader));
+            let buffered_elapsed = start.elapsed();
+            assert!(reader.into_inner().close().is_ok());
+
+            println!(
+                "Read {} bytes one by one, raw: {:?}, buffered: {:?}",
+                data.len(),
+                raw_elapsed,
+                buffered_elapsed
+            );
+
+            // Clean up
+            assert!(fs.delete(&test_file, false).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_slice_reader() {
+        let dfs = get_dfs();
+        {
+            let fs = dfs.get_hdfs().ok().unwrap();
+            let data: Vec<u8> = (0..10 * 1024).map(|i| (i % 253) as u8).collect();
+            let test_file = format!("/{}", Uuid::new_v4());
+            assert!(fs.write_all(&test_file, &data, false).is_ok());
+
+            let file = Arc::new(fs.open(&test_file).ok().unwrap());
+            let mut read = Vec::new();
+            let mut reader = HdfsSliceReader::new(file.clone(), 1000, 3000);
+            assert_eq!(3000, reader.read_to_end(&mut read).unwrap());
+            assert_eq!(&data[1000..4000], &read[..]);
+
+            // Beyond the end of the file
+            let mut read = Vec::new();
+            let mut reader = HdfsSliceReader::new(file.clone(), 9 * 1024, 4096);
+            assert_eq!(1024, reader.read_to_end(&mut read).unwrap());
+            assert_eq!(&data[9 * 1024..], &read[..]);
+            let mut reader = HdfsSliceReader::new(file.clone(), 1000, 0);
+            assert_eq!(0, reader.read(&mut [0u8; 10]).unwrap());
+            drop(reader);
+
+            // Clean up
+            assert!(Arc::try_unwrap(file).ok().unwrap().close().is_ok());
+            assert!(fs.delete(&test_file, false).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_slice_reader_seek() {
+        let dfs = get_dfs();
+        {
+            let fs = dfs.get_hdfs().ok().unwrap();
+            let data: Vec<u8> = (0..10 * 1024).map(|i| (i % 251) as u8).collect();
+            let test_file = format!("/{}", Uuid::new_v4());
+            assert!(fs.write_all(&test_file, &data, false).is_ok());
+
+            let file = Arc::new(fs.open(&test_file).ok().unwrap());
+            let mut reader = HdfsSliceReader::new(file.clone(), 2000, 1000);
+            let mut buf = [0u8; 100];
+            assert_eq!(900, reader.seek(SeekFrom::End(-100)).unwrap());
+            reader.read_exact(&mut buf).unwrap();
+            assert_eq!(&data[2900..3000], &buf[..]);
+            assert_eq!(0, reader.read(&mut buf).unwrap());
+
+            assert_eq!(10, reader.seek(SeekFrom::Start(10)).unwrap());
+            assert_eq!(60, reader.seek(SeekFrom::Current(50)).unwrap());
+            reader.read_exact(&mut buf).unwrap();
+            assert_eq!(&data[2060..2160], &buf[..]);
+
+            // Past the end of the range
+            assert_eq!(2000, reader.seek(SeekFrom::Start(2000)).unwrap());
+            assert_eq!(0, reader.read(&mut buf).unwrap());
+            assert!(reader.seek(SeekFrom::Current(-3000)).is_err());
+            drop(reader);
+
+            // Clean up
+            assert!(Arc::try_unwrap(file).ok().unwrap().close().is_ok());
+            assert!(fs.delete(&test_file, false).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_prefetching_reader() {
+        let dfs = get_dfs();
+        {
+            let fs = dfs.get_hdfs().ok().unwrap();
+
+            // Multiple blocks with a size which is not a multiple of the buffer size
+            let block_size = 1024 * 1024;
+            let data: Vec<u8> = (0..(3 * block_size + 1000))
+                .map(|i| (i % 241) as u8)
+                .collect();
+            let test_file = format!("/{}", Uuid::new_v4());
+            let mut file = fs
+                .create_with_params(&test_file, false, 0, 1, block_size as i32)
+                .ok()
+                .unwrap();
+            assert!(file.write_all(&data).is_ok());
+            assert!(file.close().is_ok());
+
+            let file = fs.open(&test_file).ok().unwrap();
+            let mut reader = HdfsBufReader::with_prefetch(300 * 1024, file, true)
+                .ok()
+                .unwrap();
+            assert!(reader.is_prefetching());
+            let mut read = Vec::new();
+            let mut buf = vec![0u8; 7000];
+            loop {
+                match reader.read(&mut buf).unwrap() {
+                    0 => break,
+                    len => read.extend_from_slice(&buf[..len]),
+                }
+            }
+            assert_eq!(data, read);
+
+            // A large read, and a seek wasting the prefetch
+            assert_eq!(0, reader.seek(SeekFrom::Start(0)).unwrap());
+            let mut read = Vec::new();
+            assert_eq!(data.len(), reader.read_to_end(&mut read).unwrap());
+            assert_eq!(data, read);
+            let pos = 2 * block_size - 10;
+            reader.seek(SeekFrom::Start(10)).unwrap();
+            reader.read_exact(&mut buf[..10]).unwrap();
+            assert_eq!(&data[10..20], &buf[..10]);
+            reader.seek(SeekFrom::Start(pos as u64)).unwrap();
+            reader.read_exact(&mut buf).unwrap();
+            assert_eq!(&data[pos..pos + buf.len()], &buf[..]);
+
+            // Clean up
+            assert!(reader.into_inner().close().is_ok());
+            assert!(fs.delete(&test_file, false).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_prefetch_error() {
+        let dfs = get_dfs();
+        {
+            let fs = dfs.get_hdfs().ok().unwrap();
+            let data: Vec<u8> = (0..10 * 1024).map(|i| (i % 251) as u8).collect();
+            let test_file = format!("/{}", Uuid::new_v4());
+            assert!(fs.write_all(&test_file, &data, false).is_ok());
+
+            let file = fs.open(&test_file).ok().unwrap();
+            let mut reader = HdfsBufReader::with_prefetch(1024, file, true).ok().unwrap();
+            let mut buf = vec![0u8; 1024];
+            reader.read_exact(&mut buf).unwrap();
+            assert_eq!(&data[..1024], &buf[..]);
+
+            // Replace the pending prefetch of the next fill with a failed one
+            let start = reader.pending.as_ref().unwrap().start;
+            assert_eq!(1024, start);
+            reader.pending = Some(Prefetch {
+                start,
+                handle: Some(thread::spawn(|| {
+                    (vec![0u8; 1024], Err(io::Error::other("injected failure")))
+                })),
+            });
+            let err = reader.read(&mut buf).unwrap_err();
+            assert_eq!("injected failure", err.to_string());
+
+            // The next read is retried without the prefetch
+            reader.read_exact(&mut buf).unwrap();
+            assert_eq!(&data[1024..2048], &buf[..]);
+            let mut read = Vec::new();
+            reader.read_to_end(&mut read).unwrap();
+            assert_eq!(&data[2048..], &read[..]);
+
+            // Clean up
+            assert!(reader.into_inner().close().is_ok());
+            assert!(fs.delete(&test_file, false).is_ok());
+        }
+    }
+}
@@ -0,0 +1,315 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! Retry of the operations failing transiently, e.g. during a failover of the namenode
+
+use std::thread;
+use std::time::Duration;
+
+use log::warn;
+
+use crate::err::HdfsErr;
+use crate::hdfs::{FileKind, FileStatus, HdfsFile, HdfsFs};
+
+/// The java exceptions thrown by HDFS which are expected to succeed on a retry
+///
+/// ``SafeModeException`` is not among them, since libhdfs translates it into ``EROFS``.
+const TRANSIENT_EXCEPTIONS: [&str; 4] = [
+    "StandbyException",
+    "RetriableException",
+    "ConnectException",
+    "SocketTimeoutException",
+];
+
+/// Policy of retrying an operation with exponential backoff.
+///
+/// The n-th retry is delayed by ``base_delay * 2^(n - 1)``, but not more than
+/// ``max_delay``. Only the errors accepted by ``retryable`` are retried.
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    /// The maximal number of attempts, including the first one
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    /// Whether an error is transient and worth a retry, ``is_transient`` by default
+    pub retryable: fn(&HdfsErr) -> bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 4,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(5),
+            retryable: is_transient,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Run the operation until it succeeds, fails with an error which is not retryable,
+    /// or runs out of the attempts. The last error is returned on failure.
+    pub fn run<T, F>(&self, mut op: F) -> Result<T, HdfsErr>
+    where
+        F: FnMut() -> Result<T, HdfsErr>,
+    {
+        let mut attempt = 1;
+        loop {
+            match op() {
+                Err(e) if attempt < self.max_attempts && (self.retryable)(&e) => {
+                    let delay = self.delay(attempt);
+                    warn!(
+                        "Retry in {:?} after attempt {} failed: {:?}",
+                        delay, attempt, e
+                    );
+                    thread::sleep(delay);
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// The delay before the retry after the given attempt
+    fn delay(&self, attempt: u32) -> Duration {
+        let factor = 1u32.checked_shl(attempt - 1).unwrap_or(u32::MAX);
+        self.base_delay
+            .checked_mul(factor)
+            .map_or(self.max_delay, |delay| delay.min(self.max_delay))
+    }
+}
+
+/// Whether an error is expected to be transient, i.e. a failure to connect to the
+/// namenode, a java exception thrown during a failover, or the ``EROFS`` of a
+/// namenode in safe mode.
+pub fn is_transient(err: &HdfsErr) -> bool {
+    match err {
+        HdfsErr::CannotConnectToNameNode(_) => true,
+        HdfsErr::Io(libc::EROFS) => true,
+        HdfsErr::Exception { message } => TRANSIENT_EXCEPTIONS
+            .iter()
+            .any(|exception| message.contains(exception)),
+        _ => false,
+    }
+}
+
+/// A HdfsFs whose operations are retried by the policy, created by ``HdfsFs::with_retry``.
+///
+/// An operation is retried as a whole, so a non-idempotent one may have taken effect
+/// before it fails, e.g. ``delete`` returns ``false`` for a retry if the path has been
+/// deleted by the failed attempt.
+#[derive(Debug, Clone)]
+pub struct RetryingHdfsFs {
+    fs: HdfsFs,
+    policy: RetryPolicy,
+}
+
+impl RetryingHdfsFs {
+    pub(crate) fn new(fs: HdfsFs, policy: RetryPolicy) -> RetryingHdfsFs {
+        RetryingHdfsFs { fs, policy }
+    }
+
+    /// Get the underlying HdfsFs, whose operations are not retried
+    #[inline]
+    pub fn fs(&self) -> &HdfsFs {
+        &self.fs
+    }
+
+    /// Get the retry policy
+    #[inline]
+    pub fn policy(&self) -> &RetryPolicy {
+        &self.policy
+    }
+
+    /// Open a file for read
+    pub fn open(&self, path: &str) -> Result<HdfsFile, HdfsErr> {
+        self.policy.run(|| self.fs.open(path))
+    }
+
+    /// Read the whole file, see ``HdfsFs::read_to_vec``
+    pub fn read_to_vec(&self, path: &str) -> Result<Vec<u8>, HdfsErr> {
+        self.policy.run(|| self.fs.read_to_vec(path))
+    }
+
+    /// Write the whole data into a file, see ``HdfsFs::write_all``
+    pub fn write_all(
+        &self,
+        path: &str,
+        data: &[u8],
+        overwrite: bool,
+    ) -> Result<(), HdfsErr> {
+        self.policy.run(|| self.fs.write_all(path, data, overwrite))
+    }
+
+    /// Get the file status
+    pub fn get_file_status(&self, path: &str) -> Result<FileStatus, HdfsErr> {
+        self.policy.run(|| self.fs.get_file_status(path))
+    }
+
+    /// Get the file status for each entry under the specified directory
+    pub fn list_status(&self, path: &str) -> Result<Vec<FileStatus>, HdfsErr> {
+        self.policy.run(|| self.fs.list_status(path))
+    }
+
+    /// Get the kind of the path, or ``None`` if it doesn't exist
+    pub fn path_kind(&self, path: &str) -> Result<Option<FileKind>, HdfsErr> {
+        self.policy.run(|| self.fs.path_kind(path))
+    }
+
+    /// Create a directory and all of its missing parents
+    pub fn mkdir(&self, path: &str) -> Result<bool, HdfsErr> {
+        self.policy.run(|| self.fs.mkdir(path))
+    }
+
+    /// Rename a file or a directory
+    pub fn rename(&self, old_path: &str, new_path: &str) -> Result<bool, HdfsErr> {
+        self.policy.run(|| self.fs.rename(old_path, new_path))
+    }
+
+    /// Delete a file or a directory, see ``HdfsFs::delete``
+    pub fn delete(&self, path: &str, recursive: bool) -> Result<bool, HdfsErr> {
+        self.policy.run(|| self.fs.delete(path, recursive))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::cell::Cell;
+    use std::time::Duration;
+
+    use uuid::Uuid;
+
+    use super::{is_transient, RetryPolicy};
+    use crate::err::HdfsErr;
+    use crate::minidfs::get_dfs;
+
+    fn policy(max_attempts: u32) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts,
+            base_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(5),
+            ..RetryPolicy::default()
+        }
+    }
+
+    #[test]
+    fn test_retry_until_success() {
+        let attempts = Cell::new(0);
+        let result = policy(5).run(|| {
+            attempts.set(attempts.get() + 1);
+            if attempts.get() < 3 {
+                Err(HdfsErr::CannotConnectToNameNode(
+                    "hdfs://mycluster".to_owned(),
+                ))
+            } else {
+                Ok(attempts.get())
+            }
+        });
+        assert_eq!(3, result.ok().unwrap());
+        assert_eq!(3, attempts.get());
+    }
+
+    #[test]
+    fn test_retry_exhausted() {
+        let attempts = Cell::new(0);
+        let result: Result<(), HdfsErr> = policy(3).run(|| {
+            attempts.set(attempts.get() + 1);
+            Err(HdfsErr::Exception {
+                message: "StandbyException: Operation category READ is not supported"
+                    .to_owned(),
+            })
+        });
+        assert!(matches!(result, Err(HdfsErr::Exception { .. })));
+        assert_eq!(3, attempts.get());
+    }
+
+    #[test]
+    fn test_not_retryable() {
+        let attempts = Cell::new(0);
+        let result: Result<(), HdfsErr> = policy(5).run(|| {
+            attempts.set(attempts.get() + 1);
+            Err(HdfsErr::FileNotFound("/missing".to_owned()))
+        });
+        assert!(matches!(result, Err(HdfsErr::FileNotFound(_))));
+        assert_eq!(1, attempts.get());
+
+        // A custom set of retryable errors
+        let attempts = Cell::new(0);
+        let policy = RetryPolicy {
+            retryable: |e| matches!(e, HdfsErr::FileNotFound(_)),
+            ..policy(5)
+        };
+        let result: Result<(), HdfsErr> = policy.run(|| {
+            attempts.set(attempts.get() + 1);
+            Err(HdfsErr::FileNotFound("/missing".to_owned()))
+        });
+        assert!(result.is_err());
+        assert_eq!(5, attempts.get());
+    }
+
+    #[test]
+    fn test_delay() {
+        let policy = RetryPolicy {
+            max_attempts: 100,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(1),
+            ..RetryPolicy::default()
+        };
+        assert_eq!(Duration::from_millis(100), policy.delay(1));
+        assert_eq!(Duration::from_millis(200), policy.delay(2));
+        assert_eq!(Duration::from_millis(800), policy.delay(4));
+        assert_eq!(Duration::from_secs(1), policy.delay(5));
+        assert_eq!(Duration::from_secs(1), policy.delay(64));
+    }
+
+    #[test]
+    fn test_is_transient() {
+        assert!(is_transient(&HdfsErr::CannotConnectToNameNode(
+            "hdfs://mycluster".to_owned()
+        )));
+        assert!(is_transient(&HdfsErr::Exception {
+            message: "RetriableException: Name node is in safe mode".to_owned()
+        }));
+        assert!(!is_transient(&HdfsErr::Exception {
+            message: "AccessControlException: Permission denied".to_owned()
+        }));
+        assert!(is_transient(&HdfsErr::Io(libc::EROFS)));
+        assert!(!is_transient(&HdfsErr::Io(libc::EIO)));
+        assert!(!is_transient(&HdfsErr::FileNotFound("/missing".to_owned())));
+    }
+
+    #[test]
+    fn test_retrying_fs() {
+        let dfs = get_dfs();
+        {
+            let fs = dfs.get_hdfs().ok().unwrap().with_retry(policy(3));
+            let test_file = format!("/{}", Uuid::new_v4());
+
+            assert!(fs.write_all(&test_file, b"retry", false).is_ok());
+            assert_eq!(b"retry".to_vec(), fs.read_to_vec(&test_file).ok().unwrap());
+
+            let missing = format!("/{}", Uuid::new_v4());
+            assert!(matches!(
+                fs.get_file_status(&missing),
+                Err(HdfsErr::FileNotFound(_))
+            ));
+
+            // Clean up
+            assert!(fs.delete(&test_file, false).ok().unwrap());
+        }
+    }
+}
@@ -15,11 +15,18 @@
 
 use crate::err::HdfsErr;
 use crate::hdfs;
-use crate::hdfs::{get_uri, HdfsFs};
+use crate::hdfs::{get_uri, to_c_string, FileKind, HdfsFile, HdfsFs};
 use crate::minidfs::MiniDFS;
 use crate::native::{hdfsCopy, hdfsMove};
-use std::ffi::CString;
-use std::sync::Arc;
+use log::warn;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Default chunk size of the streaming copy
+pub const DEFAULT_COPY_CHUNK_SIZE: usize = 1024 * 1024;
 
 /// Hdfs Utility
 pub struct HdfsUtil;
@@ -94,22 +101,350 @@ impl HdfsUtil {
         dst_fs: &HdfsFs,
         dst: &str,
     ) -> Result<bool, HdfsErr> {
+        HdfsUtil::copy_opts(src_fs, src, dst_fs, dst, true)
+    }
+
+    /// Copy file from one filesystem to another, with the control of overwriting.
+    ///
+    /// If the destination is an existing file, it fails with ``FileAlreadyExists``
+    /// without touching the file when ``overwrite`` is false, or the file is deleted
+    /// before the copy when it's true.
+    pub fn copy_opts(
+        src_fs: &HdfsFs,
+        src: &str,
+        dst_fs: &HdfsFs,
+        dst: &str,
+        overwrite: bool,
+    ) -> Result<bool, HdfsErr> {
+        if dst_fs.path_kind(dst)? == Some(FileKind::File) {
+            if !overwrite {
+                return Err(HdfsErr::FileAlreadyExists(dst.to_owned()));
+            }
+            dst_fs.delete(dst, false)?;
+        }
+
+        HdfsUtil::copy_with_size(src_fs, src, dst_fs, dst).map(|_| true)
+    }
+
+    /// Copy file from one filesystem to another, and return the size of the copied file.
+    ///
+    /// #### Params
+    /// * ```srcFS``` - The handle to source filesystem.
+    /// * ```src``` - The path of source file.
+    /// * ```dstFS``` - The handle to destination filesystem.
+    /// * ```dst``` - The path of destination file.
+    pub fn copy_with_size(
+        src_fs: &HdfsFs,
+        src: &str,
+        dst_fs: &HdfsFs,
+        dst: &str,
+    ) -> Result<u64, HdfsErr> {
         let res = unsafe {
-            let cstr_src = CString::new(src).unwrap();
-            let cstr_dst = CString::new(dst).unwrap();
-            hdfsCopy(
-                src_fs.raw(),
-                cstr_src.as_ptr(),
-                dst_fs.raw(),
-                cstr_dst.as_ptr(),
+            let cstr_src = to_c_string(src)?;
+            let cstr_dst = to_c_string(dst)?;
+            native_call!(
+                hdfsCopy(
+                    src_fs.raw(),
+                    cstr_src.as_ptr(),
+                    dst_fs.raw(),
+                    cstr_dst.as_ptr(),
+                ),
+                src,
+                dst
             )
         };
 
         if res == 0 {
-            Ok(true)
+            Ok(dst_fs.get_file_status(dst)?.len() as u64)
         } else {
-            Err(HdfsErr::Unknown)
+            Err(HdfsErr::from_errno(src).logged(src))
+        }
+    }
+
+    /// Copy file from one filesystem to another by streaming the data through the client
+    /// in chunks of ``DEFAULT_COPY_CHUNK_SIZE``, and return the size of the copied file.
+    ///
+    /// Unlike ``copy``, the progress is observable. ``progress`` is called with the total
+    /// number of bytes copied so far after each chunk.
+    pub fn copy_with_progress<F: FnMut(u64)>(
+        src_fs: &HdfsFs,
+        src: &str,
+        dst_fs: &HdfsFs,
+        dst: &str,
+        progress: F,
+    ) -> Result<u64, HdfsErr> {
+        HdfsUtil::copy_with_progress_and_chunk_size(
+            src_fs,
+            src,
+            dst_fs,
+            dst,
+            DEFAULT_COPY_CHUNK_SIZE,
+            progress,
+        )
+    }
+
+    /// Similar to ``copy_with_progress``, but with the specified chunk size
+    pub fn copy_with_progress_and_chunk_size<F: FnMut(u64)>(
+        src_fs: &HdfsFs,
+        src: &str,
+        dst_fs: &HdfsFs,
+        dst: &str,
+        chunk_size: usize,
+        mut progress: F,
+    ) -> Result<u64, HdfsErr> {
+        if chunk_size == 0 {
+            return Err(HdfsErr::InvalidArgument(format!(
+                "zero chunk size to copy {}",
+                src
+            )));
         }
+
+        let mut src_file = src_fs.open(src)?;
+        let mut dst_file = match dst_fs.create_with_overwrite(dst, true) {
+            Ok(dst_file) => dst_file,
+            Err(e) => {
+                let _ = src_file.close();
+                return Err(e);
+            }
+        };
+
+        let copied = HdfsUtil::copy_chunks(
+            &mut src_file,
+            &mut dst_file,
+            chunk_size,
+            &mut progress,
+        );
+        let src_closed = src_file.close();
+        let dst_closed = dst_file.close();
+
+        let copied = copied?;
+        src_closed?;
+        dst_closed?;
+        Ok(copied)
+    }
+
+    fn copy_chunks<F: FnMut(u64)>(
+        src_file: &mut HdfsFile,
+        dst_file: &mut HdfsFile,
+        chunk_size: usize,
+        progress: &mut F,
+    ) -> Result<u64, HdfsErr> {
+        let mut buf = vec![0u8; chunk_size];
+        let mut copied = 0u64;
+
+        loop {
+            let read_len = src_file.read(&mut buf)? as usize;
+            if read_len == 0 {
+                return Ok(copied);
+            }
+
+            dst_file.write_chunked(&buf[..read_len], chunk_size)?;
+            copied += read_len as u64;
+            progress(copied);
+        }
+    }
+
+    /// Copy file from one filesystem to another by streaming the data through the client,
+    /// limiting the rate to ``bytes_per_sec``, and return the size of the copied file.
+    ///
+    /// The rate is limited by a token bucket refilled at ``bytes_per_sec``, which takes
+    /// the tokens of each chunk after it's copied, so that a short burst of a chunk is
+    /// allowed but the average rate is kept. A chunk is a tenth of the rate at most.
+    pub fn copy_throttled(
+        src_fs: &HdfsFs,
+        src: &str,
+        dst_fs: &HdfsFs,
+        dst: &str,
+        bytes_per_sec: u64,
+    ) -> Result<u64, HdfsErr> {
+        if bytes_per_sec == 0 {
+            return Err(HdfsErr::InvalidArgument(format!(
+                "zero rate to copy {}",
+                src
+            )));
+        }
+
+        let chunk_size =
+            (bytes_per_sec / 10).clamp(1, DEFAULT_COPY_CHUNK_SIZE as u64) as usize;
+        let mut bucket = TokenBucket::new(bytes_per_sec, chunk_size as u64);
+        let mut last_copied = 0;
+        HdfsUtil::copy_with_progress_and_chunk_size(
+            src_fs,
+            src,
+            dst_fs,
+            dst,
+            chunk_size,
+            |copied| {
+                bucket.take(copied - last_copied);
+                last_copied = copied;
+            },
+        )
+    }
+
+    /// Copy a directory recursively from one filesystem to another, and return the total
+    /// size of the copied files.
+    ///
+    /// The directory tree is recreated under ``dst_dir``, which is merged into if it exists.
+    /// A file that can't be opened for read is skipped with a warning.
+    pub fn copy_dir(
+        src_fs: &HdfsFs,
+        src_dir: &str,
+        dst_fs: &HdfsFs,
+        dst_dir: &str,
+    ) -> Result<u64, HdfsErr> {
+        // The listed names are fully qualified
+        let src_root = src_fs.get_file_status(src_dir)?.name().to_owned();
+        let dst_dir = dst_dir.trim_end_matches('/');
+        dst_fs.mkdir(dst_dir)?;
+
+        let mut copied = 0;
+        for status in src_fs.list_status_recursive(src_dir)? {
+            let dst = format!("{}/{}", dst_dir, relative_path(&src_root, status.name()));
+
+            if status.is_directory() {
+                dst_fs.mkdir(&dst)?;
+                continue;
+            }
+
+            match src_fs.open(status.name()) {
+                Ok(file) => {
+                    let _ = file.close();
+                }
+                Err(e) => {
+                    warn!(
+                        "Skip copying {} which can't be read: {:?}",
+                        status.name(),
+                        e
+                    );
+                    continue;
+                }
+            }
+            copied += HdfsUtil::copy_with_size(src_fs, status.name(), dst_fs, &dst)?;
+        }
+
+        Ok(copied)
+    }
+
+    /// Compare the directory trees of two filesystems by the paths relative to the
+    /// directories, e.g. to verify a replication, see ``DirDiff``.
+    ///
+    /// Only the lengths of the files are compared rather than the contents, which can be
+    /// verified by ``verify_copy``.
+    pub fn diff_dirs(
+        fs_a: &HdfsFs,
+        dir_a: &str,
+        fs_b: &HdfsFs,
+        dir_b: &str,
+    ) -> Result<DirDiff, HdfsErr> {
+        let entries_a = HdfsUtil::relative_entries(fs_a, dir_a)?;
+        let mut entries_b = HdfsUtil::relative_entries(fs_b, dir_b)?;
+
+        let mut diff = DirDiff::default();
+        for (relative, (kind_a, len_a)) in entries_a {
+            match entries_b.remove(&relative) {
+                None => diff.only_in_a.push(relative),
+                Some((kind_b, len_b)) => {
+                    if kind_a != kind_b || (kind_a == FileKind::File && len_a != len_b) {
+                        diff.different.push(relative);
+                    }
+                }
+            }
+        }
+        diff.only_in_b = entries_b.into_keys().collect();
+
+        Ok(diff)
+    }
+
+    /// Get the kind and the length of each entry of the tree by its relative path
+    fn relative_entries(
+        fs: &HdfsFs,
+        dir: &str,
+    ) -> Result<BTreeMap<String, (FileKind, usize)>, HdfsErr> {
+        // The listed names are fully qualified
+        let root = fs.get_file_status(dir)?.name().to_owned();
+        Ok(fs
+            .list_status_recursive(dir)?
+            .into_iter()
+            .map(|status| {
+                (
+                    relative_path(&root, status.name()).to_owned(),
+                    (status.kind(), status.len()),
+                )
+            })
+            .collect())
+    }
+
+    /// Copy files concurrently by up to ``parallelism`` threads, and return the result of
+    /// each copy in the order of ``pairs``, which is the size of the copied file on success.
+    ///
+    /// Each pair is ``(src_fs, src, dst_fs, dst)`` as the arguments of ``copy_with_size``.
+    /// A failed copy doesn't stop the others.
+    pub fn copy_many(
+        pairs: &[(&HdfsFs, &str, &HdfsFs, &str)],
+        parallelism: usize,
+    ) -> Vec<Result<u64, HdfsErr>> {
+        let next = AtomicUsize::new(0);
+        let results: Mutex<Vec<Option<Result<u64, HdfsErr>>>> =
+            Mutex::new(pairs.iter().map(|_| None).collect());
+
+        thread::scope(|scope| {
+            for _ in 0..parallelism.clamp(1, pairs.len().max(1)) {
+                scope.spawn(|| loop {
+                    let idx = next.fetch_add(1, Ordering::SeqCst);
+                    let (src_fs, src, dst_fs, dst) = match pairs.get(idx) {
+                        Some(pair) => *pair,
+                        None => break,
+                    };
+                    let result = HdfsUtil::copy_with_size(src_fs, src, dst_fs, dst);
+                    results.lock().unwrap()[idx] = Some(result);
+                });
+            }
+        });
+
+        results
+            .into_inner()
+            .unwrap()
+            .into_iter()
+            .map(|result| result.unwrap())
+            .collect()
+    }
+
+    /// Verify a copied file by comparing the CRC32 checksums of both files, which are
+    /// computed by the client while streaming the files.
+    ///
+    /// It doesn't rely on the checksums of HDFS, so the filesystems can be different,
+    /// e.g. ``file://`` and ``hdfs://``. Files of different lengths are never read.
+    pub fn verify_copy(
+        src_fs: &HdfsFs,
+        src: &str,
+        dst_fs: &HdfsFs,
+        dst: &str,
+    ) -> Result<bool, HdfsErr> {
+        if src_fs.get_file_status(src)?.len() != dst_fs.get_file_status(dst)?.len() {
+            return Ok(false);
+        }
+
+        Ok(HdfsUtil::checksum(src_fs, src)? == HdfsUtil::checksum(dst_fs, dst)?)
+    }
+
+    /// Compute the CRC32 checksum of a file by streaming it
+    fn checksum(fs: &HdfsFs, path: &str) -> Result<u32, HdfsErr> {
+        let mut file = fs.open(path)?;
+        let mut buf = vec![0u8; DEFAULT_COPY_CHUNK_SIZE];
+        let mut crc = Crc32::new();
+
+        let read = loop {
+            match file.read(&mut buf) {
+                Ok(0) => break Ok(()),
+                Ok(read_len) => crc.update(&buf[..read_len as usize]),
+                Err(e) => break Err(e),
+            }
+        };
+        let closed = file.close();
+
+        read?;
+        closed?;
+        Ok(crc.finish())
     }
 
     /// Move file from one filesystem to another.
@@ -126,28 +461,144 @@ impl HdfsUtil {
         dst: &str,
     ) -> Result<bool, HdfsErr> {
         let res = unsafe {
-            let cstr_src = CString::new(src).unwrap();
-            let cstr_dst = CString::new(dst).unwrap();
-            hdfsMove(
-                src_fs.raw(),
-                cstr_src.as_ptr(),
-                dst_fs.raw(),
-                cstr_dst.as_ptr(),
+            let cstr_src = to_c_string(src)?;
+            let cstr_dst = to_c_string(dst)?;
+            native_call!(
+                hdfsMove(
+                    src_fs.raw(),
+                    cstr_src.as_ptr(),
+                    dst_fs.raw(),
+                    cstr_dst.as_ptr(),
+                ),
+                src,
+                dst
             )
         };
 
         if res == 0 {
             Ok(true)
         } else {
-            Err(HdfsErr::Unknown)
+            Err(HdfsErr::from_errno(src).logged(src))
+        }
+    }
+}
+
+/// The differences of two directory trees, by the paths relative to the directories
+/// in the lexicographical order
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DirDiff {
+    /// The files and the directories only in the first tree
+    pub only_in_a: Vec<String>,
+    /// The files and the directories only in the second tree
+    pub only_in_b: Vec<String>,
+    /// The files in both trees of different lengths, or the paths of a file in one of
+    /// the trees but a directory in the other
+    pub different: Vec<String>,
+}
+
+impl DirDiff {
+    /// Whether the trees are the same
+    pub fn is_empty(&self) -> bool {
+        self.only_in_a.is_empty()
+            && self.only_in_b.is_empty()
+            && self.different.is_empty()
+    }
+}
+
+/// Get the path of an entry relative to the fully qualified root of its tree
+fn relative_path<'a>(root: &str, name: &'a str) -> &'a str {
+    name.strip_prefix(root)
+        .unwrap_or(name)
+        .trim_start_matches('/')
+}
+
+/// A token bucket refilled at a rate of tokens per second, which starts empty
+struct TokenBucket {
+    rate: u64,
+    capacity: u64,
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    fn new(rate: u64, capacity: u64) -> TokenBucket {
+        TokenBucket {
+            rate,
+            capacity,
+            tokens: 0.0,
+            last_refill: Instant::now(),
+        }
+    }
+
+    /// Take the tokens, which waits until they are refilled if there are not enough
+    fn take(&mut self, tokens: u64) {
+        self.refill();
+        let missing = tokens as f64 - self.tokens;
+        if missing > 0.0 {
+            thread::sleep(Duration::from_secs_f64(missing / self.rate as f64));
+            self.refill();
+        }
+        self.tokens = (self.tokens - tokens as f64).max(0.0);
+    }
+
+    fn refill(&mut self) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        self.tokens =
+            (self.tokens + elapsed * self.rate as f64).min(self.capacity as f64);
+        self.last_refill = now;
+    }
+}
+
+/// CRC32 with the IEEE polynomial, as the one of zlib
+struct Crc32(u32);
+
+impl Crc32 {
+    const TABLE: [u32; 256] = Crc32::table();
+
+    const fn table() -> [u32; 256] {
+        let mut table = [0u32; 256];
+        let mut i = 0;
+        while i < 256 {
+            let mut crc = i as u32;
+            let mut bit = 0;
+            while bit < 8 {
+                crc = if crc & 1 == 1 {
+                    (crc >> 1) ^ 0xEDB8_8320
+                } else {
+                    crc >> 1
+                };
+                bit += 1;
+            }
+            table[i] = crc;
+            i += 1;
+        }
+        table
+    }
+
+    fn new() -> Crc32 {
+        Crc32(!0)
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        for byte in data {
+            self.0 =
+                Crc32::TABLE[((self.0 ^ *byte as u32) & 0xFF) as usize] ^ (self.0 >> 8);
         }
     }
+
+    fn finish(&self) -> u32 {
+        !self.0
+    }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::hdfs::HdfsFsBuilder;
     use crate::minidfs::get_dfs;
+    use crate::path;
+    use std::io::Write;
     use std::path::Path;
     use tempfile::tempdir;
     use uuid::Uuid;
@@ -242,4 +693,400 @@ mod test {
             }
         }
     }
+
+    #[test]
+    fn test_copy_with_size() {
+        let dfs = get_dfs();
+        {
+            let fs = dfs.get_hdfs().ok().unwrap();
+
+            let src_file = format!("/{}", Uuid::new_v4());
+            let mut file = fs.create(&src_file).ok().unwrap();
+            assert!(file.write_all(&[1u8; 1234]).is_ok());
+            assert!(file.close().is_ok());
+
+            let dst_file = format!("/{}", Uuid::new_v4());
+            let copied = HdfsUtil::copy_with_size(&fs, &src_file, &fs, &dst_file)
+                .ok()
+                .unwrap();
+            assert_eq!(1234, copied);
+            assert_eq!(1234, fs.get_file_status(&dst_file).ok().unwrap().len());
+
+            // Clean up
+            assert!(fs.delete(&src_file, false).is_ok());
+            assert!(fs.delete(&dst_file, false).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_copy_with_progress() {
+        let dfs = get_dfs();
+        {
+            let fs = dfs.get_hdfs().ok().unwrap();
+
+            let data: Vec<u8> = (0..3500u32).map(|i| i as u8).collect();
+            let src_file = format!("/{}", Uuid::new_v4());
+            let mut file = fs.create(&src_file).ok().unwrap();
+            assert!(file.write_all(&data).is_ok());
+            assert!(file.close().is_ok());
+
+            let dst_file = format!("/{}", Uuid::new_v4());
+            let mut progress = Vec::new();
+            let copied = HdfsUtil::copy_with_progress_and_chunk_size(
+                &fs,
+                &src_file,
+                &fs,
+                &dst_file,
+                1000,
+                |copied| progress.push(copied),
+            )
+            .ok()
+            .unwrap();
+            assert_eq!(data.len() as u64, copied);
+            assert!(progress.windows(2).all(|pair| pair[0] < pair[1]));
+            assert_eq!(Some(&copied), progress.last());
+
+            let mut buf = vec![0u8; data.len()];
+            let file = fs.open(&dst_file).ok().unwrap();
+            assert_eq!(data.len(), file.pread(0, &mut buf).ok().unwrap());
+            assert_eq!(data, buf);
+            assert!(file.close().is_ok());
+
+            // Default chunk size
+            let mut last_progress = 0;
+            let copied =
+                HdfsUtil::copy_with_progress(&fs, &src_file, &fs, &dst_file, |copied| {
+                    last_progress = copied
+                })
+                .ok()
+                .unwrap();
+            assert_eq!(data.len() as u64, copied);
+            assert_eq!(copied, last_progress);
+
+            // Clean up
+            assert!(fs.delete(&src_file, false).is_ok());
+            assert!(fs.delete(&dst_file, false).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_copy_throttled() {
+        let dfs = get_dfs();
+        {
+            let fs = dfs.get_hdfs().ok().unwrap();
+
+            let data: Vec<u8> = (0..3 * 1024 * 1024u32).map(|i| i as u8).collect();
+            let src_file = format!("/{}", Uuid::new_v4());
+            assert!(fs.write_all(&src_file, &data, false).is_ok());
+
+            // 3 MiB at 1 MiB/s
+            let dst_file = format!("/{}", Uuid::new_v4());
+            let start = Instant::now();
+            let copied =
+                HdfsUtil::copy_throttled(&fs, &src_file, &fs, &dst_file, 1024 * 1024)
+                    .ok()
+                    .unwrap();
+            let elapsed = start.elapsed();
+            assert_eq!(data.len() as u64, copied);
+            assert!(
+                elapsed >= Duration::from_millis(2700)
+                    && elapsed < Duration::from_secs(8),
+                "unexpected duration {:?}",
+                elapsed
+            );
+            assert_eq!(data, fs.read_to_vec(&dst_file).ok().unwrap());
+
+            assert!(matches!(
+                HdfsUtil::copy_throttled(&fs, &src_file, &fs, &dst_file, 0),
+                Err(HdfsErr::InvalidArgument(_))
+            ));
+
+            // Clean up
+            assert!(fs.delete(&src_file, false).is_ok());
+            assert!(fs.delete(&dst_file, false).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_copy_dir() {
+        let dfs = get_dfs();
+        {
+            let fs = dfs.get_hdfs().ok().unwrap();
+
+            let src_dir = format!("/{}", Uuid::new_v4());
+            let dirs = ["a", "a/b", "empty"];
+            let files = ["f0", "a/f1", "a/b/f2"];
+            for dir in dirs.iter() {
+                assert!(fs.mkdir(&format!("{}/{}", src_dir, dir)).is_ok());
+            }
+            for file in files.iter() {
+                let mut f = fs.create(&format!("{}/{}", src_dir, file)).ok().unwrap();
+                assert!(f.write_all(file.as_bytes()).is_ok());
+                assert!(f.close().is_ok());
+            }
+
+            // Merge into an existing directory
+            let dst_dir = format!("/{}", Uuid::new_v4());
+            let existing_file = format!("{}/existing", dst_dir);
+            assert!(fs.mkdir(&dst_dir).is_ok());
+            assert!(fs.create(&existing_file).ok().unwrap().close().is_ok());
+
+            let copied = HdfsUtil::copy_dir(&fs, &src_dir, &fs, &dst_dir)
+                .ok()
+                .unwrap();
+            let expected: usize = files.iter().map(|file| file.len()).sum();
+            assert_eq!(expected as u64, copied);
+
+            for dir in dirs.iter() {
+                let status = fs.get_file_status(&format!("{}/{}", dst_dir, dir));
+                assert!(status.ok().unwrap().is_directory());
+            }
+            for file in files.iter() {
+                let f = fs.open(&format!("{}/{}", dst_dir, file)).ok().unwrap();
+                let mut buf = vec![0u8; file.len() + 1];
+                assert_eq!(file.len(), f.pread(0, &mut buf).ok().unwrap());
+                assert_eq!(file.as_bytes(), &buf[..file.len()]);
+                assert!(f.close().is_ok());
+            }
+            assert!(fs.exist(&existing_file));
+
+            // Clean up
+            assert!(fs.delete(&src_dir, true).is_ok());
+            assert!(fs.delete(&dst_dir, true).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_diff_dirs() {
+        let temp_dir = tempdir().unwrap();
+        let local_dir = temp_dir.path().join("tree");
+        let local_dir = local_dir.to_str().unwrap();
+        let local_fs = HdfsFsBuilder::new()
+            .set_name_node("file:///")
+            .connect()
+            .ok()
+            .unwrap();
+
+        let dfs = get_dfs();
+        {
+            let fs = dfs.get_hdfs().ok().unwrap();
+            let hdfs_dir = format!("/{}", Uuid::new_v4());
+            let write_tree = |fs: &HdfsFs, root: &str, files: &[(&str, &[u8])]| {
+                for (file, data) in files {
+                    let path = format!("{}/{}", root, file);
+                    assert!(fs.mkdir(&path::parent(&path).unwrap()).is_ok());
+                    assert!(fs.write_all(&path, data, true).is_ok());
+                }
+            };
+            write_tree(
+                &fs,
+                &hdfs_dir,
+                &[
+                    ("same", b"same"),
+                    ("a/same", b"same"),
+                    ("a/longer", b"longer"),
+                    ("a/only_a", b""),
+                    ("kind", b"file"),
+                ],
+            );
+            write_tree(
+                &local_fs,
+                local_dir,
+                &[
+                    ("same", b"same"),
+                    ("a/same", b"same"),
+                    ("a/longer", b"long"),
+                    ("b/only_b", b""),
+                    ("kind/file", b"file"),
+                ],
+            );
+
+            let diff = HdfsUtil::diff_dirs(&fs, &hdfs_dir, &local_fs, local_dir)
+                .ok()
+                .unwrap();
+            assert_eq!(vec!["a/only_a"], diff.only_in_a);
+            assert_eq!(vec!["b", "b/only_b", "kind/file"], diff.only_in_b);
+            assert_eq!(vec!["a/longer", "kind"], diff.different);
+            assert!(!diff.is_empty());
+
+            // The same tree with a trailing slash
+            let diff =
+                HdfsUtil::diff_dirs(&fs, &hdfs_dir, &fs, &format!("{}/", hdfs_dir))
+                    .ok()
+                    .unwrap();
+            assert!(diff.is_empty());
+
+            // Clean up
+            assert!(fs.delete(&hdfs_dir, true).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_copy_many() {
+        let dfs = get_dfs();
+        {
+            let fs = dfs.get_hdfs().ok().unwrap();
+
+            let src_dir = format!("/{}", Uuid::new_v4());
+            let dst_dir = format!("/{}", Uuid::new_v4());
+            let paths: Vec<(String, String)> = (0..10)
+                .map(|idx| {
+                    (
+                        format!("{}/f{}", src_dir, idx),
+                        format!("{}/f{}", dst_dir, idx),
+                    )
+                })
+                .collect();
+            for (idx, (src, _)) in paths.iter().enumerate() {
+                let mut file = fs.create(src).ok().unwrap();
+                assert!(file.write_all(&vec![idx as u8; (idx + 1) * 1000]).is_ok());
+                assert!(file.close().is_ok());
+            }
+
+            let pairs: Vec<(&HdfsFs, &str, &HdfsFs, &str)> = paths
+                .iter()
+                .map(|(src, dst)| (fs.as_ref(), src.as_str(), fs.as_ref(), dst.as_str()))
+                .collect();
+            let results = HdfsUtil::copy_many(&pairs, 4);
+            assert_eq!(10, results.len());
+            for (idx, result) in results.into_iter().enumerate() {
+                assert_eq!((idx as u64 + 1) * 1000, result.ok().unwrap());
+                let dst = &paths[idx].1;
+                assert_eq!(
+                    (idx + 1) * 1000,
+                    fs.get_file_status(dst).ok().unwrap().len()
+                );
+            }
+
+            // A failed copy doesn't stop the others
+            let missing = format!("{}/missing", src_dir);
+            let (dst0, dst1) = (format!("{}/c0", dst_dir), format!("{}/c1", dst_dir));
+            let pairs = [
+                (fs.as_ref(), missing.as_str(), fs.as_ref(), dst0.as_str()),
+                (fs.as_ref(), paths[0].0.as_str(), fs.as_ref(), dst1.as_str()),
+            ];
+            let results = HdfsUtil::copy_many(&pairs, 2);
+            assert!(results[0].is_err());
+            assert_eq!(1000, *results[1].as_ref().ok().unwrap());
+
+            // Clean up
+            assert!(fs.delete(&src_dir, true).is_ok());
+            assert!(fs.delete(&dst_dir, true).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_copy_invalid_path() {
+        let dfs = get_dfs();
+        {
+            let fs = dfs.get_hdfs().ok().unwrap();
+
+            let invalid = "/invalid\0path";
+            let dst = format!("/{}", Uuid::new_v4());
+            assert!(matches!(
+                HdfsUtil::copy(&fs, invalid, &fs, &dst),
+                Err(HdfsErr::InvalidPath(_))
+            ));
+            assert!(matches!(
+                HdfsUtil::mv(&fs, invalid, &fs, &dst),
+                Err(HdfsErr::InvalidPath(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_crc32() {
+        let mut crc = Crc32::new();
+        assert_eq!(0, crc.finish());
+        crc.update(b"123456789");
+        assert_eq!(0xCBF4_3926, crc.finish());
+
+        // Updated in pieces
+        let mut crc = Crc32::new();
+        crc.update(b"1234");
+        crc.update(b"56789");
+        assert_eq!(0xCBF4_3926, crc.finish());
+    }
+
+    #[test]
+    fn test_verify_copy() {
+        let dfs = get_dfs();
+        {
+            let fs = dfs.get_hdfs().ok().unwrap();
+
+            let data: Vec<u8> = (0..(DEFAULT_COPY_CHUNK_SIZE + 4321))
+                .map(|i| (i % 239) as u8)
+                .collect();
+            let src_file = format!("/{}", Uuid::new_v4());
+            assert!(fs.write_all(&src_file, &data, false).is_ok());
+
+            let dst_file = format!("/{}", Uuid::new_v4());
+            assert!(HdfsUtil::copy(&fs, &src_file, &fs, &dst_file).is_ok());
+            assert!(HdfsUtil::verify_copy(&fs, &src_file, &fs, &dst_file)
+                .ok()
+                .unwrap());
+
+            // Corrupted with the same length
+            let mut corrupted = data.clone();
+            corrupted[DEFAULT_COPY_CHUNK_SIZE + 7] ^= 0xFF;
+            assert!(fs.write_all(&dst_file, &corrupted, true).is_ok());
+            assert!(!HdfsUtil::verify_copy(&fs, &src_file, &fs, &dst_file)
+                .ok()
+                .unwrap());
+
+            // Truncated
+            assert!(fs.write_all(&dst_file, &data[..100], true).is_ok());
+            assert!(!HdfsUtil::verify_copy(&fs, &src_file, &fs, &dst_file)
+                .ok()
+                .unwrap());
+
+            let missing = format!("/{}", Uuid::new_v4());
+            assert!(HdfsUtil::verify_copy(&fs, &src_file, &fs, &missing).is_err());
+
+            // Clean up
+            assert!(fs.delete(&src_file, false).is_ok());
+            assert!(fs.delete(&dst_file, false).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_copy_opts() {
+        let dfs = get_dfs();
+        {
+            let fs = dfs.get_hdfs().ok().unwrap();
+
+            let src_file = format!("/{}", Uuid::new_v4());
+            assert!(fs.write_all(&src_file, b"new data", false).is_ok());
+            let dst_file = format!("/{}", Uuid::new_v4());
+
+            // A missing destination
+            assert!(HdfsUtil::copy_opts(&fs, &src_file, &fs, &dst_file, false)
+                .ok()
+                .unwrap());
+            assert_eq!(
+                b"new data".to_vec(),
+                fs.read_to_vec(&dst_file).ok().unwrap()
+            );
+
+            // An existing destination isn't touched
+            assert!(fs.write_all(&dst_file, b"old", true).is_ok());
+            assert!(matches!(
+                HdfsUtil::copy_opts(&fs, &src_file, &fs, &dst_file, false),
+                Err(HdfsErr::FileAlreadyExists(_))
+            ));
+            assert_eq!(b"old".to_vec(), fs.read_to_vec(&dst_file).ok().unwrap());
+
+            assert!(HdfsUtil::copy_opts(&fs, &src_file, &fs, &dst_file, true)
+                .ok()
+                .unwrap());
+            assert_eq!(
+                b"new data".to_vec(),
+                fs.read_to_vec(&dst_file).ok().unwrap()
+            );
+
+            // Clean up
+            assert!(fs.delete(&src_file, false).is_ok());
+            assert!(fs.delete(&dst_file, false).is_ok());
+        }
+    }
 }
@@ -0,0 +1,215 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! A virtual namespace mounting multiple filesystems by the prefixes of the paths
+//!
+//! Each operation is dispatched to the filesystem mounted by the longest prefix of
+//! its fully qualified path, e.g. ``hdfs://a/data`` to the one mounted at ``hdfs://a``.
+//! The path is passed to the filesystem as it is, so the prefix of a mount should be
+//! qualified by the scheme and the authority of the filesystem.
+
+use std::collections::BTreeMap;
+
+use crate::err::HdfsErr;
+use crate::hdfs::{FileStatus, HdfsFs};
+use crate::path;
+use crate::util::HdfsUtil;
+
+/// A set of filesystems mounted by the prefixes of the paths
+#[derive(Debug, Clone, Default)]
+pub struct VirtualFs {
+    /// The normalized prefixes and the filesystems mounted there
+    mounts: BTreeMap<String, HdfsFs>,
+}
+
+impl VirtualFs {
+    pub fn new() -> VirtualFs {
+        VirtualFs::default()
+    }
+
+    /// Mount the filesystem at the prefix, e.g. ``hdfs://a`` or ``file:///``,
+    /// which fails with ``InvalidArgument`` if the prefix is mounted already.
+    pub fn mount(&mut self, prefix: &str, fs: HdfsFs) -> Result<&mut Self, HdfsErr> {
+        let prefix = path::normalize(prefix);
+        if self.mounts.contains_key(&prefix) {
+            return Err(HdfsErr::InvalidArgument(format!(
+                "{} is mounted already",
+                prefix
+            )));
+        }
+
+        self.mounts.insert(prefix, fs);
+        Ok(self)
+    }
+
+    /// Unmount the filesystem at the prefix, and return it if it's mounted
+    pub fn unmount(&mut self, prefix: &str) -> Option<HdfsFs> {
+        self.mounts.remove(&path::normalize(prefix))
+    }
+
+    /// Get the filesystem mounted by the longest prefix of the path,
+    /// or ``InvalidArgument`` if it isn't under any mount.
+    pub fn resolve(&self, path: &str) -> Result<&HdfsFs, HdfsErr> {
+        let normalized = path::normalize(path);
+        self.mounts
+            .iter()
+            .filter(|(prefix, _)| is_under(&normalized, prefix))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map(|(_, fs)| fs)
+            .ok_or_else(|| {
+                HdfsErr::InvalidArgument(format!("no filesystem mounted for {}", path))
+            })
+    }
+
+    /// Read the whole file, see ``HdfsFs::read_to_vec``
+    pub fn read_to_vec(&self, path: &str) -> Result<Vec<u8>, HdfsErr> {
+        self.resolve(path)?.read_to_vec(path)
+    }
+
+    /// Write the whole data into a file, see ``HdfsFs::write_all``
+    pub fn write_all(
+        &self,
+        path: &str,
+        data: &[u8],
+        overwrite: bool,
+    ) -> Result<(), HdfsErr> {
+        self.resolve(path)?.write_all(path, data, overwrite)
+    }
+
+    /// Checks if a given path exists, which is ``false`` if the path isn't under any mount
+    pub fn exist(&self, path: &str) -> bool {
+        self.resolve(path).is_ok_and(|fs| fs.exist(path))
+    }
+
+    /// Get the file status
+    pub fn get_file_status(&self, path: &str) -> Result<FileStatus, HdfsErr> {
+        self.resolve(path)?.get_file_status(path)
+    }
+
+    /// Get the file status for each entry under the specified directory
+    pub fn list_status(&self, path: &str) -> Result<Vec<FileStatus>, HdfsErr> {
+        self.resolve(path)?.list_status(path)
+    }
+
+    /// Create a directory and all of its missing parents
+    pub fn mkdir(&self, path: &str) -> Result<bool, HdfsErr> {
+        self.resolve(path)?.mkdir(path)
+    }
+
+    /// Delete a file or a directory, see ``HdfsFs::delete``
+    pub fn delete(&self, path: &str, recursive: bool) -> Result<bool, HdfsErr> {
+        self.resolve(path)?.delete(path, recursive)
+    }
+
+    /// Copy a file, which may cross the filesystems, see ``HdfsUtil::copy_opts``
+    pub fn copy(&self, src: &str, dst: &str, overwrite: bool) -> Result<bool, HdfsErr> {
+        HdfsUtil::copy_opts(self.resolve(src)?, src, self.resolve(dst)?, dst, overwrite)
+    }
+}
+
+/// Whether the normalized path is the prefix or under it
+fn is_under(path: &str, prefix: &str) -> bool {
+    match path.strip_prefix(prefix) {
+        Some(rest) => rest.is_empty() || prefix.ends_with('/') || rest.starts_with('/'),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use uuid::Uuid;
+
+    use super::{is_under, VirtualFs};
+    use crate::err::HdfsErr;
+    use crate::hdfs::HdfsFsBuilder;
+    use crate::minidfs::get_dfs;
+
+    #[test]
+    fn test_is_under() {
+        assert!(is_under("hdfs://a/data", "hdfs://a/"));
+        assert!(is_under("hdfs://a/", "hdfs://a/"));
+        assert!(is_under("hdfs://a/data/x", "hdfs://a/data"));
+        assert!(is_under("hdfs://a/data", "hdfs://a/data"));
+        assert!(!is_under("hdfs://a/database", "hdfs://a/data"));
+        assert!(!is_under("hdfs://ab/", "hdfs://a/"));
+        assert!(is_under("file:///tmp/x", "file:///"));
+    }
+
+    #[test]
+    fn test_virtual_fs() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let local_file = format!(
+            "file://{}",
+            temp_dir.path().join(Uuid::new_v4().to_string()).display()
+        );
+
+        let dfs = get_dfs();
+        {
+            let hdfs = dfs.get_hdfs().ok().unwrap().as_ref().clone();
+            let hdfs_file = format!("{}/{}", hdfs.url(), Uuid::new_v4());
+            let local_fs = HdfsFsBuilder::new()
+                .set_name_node("file:///")
+                .connect()
+                .ok()
+                .unwrap();
+
+            let mut vfs = VirtualFs::new();
+            vfs.mount("file:///", local_fs)
+                .ok()
+                .unwrap()
+                .mount(hdfs.url(), hdfs.clone())
+                .ok()
+                .unwrap();
+            assert!(matches!(
+                vfs.mount(hdfs.url(), hdfs.clone()),
+                Err(HdfsErr::InvalidArgument(_))
+            ));
+            assert_eq!(hdfs.url(), vfs.resolve(&hdfs_file).ok().unwrap().url());
+            assert_eq!("file:///", vfs.resolve(&local_file).ok().unwrap().url());
+            assert!(matches!(
+                vfs.resolve("hdfs://unmounted/a"),
+                Err(HdfsErr::InvalidArgument(_))
+            ));
+
+            // Copy from the local filesystem into HDFS, and back
+            assert!(vfs.write_all(&local_file, b"virtual", false).is_ok());
+            assert!(vfs.copy(&local_file, &hdfs_file, false).ok().unwrap());
+            assert_eq!(
+                b"virtual".to_vec(),
+                hdfs.read_to_vec(&hdfs_file).ok().unwrap()
+            );
+            assert!(matches!(
+                vfs.copy(&local_file, &hdfs_file, false),
+                Err(HdfsErr::FileAlreadyExists(_))
+            ));
+
+            assert!(vfs.write_all(&hdfs_file, b"updated", true).is_ok());
+            assert!(vfs.copy(&hdfs_file, &local_file, true).ok().unwrap());
+            assert_eq!(
+                b"updated".to_vec(),
+                vfs.read_to_vec(&local_file).ok().unwrap()
+            );
+
+            // Clean up
+            assert!(vfs.delete(&hdfs_file, false).ok().unwrap());
+            assert!(vfs.delete(&local_file, false).ok().unwrap());
+            assert!(!vfs.exist(&hdfs_file));
+            assert!(vfs.unmount(hdfs.url()).is_some());
+            assert!(!vfs.exist(&hdfs_file));
+        }
+    }
+}
@@ -0,0 +1,212 @@
+// Licensed to the Apache Software Foundation (ASF) under one
+// or more contributor license agreements.  See the NOTICE file
+// distributed with this work for additional information
+// regarding copyright ownership.  The ASF licenses this file
+// to you under the Apache License, Version 2.0 (the
+// "License"); you may not use this file except in compliance
+// with the License.  You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing,
+// software distributed under the License is distributed on an
+// "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.  See the License for the
+// specific language governing permissions and limitations
+// under the License.
+
+//! A buffered writer of the delimited records into HdfsFile
+
+use log::warn;
+
+use crate::err::HdfsErr;
+use crate::hdfs::HdfsFile;
+
+/// Default buffer size of HdfsRecordWriter
+pub const DEFAULT_BUF_SIZE: usize = 128 * 1024;
+
+/// Default delimiter of the records written by HdfsRecordWriter
+pub const DEFAULT_DELIMITER: &[u8] = b"\n";
+
+/// A writer of the records delimited by a delimiter, e.g. JSON lines, into a file opened
+/// for write or append.
+///
+/// The records are batched in the buffer, which is written to the file when it's full,
+/// so a record may be split across the writes to libhdfs. A record larger than the
+/// buffer is written directly. The buffer is written when the writer is dropped, but
+/// only ``finish`` reports the errors.
+pub struct HdfsRecordWriter {
+    /// The file, which is only taken by ``finish``
+    file: Option<HdfsFile>,
+    buf: Vec<u8>,
+    capacity: usize,
+    delimiter: Vec<u8>,
+}
+
+impl HdfsRecordWriter {
+    /// Create a writer with the default buffer size and the delimiter of ``\n``
+    pub fn new(file: HdfsFile) -> Result<HdfsRecordWriter, HdfsErr> {
+        HdfsRecordWriter::with_capacity(DEFAULT_BUF_SIZE, file)
+    }
+
+    /// Create a writer with the specified buffer size
+    pub fn with_capacity(
+        capacity: usize,
+        file: HdfsFile,
+    ) -> Result<HdfsRecordWriter, HdfsErr> {
+        if capacity == 0 {
+            return Err(HdfsErr::InvalidArgument(format!(
+                "zero buffer size to write {}",
+                file.path()
+            )));
+        }
+
+        Ok(HdfsRecordWriter {
+            file: Some(file),
+            buf: Vec::with_capacity(capacity),
+            capacity,
+            delimiter: DEFAULT_DELIMITER.to_vec(),
+        })
+    }
+
+    /// Set the delimiter appended to each record, which may be empty for framed records
+    pub fn set_delimiter(&mut self, delimiter: &[u8]) -> &mut Self {
+        self.delimiter = delimiter.to_vec();
+        self
+    }
+
+    /// Get the underlying file
+    #[inline]
+    pub fn get_ref(&self) -> &HdfsFile {
+        self.file.as_ref().unwrap()
+    }
+
+    /// Get the buffer size
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Write a record followed by the delimiter
+    pub fn write_record(&mut self, record: &[u8]) -> Result<(), HdfsErr> {
+        let len = record.len() + self.delimiter.len();
+        if self.buf.len() + len > self.capacity {
+            self.flush_buf()?;
+        }
+
+        if len > self.capacity {
+            let file = self.file.as_mut().unwrap();
+            file.write_chunked(record, usize::MAX)?;
+            file.write_chunked(&self.delimiter, usize::MAX)
+        } else {
+            self.buf.extend_from_slice(record);
+            self.buf.extend_from_slice(&self.delimiter);
+            Ok(())
+        }
+    }
+
+    /// Write the buffered records, and flush and close the file
+    pub fn finish(mut self) -> Result<(), HdfsErr> {
+        let flushed = self.flush_buf();
+        let file = self.file.take().unwrap();
+        if let Err(e) = flushed {
+            let _ = file.close();
+            return Err(e);
+        }
+
+        if !file.flush() {
+            let e = HdfsErr::from_errno(file.path()).logged(file.path());
+            let _ = file.close();
+            return Err(e);
+        }
+        file.close()
+    }
+
+    fn flush_buf(&mut self) -> Result<(), HdfsErr> {
+        if !self.buf.is_empty() {
+            let written = self
+                .file
+                .as_mut()
+                .unwrap()
+                .write_chunked(&self.buf, usize::MAX);
+            // The buffer is dropped on failure, as the file may have taken a part of it
+            self.buf.clear();
+            written?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for HdfsRecordWriter {
+    fn drop(&mut self) {
+        if self.file.is_some() {
+            if let Err(e) = self.flush_buf() {
+                warn!(
+                    "Fail to write the buffered records of {} when dropped: {:?}",
+                    self.get_ref().path(),
+                    e
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::{BufRead, BufReader};
+
+    use uuid::Uuid;
+
+    use super::HdfsRecordWriter;
+    use crate::err::HdfsErr;
+    use crate::minidfs::get_dfs;
+
+    #[test]
+    fn test_record_writer() {
+        let dfs = get_dfs();
+        {
+            let fs = dfs.get_hdfs().ok().unwrap();
+            let test_file = format!("/{}", Uuid::new_v4());
+            let records: Vec<String> = (0..1000)
+                .map(|i| format!("{{\"id\": {}, \"name\": \"record-{}\"}}", i, i))
+                .collect();
+
+            // A small buffer to flush many times
+            let file = fs.create(&test_file).ok().unwrap();
+            let mut writer = HdfsRecordWriter::with_capacity(4096, file).ok().unwrap();
+            for record in records.iter() {
+                assert!(writer.write_record(record.as_bytes()).is_ok());
+            }
+            assert!(writer.finish().is_ok());
+
+            let file = fs.open(&test_file).ok().unwrap();
+            let lines: Vec<String> = BufReader::new(file)
+                .lines()
+                .map(|line| line.unwrap())
+                .collect();
+            assert_eq!(records, lines);
+
+            // A custom delimiter, and a record larger than the buffer
+            let file = fs.create_with_overwrite(&test_file, true).ok().unwrap();
+            let mut writer = HdfsRecordWriter::with_capacity(8, file).ok().unwrap();
+            writer.set_delimiter(b"\x00");
+            assert!(writer.write_record(b"ab").is_ok());
+            assert!(writer.write_record(b"a large record").is_ok());
+            assert!(writer.write_record(b"").is_ok());
+            drop(writer);
+            assert_eq!(
+                b"ab\x00a large record\x00\x00".to_vec(),
+                fs.read_to_vec(&test_file).ok().unwrap()
+            );
+
+            let file = fs.create_with_overwrite(&test_file, true).ok().unwrap();
+            assert!(matches!(
+                HdfsRecordWriter::with_capacity(0, file),
+                Err(HdfsErr::InvalidArgument(_))
+            ));
+
+            // Clean up
+            assert!(fs.delete(&test_file, false).ok().unwrap());
+        }
+    }
+}